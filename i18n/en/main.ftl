@@ -0,0 +1,41 @@
+converting = Converting the pages with { $renderer }{" "}
+done = Done
+generating = Generating the final result ...{" "}
+done-generating = Done.
+interrupted = {" "}Interrupted
+writing-partial = Writing the pages converted so far ...{" "}
+pdfa-result = { $file }: { $result }
+dry-run-result = { $file }: { $report }
+uploading = Uploading the result ...{" "}
+size-report = Pages as rendered: { $before }; output: { $after }.
+batch-done = { $file }: done
+batch-failed = { $file }: { $error }
+batch-summary = { $converted } of { $total } PDFs converted, { $failed } failed.
+
+error-io = An error occurred during I/O.
+error-image = Something went wrong while processing the images.
+error-inkscape-not-found = Inkscape not found. Consider installing inkscape?
+error-pdf-invalid = Invalid PDF.
+error-sandbox-not-found = The sandbox program (bwrap or firejail) was not found.
+error-renderer-killed = Inkscape was killed, possibly for exceeding a resource limit.
+error-limit-exceeded = The PDF exceeds the configured size or page limits.
+error-time-budget-exceeded = The conversion ran out of its time budget.
+error-invalid-signing-key = Could not read the signing key or certificate.
+error-output-not-writable = The output file cannot be written.
+error-page-out-of-range = No such page.
+error-template-invalid = The template is invalid.
+error-interrupted = Interrupted.
+error-output-locked = Another conversion is writing the same output file.
+error-plugin-invalid = A library in the plugin directory is not a compatible plugin.
+error-plugin-failed = A plugin failed to render a page.
+error-script-invalid = The page script does not evaluate to a function.
+error-script-failed = The page script failed.
+error-upload-invalid = The upload URL is not a webdav://, webdavs://, http:// or https:// URL.
+error-upload-failed = The server did not accept the upload.
+error-tesseract-not-found = Tesseract not found. Consider installing tesseract?
+error-renderer-not-found = The PDF renderer was not found. Is it installed?
+error-pdf-encrypted = The PDF is encrypted. Give its password with --password and --backend pdftocairo or mutool.
+error-io-path = Could not read or write { $path }: { $cause }
+error-cause = { $error } ({ $cause })
+error-page = Page { $page }: { $error }
+error-page-timeout = Page { $page } took too long to render, and the renderer was killed.
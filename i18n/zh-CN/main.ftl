@@ -0,0 +1,41 @@
+converting = 正在用 { $renderer } 转换页面{" "}
+done = 完成
+generating = 正在生成最终结果……{" "}
+done-generating = 完成。
+interrupted = {" "}已中断
+writing-partial = 正在写入已转换的页面……{" "}
+pdfa-result = { $file }：{ $result }
+dry-run-result = { $file }：{ $report }
+uploading = 正在上传结果……{" "}
+size-report = 页面渲染后大小：{ $before }；输出文件大小：{ $after }。
+batch-done = { $file }：完成
+batch-failed = { $file }：{ $error }
+batch-summary = 共 { $total } 个 PDF，已转换 { $converted } 个，失败 { $failed } 个。
+
+error-io = 读写文件时出错。
+error-image = 处理图像时出错。
+error-inkscape-not-found = 未找到 Inkscape，请先安装 inkscape。
+error-pdf-invalid = 无效的 PDF。
+error-sandbox-not-found = 未找到沙箱程序（bwrap 或 firejail）。
+error-renderer-killed = Inkscape 被终止，可能超出了资源限制。
+error-limit-exceeded = PDF 超出了设定的大小或页数限制。
+error-time-budget-exceeded = 转换超出了时间限制。
+error-invalid-signing-key = 无法读取签名密钥或证书。
+error-output-not-writable = 无法写入输出文件。
+error-page-out-of-range = 没有这一页。
+error-template-invalid = 模板无效。
+error-interrupted = 已中断。
+error-output-locked = 另一个转换正在写入同一个输出文件。
+error-plugin-invalid = 插件目录中有不兼容的库。
+error-plugin-failed = 插件渲染页面失败。
+error-script-invalid = 页面脚本的结果不是函数。
+error-script-failed = 页面脚本运行失败。
+error-upload-invalid = 上传地址不是 webdav://、webdavs://、http:// 或 https:// 地址。
+error-upload-failed = 服务器没有接受上传。
+error-tesseract-not-found = 未找到 Tesseract，请先安装 tesseract。
+error-renderer-not-found = 未找到 PDF 渲染程序，请确认已安装。
+error-pdf-encrypted = PDF 已加密，请用 --password 提供密码，并使用 --backend pdftocairo 或 mutool。
+error-io-path = 无法读写 { $path }：{ $cause }
+error-cause = { $error }（{ $cause }）
+error-page = 第 { $page } 页：{ $error }
+error-page-timeout = 第 { $page } 页渲染超时，渲染程序已被终止。
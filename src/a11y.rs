@@ -0,0 +1,188 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! An accessibility audit of the finished package, listing what a screen
+//! reader user would miss.
+
+use crate::ooxml::{attribute, CORE_PROPERTIES, DOCUMENT};
+use crate::package::Package;
+use crate::{Error, Result};
+use std::fmt;
+
+const STYLES: &str = "word/styles.xml";
+
+/// Something to remediate before the document can be called accessible.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum A11yIssue {
+    /// The drawing with this `docPr` name has no description.
+    MissingAltText(String),
+    /// The core properties carry no `dc:title`.
+    MissingTitle,
+    /// Neither the styles nor the document declare a `w:lang`.
+    MissingLanguage,
+    /// The body has no text at all, only images.
+    ImageOnly,
+}
+
+impl fmt::Display for A11yIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            A11yIssue::MissingAltText(name) => write!(f, "image {} has no alt text", name),
+            A11yIssue::MissingTitle => write!(f, "the document has no title"),
+            A11yIssue::MissingLanguage => write!(f, "the document language is not set"),
+            A11yIssue::ImageOnly => write!(
+                f,
+                "the document contains only images; add a text layer or captions"
+            ),
+        }
+    }
+}
+
+fn part(package: &Package, name: &str) -> String {
+    package
+        .get(name)
+        .ok()
+        .and_then(|data| String::from_utf8(data).ok())
+        .unwrap_or_default()
+}
+
+/// The text of every `<w:t>` element, concatenated.
+fn text(document: &str) -> String {
+    document
+        .split("<w:t")
+        .skip(1)
+        .filter(|run| run.starts_with('>') || run.starts_with(' '))
+        .filter_map(|run| {
+            let start = run.find('>')? + 1;
+            Some(&run[start..start + run[start..].find('<')?])
+        })
+        .collect()
+}
+
+pub(crate) fn audit(package: &Package) -> Result<Vec<A11yIssue>> {
+    let document = String::from_utf8(package.get(DOCUMENT)?).map_err(Error::invalid_data)?;
+    let mut issues: Vec<_> = document
+        .split('<')
+        .filter(|tag| tag.starts_with("wp:docPr "))
+        .filter(|tag| attribute(tag, "descr").is_none_or(|descr| descr.trim().is_empty()))
+        .map(|tag| A11yIssue::MissingAltText(attribute(tag, "name").unwrap_or("").to_owned()))
+        .collect();
+    let title = text(&part(package, CORE_PROPERTIES).replace("<dc:title", "<w:t"));
+    if title.trim().is_empty() {
+        issues.push(A11yIssue::MissingTitle);
+    }
+    if !document.contains("<w:lang ") && !part(package, STYLES).contains("<w:lang ") {
+        issues.push(A11yIssue::MissingLanguage);
+    }
+    if text(&document).trim().is_empty() {
+        issues.push(A11yIssue::ImageOnly);
+    }
+    Ok(issues)
+}
+
+const CAPTION_LABELS: [&str; 11] = [
+    "Figure", "Fig.", "Table", "Chart", "Diagram", "Photo", "Image", "Plate", "Exhibit", "图", "表",
+];
+
+fn is_caption(text: &str) -> bool {
+    CAPTION_LABELS.iter().any(|label| {
+        text.strip_prefix(label).is_some_and(|rest| {
+            rest.trim_start()
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_digit())
+        })
+    })
+}
+
+/// The caption-like lines ("Figure 3: Revenue by year") in the layout text of
+/// a page, joined into an image description.
+pub(crate) fn captions(text: &str) -> Option<String> {
+    let captions: Vec<String> = text
+        .lines()
+        // Side-by-side columns are separated by runs of spaces.
+        .flat_map(|line| line.split("   "))
+        .map(|part| part.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|part| is_caption(part))
+        .collect();
+    (!captions.is_empty()).then(|| captions.join("; "))
+}
+
+/// One issue per line, or a line saying there are none.
+pub(crate) fn report(issues: &[A11yIssue]) -> String {
+    if issues.is_empty() {
+        return String::from("No accessibility issues found.\n");
+    }
+    issues.iter().map(|issue| format!("{}\n", issue)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+
+    #[test]
+    fn test_audit() {
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let mut package = Package::new(&options).unwrap();
+        package
+            .put(
+                DOCUMENT,
+                br#"<w:body><wp:docPr id="0" name="0" descr="Page 1"/><wp:docPr id="1" name="1"/><w:t xml:space="preserve"> </w:t></w:body>"#.to_vec(),
+            )
+            .unwrap();
+        let issues = audit(&package).unwrap();
+        assert_eq!(
+            issues,
+            [
+                A11yIssue::MissingAltText(String::from("1")),
+                A11yIssue::MissingTitle,
+                A11yIssue::MissingLanguage,
+                A11yIssue::ImageOnly
+            ]
+        );
+        assert!(report(&issues).starts_with("image 1 has no alt text\n"));
+
+        package
+            .put(
+                DOCUMENT,
+                br#"<w:body><w:rPr><w:lang w:val="en-GB"/></w:rPr><w:t>Caption</w:t></w:body>"#.to_vec(),
+            )
+            .unwrap();
+        package
+            .put(
+                CORE_PROPERTIES,
+                b"<cp:coreProperties><dc:title>Scan</dc:title></cp:coreProperties>".to_vec(),
+            )
+            .unwrap();
+        assert_eq!(audit(&package).unwrap(), []);
+        assert_eq!(report(&[]), "No accessibility issues found.\n");
+    }
+
+    #[test]
+    fn test_captions() {
+        let text = "Results\n\n   Figure 2:  Revenue by  year        Table 1. Costs\n\
+                    Figures are in thousands.\n图 3 年度收入\n";
+        assert_eq!(
+            captions(text).unwrap(),
+            "Figure 2: Revenue by year; Table 1. Costs; 图 3 年度收入"
+        );
+        assert_eq!(captions("Figures are in thousands.\nTable of contents"), None);
+    }
+}
@@ -0,0 +1,225 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! What converting a PDF would take, found without rendering it, so that
+//! the resolution and optimization can be chosen before a long conversion.
+
+use crate::pdf::{find, is_encrypted, is_image_only, page_count, page_sizes};
+use crate::{Backend, Docx, Error, Result};
+use std::fmt;
+use std::path::Path;
+
+/// Bytes of SVG for each byte of the PDF, SVG being the wordier of the two.
+const SVG_BYTES_PER_PDF_BYTE: f64 = 2.0;
+
+/// Bytes of PNG for each pixel of a page of black text on white.
+const PNG_BYTES_PER_PX: f64 = 0.25;
+
+/// A page of the PDF, as [`Docx::analyze_pdf`] found it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageReport {
+    /// 1-based page number.
+    pub number: usize,
+    /// Width as shown, turned by the page's rotation, in points.
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PageReport {
+    pub fn is_landscape(&self) -> bool {
+        self.width > self.height
+    }
+}
+
+/// What [`Docx::analyze_pdf`] found.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PdfReport {
+    pub page_count: Option<usize>,
+    /// Empty if the page tree is in compressed object streams.
+    pub pages: Vec<PageReport>,
+    pub encrypted: bool,
+    /// Whether the pages are images without text, as scans are. `None` if
+    /// that cannot be told from outside the compressed object streams.
+    pub scanned: Option<bool>,
+    /// A rough guess at the size of the output before deflating, `None`
+    /// without the sizes of the pages.
+    pub estimated_bytes: Option<u64>,
+    /// The backend that would render the pages, `None` for a renderer
+    /// plugin.
+    pub backend: Option<Backend>,
+}
+
+impl fmt::Display for PdfReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.page_count {
+            Some(count) => write!(f, "{} pages", count)?,
+            None => write!(f, "unknown number of pages")?,
+        }
+        if self.encrypted {
+            write!(f, ", encrypted")?;
+        }
+        match self.scanned {
+            Some(true) => write!(f, ", scanned")?,
+            Some(false) => write!(f, ", with text")?,
+            None => {}
+        }
+        let renderer = match self.backend {
+            Some(Backend::Inkscape) => "Inkscape",
+            Some(Backend::Pdftocairo) => "pdftocairo",
+            Some(Backend::Mutool) => "mutool",
+            #[cfg(feature = "pdfium")]
+            Some(Backend::Pdfium) => "PDFium",
+            None => "a plugin",
+        };
+        write!(f, ", rendered with {}", renderer)?;
+        match self.estimated_bytes {
+            Some(bytes @ 0..=999_999) => write!(f, ", about {:.1} kB", bytes as f64 / 1e3)?,
+            Some(bytes) => write!(f, ", about {:.1} MB", bytes as f64 / 1e6)?,
+            None => {}
+        }
+        for page in &self.pages {
+            let orientation = match page.is_landscape() {
+                true => "landscape",
+                false => "portrait",
+            };
+            write!(
+                f,
+                "\npage {}: {:.0} × {:.0} pt, {}",
+                page.number, page.width, page.height, orientation
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether `program` is in one of the directories of `PATH`.
+#[cfg(feature = "pdfium")]
+fn on_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path)
+        .any(|dir| dir.join(program).is_file() || dir.join(format!("{}.exe", program)).is_file())
+}
+
+impl Docx {
+    /// The backend [`Docx::convert_pdf`] would render with, `None` for a
+    /// renderer plugin.
+    fn planned_backend(&self) -> Option<Backend> {
+        #[cfg(feature = "plugins")]
+        if self.plugins.renders_pages() {
+            return None;
+        }
+        #[cfg(feature = "pdfium")]
+        if self.options.backend == Backend::Inkscape && !on_path("inkscape") {
+            return Some(Backend::Pdfium);
+        }
+        Some(self.options.backend)
+    }
+
+    /// Reports on the PDF `data` as [`Docx::analyze_pdf`] does.
+    fn analyze(&self, data: &[u8]) -> Result<PdfReport> {
+        // The header may come after up to a kilobyte of anything.
+        if find(&data[..data.len().min(1024)], b"%PDF-").is_none() {
+            return Err(Error::PDFInvalid);
+        }
+        let pages: Vec<PageReport> = page_sizes(data)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (width, height))| PageReport {
+                number: i + 1,
+                width,
+                height,
+            })
+            .collect();
+        let page_count = match pages.len() {
+            0 => page_count(data),
+            count => Some(count),
+        };
+        let estimated_bytes = (!pages.is_empty()).then(|| {
+            let dpi = self.options.raster_dpi;
+            let svg = data.len() as f64 * SVG_BYTES_PER_PDF_BYTE;
+            let png: f64 = pages
+                .iter()
+                .filter(|_| !self.options.optimization.svg_only)
+                .map(|page| {
+                    let px = page.width / 72.0 * dpi * page.height / 72.0 * dpi;
+                    let px = self
+                        .options
+                        .max_raster_px
+                        .map_or(px, |max| px.min(max as f64));
+                    px * PNG_BYTES_PER_PX
+                })
+                .sum();
+            (svg + png) as u64
+        });
+        Ok(PdfReport {
+            page_count,
+            pages,
+            encrypted: is_encrypted(data),
+            scanned: is_image_only(data),
+            estimated_bytes,
+            backend: self.planned_backend(),
+        })
+    }
+
+    /// Reads `pdf` and reports its pages, whether it is encrypted or
+    /// scanned, and how large the output of converting it with these
+    /// options might come out, without rendering anything.
+    pub fn analyze_pdf(&self, pdf: &Path) -> Result<PdfReport> {
+        let data = std::fs::read(pdf).map_err(|e| Error::from(e).at(pdf))?;
+        self.analyze(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+                    2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R] /MediaBox [0 0 720 360] >> endobj\n\
+                    3 0 obj << /Type /Page /Parent 2 0 R >> endobj\n\
+                    4 0 obj << /Type /Page /Parent 2 0 R /Rotate 90 >> endobj\n\
+                    6 0 obj << /Type /XObject /Subtype /Image >> endobj\n\
+                    trailer << /Root 1 0 R /Encrypt 9 0 R >>";
+        let mut docx = Docx::builder().raster_dpi(72.0).build().unwrap();
+        docx.options.backend = Backend::Mutool;
+        let report = docx.analyze(pdf).unwrap();
+        assert_eq!(report.page_count, Some(2));
+        assert!(report.pages[0].is_landscape());
+        assert!(!report.pages[1].is_landscape());
+        assert!(report.encrypted);
+        assert_eq!(report.scanned, Some(true));
+        let svg = pdf.len() as u64 * 2;
+        assert_eq!(report.estimated_bytes, Some(svg + 2 * 720 * 360 / 4));
+        assert_eq!(report.backend, Some(Backend::Mutool));
+        assert!(report
+            .to_string()
+            .starts_with("2 pages, encrypted, scanned, rendered with mutool, about"));
+        assert!(report
+            .to_string()
+            .ends_with("\npage 2: 360 × 720 pt, portrait"));
+
+        let docx = Docx::builder().no_png_fallback().build().unwrap();
+        let report = docx.analyze(pdf).unwrap();
+        assert_eq!(report.estimated_bytes, Some(svg));
+        assert!(matches!(docx.analyze(b"<html>"), Err(Error::PDFInvalid)));
+    }
+}
@@ -0,0 +1,214 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Converting every PDF in a directory tree.
+
+use crate::render::interrupted;
+use crate::{lock_output, prepare_output, Docx, DocxOptions, Error, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// How the conversion of one PDF of a batch went.
+#[derive(Debug)]
+pub struct BatchResult {
+    pub source: PathBuf,
+    pub output: PathBuf,
+    pub result: Result<()>,
+}
+
+/// Adds the PDFs under `dir` and its subdirectories to `pdfs`. Symbolic
+/// links to directories are not followed.
+fn find_pdfs(dir: &Path, pdfs: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = std::fs::read_dir(dir).map_err(|e| Error::from(e).at(dir))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| Error::from(e).at(dir))?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            find_pdfs(&path, pdfs)?;
+        } else if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+        {
+            pdfs.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn convert_one(source: &Path, output: &Path, options: &DocxOptions) -> Result<()> {
+    let _span = tracing::info_span!("batch", output = %output.display()).entered();
+    prepare_output(output)?;
+    let _lock = lock_output(output, false)?;
+    let mut docx = Docx::with_options(options.clone())?;
+    docx.stream_to(output)?;
+    docx.convert_pdf(source)?;
+    docx.generate_docx(output)
+}
+
+/// Converts every PDF under `input` into a DOCX at the same relative path
+/// under `output`, `jobs` PDFs at a time. A PDF that fails does not stop the
+/// others; `on_done` is called as each one finishes. The results are in the
+/// order of the sources. After an interrupt, the PDFs not started yet are
+/// left as they are, with [`Error::Interrupted`] for their result.
+pub fn convert_batch(
+    input: &Path,
+    output: &Path,
+    options: &DocxOptions,
+    jobs: usize,
+    on_done: impl Fn(&BatchResult) + Sync,
+) -> Result<Vec<BatchResult>> {
+    convert_until(input, output, options, jobs, on_done, || interrupted(None))
+}
+
+/// [`convert_batch`], starting no more PDFs once `stop` returns true.
+fn convert_until(
+    input: &Path,
+    output: &Path,
+    options: &DocxOptions,
+    jobs: usize,
+    on_done: impl Fn(&BatchResult) + Sync,
+    stop: impl Fn() -> bool + Sync,
+) -> Result<Vec<BatchResult>> {
+    let mut sources = Vec::new();
+    find_pdfs(input, &mut sources)?;
+    sources.sort();
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(sources.len()));
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(source) = sources.get(i) else {
+                    return;
+                };
+                let relative = source.strip_prefix(input).unwrap_or(source);
+                let output = output.join(relative).with_extension("docx");
+                if stop() {
+                    let result = BatchResult {
+                        source: source.clone(),
+                        output,
+                        result: Err(Error::Interrupted),
+                    };
+                    results.lock().unwrap().push((i, result));
+                    continue;
+                }
+                let result = convert_one(source, &output, options);
+                if let Err(e) = &result {
+                    tracing::warn!(source = %source.display(), error = %e, "failed");
+                }
+                let result = BatchResult {
+                    source: source.clone(),
+                    output,
+                    result,
+                };
+                on_done(&result);
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().map(|(_, result)| result).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_pdfs() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        for name in ["a/b/2.PDF", "a/1.pdf", "notes.txt", "0.pdf"] {
+            std::fs::write(dir.path().join(name), b"").unwrap();
+        }
+        let mut pdfs = Vec::new();
+        find_pdfs(dir.path(), &mut pdfs).unwrap();
+        pdfs.sort();
+        let names: Vec<_> = pdfs
+            .iter()
+            .map(|pdf| pdf.strip_prefix(dir.path()).unwrap().to_owned())
+            .collect();
+        assert_eq!(names, ["0.pdf", "a/1.pdf", "a/b/2.PDF"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn test_convert_batch_continues() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        std::fs::create_dir(input.path().join("sub")).unwrap();
+        std::fs::write(input.path().join("sub/broken.pdf"), b"").unwrap();
+        std::fs::write(input.path().join("empty.pdf"), b"").unwrap();
+        let done = AtomicUsize::new(0);
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let results = convert_batch(input.path(), output.path(), &options, 2, |_| {
+            done.fetch_add(1, Ordering::Relaxed);
+        })
+        .unwrap();
+        assert_eq!(done.into_inner(), 2);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|result| result.result.is_err()));
+        assert_eq!(results[1].output, output.path().join("sub/broken.docx"));
+        assert!(convert_batch(
+            &input.path().join("missing"),
+            output.path(),
+            &options,
+            1,
+            |_| {}
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_convert_batch_interrupted() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        for name in ["a.pdf", "b.pdf", "c.pdf"] {
+            std::fs::write(input.path().join(name), b"").unwrap();
+        }
+        let done = AtomicUsize::new(0);
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let results = convert_until(
+            input.path(),
+            output.path(),
+            &options,
+            1,
+            |_| {
+                done.fetch_add(1, Ordering::SeqCst);
+            },
+            || done.load(Ordering::SeqCst) > 0,
+        )
+        .unwrap();
+        // The PDFs not started are there all the same.
+        assert_eq!(done.into_inner(), 1);
+        assert_eq!(results.len(), 3);
+        assert!(matches!(&results[0].result, Err(e) if !matches!(e, Error::Interrupted)));
+        assert!(results[1..]
+            .iter()
+            .all(|result| matches!(result.result, Err(Error::Interrupted))));
+        assert_eq!(results[2].output, output.path().join("c.docx"));
+    }
+}
@@ -0,0 +1,221 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Pages rendered before, kept on disk by PDF and page number so that a
+//! conversion that stopped part of the way can be run again without
+//! rendering them again.
+
+use crate::image::parse_svg;
+use crate::raster::RasterPage;
+use crate::{DocxOptions, Error, Result};
+use sha2::Digest;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::warn;
+
+/// The pages of one PDF, as converted with one set of options, in a
+/// directory of [`DocxOptions::cache_dir`].
+pub(crate) struct PageCache {
+    dir: PathBuf,
+}
+
+/// What the pages rendered and rasterized depend on besides the PDF.
+fn fingerprint(options: &DocxOptions) -> String {
+    let fingerprint = format!(
+        "{:?} {} {:?} {:?} {} {} {} {:?} {:?}",
+        options.backend,
+        options.raster_dpi,
+        options.max_raster_px,
+        options.scan_cleanup,
+        options.auto_orient,
+        options.scrub_metadata,
+        options.external_images,
+        options.max_svg_bytes,
+        options.max_page_px,
+    );
+    #[cfg(feature = "plugins")]
+    let fingerprint = format!("{} {:?}", fingerprint, options.plugin_dir);
+    fingerprint
+}
+
+/// Where pages are kept when no [`DocxOptions::cache_dir`] is given: the
+/// user's own cache directory, `$XDG_CACHE_HOME/docx-you-want` or
+/// `~/.cache/docx-you-want`, rather than a shared temporary directory.
+pub fn default_cache_dir() -> PathBuf {
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir);
+    cache.join("docx-you-want")
+}
+
+/// Makes `dir`, and the directories above it that are missing, for the
+/// current user only. A directory that is there already must belong to the
+/// user and be closed to everyone else: pages planted in it would be taken
+/// for rendered ones, and pages of encrypted PDFs kept in it read.
+fn create_private_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .map_err(|e| Error::from(e).at(dir))?;
+        let metadata = std::fs::metadata(dir).map_err(|e| Error::from(e).at(dir))?;
+        // geteuid(2) always succeeds.
+        let uid = unsafe { libc::geteuid() };
+        if metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+            let message = "cache directory is not private to the current user";
+            let error = io::Error::new(io::ErrorKind::PermissionDenied, message);
+            return Err(Error::from(error).at(dir));
+        }
+    }
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(dir).map_err(|e| Error::from(e).at(dir))?;
+    Ok(())
+}
+
+impl PageCache {
+    /// The cache of the PDF `data` under `root`, made if it is not there.
+    pub(crate) fn open(root: &Path, data: &[u8], options: &DocxOptions) -> Result<PageCache> {
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(data);
+        hasher.update(fingerprint(options));
+        create_private_dir(root)?;
+        let dir = root.join(format!("{:x}", hasher.finalize()));
+        create_private_dir(&dir)?;
+        Ok(PageCache { dir })
+    }
+
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.dir.join(name)).ok()
+    }
+
+    /// Writes the file through a temporary one, so that a conversion killed
+    /// meanwhile leaves no half-written page. Failing to is not an error:
+    /// the page is rendered again next time.
+    fn write(&self, name: &str, data: &[u8]) {
+        let path = self.dir.join(name);
+        let partial = self.dir.join(format!("{}.partial", name));
+        let written =
+            std::fs::write(&partial, data).and_then(|()| std::fs::rename(&partial, &path));
+        if let Err(e) = written {
+            warn!(path = %path.display(), error = %e, "could not cache page");
+        }
+    }
+
+    /// Page `page` as the renderer made it.
+    pub(crate) fn svg(&self, page: usize) -> Option<Vec<u8>> {
+        self.read(&format!("{}.svg", page))
+    }
+
+    pub(crate) fn put_svg(&self, page: usize, svg_data: &[u8]) {
+        self.write(&format!("{}.svg", page), svg_data);
+    }
+
+    /// Page `page` as rasterized without hooks.
+    pub(crate) fn raster(&self, page: usize, options: &DocxOptions) -> Option<RasterPage> {
+        let svg_data = self.read(&format!("{}.raster.svg", page))?;
+        let png_data = self.read(&format!("{}.png", page))?;
+        let size = parse_svg(&svg_data, options).ok()?.svg_node().size;
+        Some(RasterPage {
+            svg_data,
+            png_data: Some(png_data),
+            size,
+            raster: Duration::ZERO,
+        })
+    }
+
+    /// Keeps the PNG before its SVG, which marks the page as cached. Pages
+    /// rasterized without a PNG are not kept.
+    pub(crate) fn put_raster(&self, page: usize, raster: &RasterPage) {
+        let Some(png_data) = &raster.png_data else {
+            return;
+        };
+        self.write(&format!("{}.png", page), png_data);
+        self.write(&format!("{}.raster.svg", page), &raster.svg_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_cache() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().join("cache");
+        let options = DocxOptions::default();
+        let cache = PageCache::open(&root, b"%PDF-1.7", &options).unwrap();
+        assert!(cache.svg(1).is_none());
+        let svg_data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg")).unwrap();
+        cache.put_svg(1, &svg_data);
+        assert_eq!(cache.svg(1).unwrap(), svg_data);
+        cache.put_raster(
+            1,
+            &RasterPage {
+                svg_data: svg_data.clone(),
+                png_data: Some(vec![1, 2, 3]),
+                size: usvg::Size::new(1.0, 1.0).unwrap(),
+                raster: Duration::from_secs(1),
+            },
+        );
+        let raster = cache.raster(1, &options).unwrap();
+        assert_eq!(raster.png_data.unwrap(), [1, 2, 3]);
+        assert_eq!(raster.raster, Duration::ZERO);
+
+        // Another PDF, or other options, find nothing.
+        let other = PageCache::open(&root, b"%PDF-1.4", &options).unwrap();
+        assert!(other.svg(1).is_none());
+        let options = DocxOptions {
+            raster_dpi: 300.0,
+            ..DocxOptions::default()
+        };
+        let other = PageCache::open(&root, b"%PDF-1.7", &options).unwrap();
+        assert!(other.raster(1, &options).is_none());
+        let options = DocxOptions {
+            external_images: !DocxOptions::default().external_images,
+            ..DocxOptions::default()
+        };
+        let other = PageCache::open(&root, b"%PDF-1.7", &options).unwrap();
+        assert!(other.raster(1, &options).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shared_cache_refused() {
+        use std::os::unix::fs::PermissionsExt;
+        let root = tempfile::tempdir().unwrap();
+        let shared = root.path().join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o777)).unwrap();
+        let options = DocxOptions::default();
+        assert!(matches!(
+            PageCache::open(&shared, b"%PDF-1.7", &options),
+            Err(Error::IoError { source, .. }) if source.kind() == io::ErrorKind::PermissionDenied
+        ));
+
+        // Made afresh, it is closed to others.
+        let fresh = root.path().join("a/b");
+        PageCache::open(&fresh, b"%PDF-1.7", &options).unwrap();
+        let mode = std::fs::metadata(&fresh).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+}
@@ -0,0 +1,823 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The command line, parsed into [`DocxOptions`] and what the binary itself
+//! does with them.
+
+use crate::console::Progress;
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand};
+use docx_you_want::{
+    default_cache_dir, Backend, Background, DocxOptions, FitMode, Margins, Metadata, Optimization,
+    PageBreaks, PageSelection, PageSize, Sandbox, SensitivityLabel, SourceSeparator, TextLayer,
+    Watermark, OPTIMIZED_DPI,
+};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0    success
+  1    any other error; some PDFs of a batch failed; the PDF is not PDF/A
+  2    invalid command line
+  3    the PDF is invalid, encrypted or has no such page
+  4    the renderer, sandbox or tesseract was not found
+  5    a resource limit, the time budget or the page timeout was exceeded
+  6    the output could not be written, locked or uploaded
+  130  interrupted";
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "Converts PDFs into DOCX files whose pages are vector images.",
+    after_help = EXIT_CODES,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Language of the messages, `en` or `zh-CN`.
+    #[arg(long, global = true, value_name = "LANG")]
+    pub lang: Option<String>,
+    #[command(flatten)]
+    pub convert: ConvertArgs,
+    /// What to write: a DOCX, a PPTX with a slide per page, or an ODT.
+    #[arg(long, value_parser = choice(FORMATS), default_value = "docx")]
+    pub format: Format,
+    /// Report the pages of the PDF, whether it is encrypted or scanned, the
+    /// renderer and a guess at the output size, and write nothing.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// The PDF to convert.
+    #[arg(required = true)]
+    pub pdf: Option<PathBuf>,
+    /// Where to write the DOCX, PPTX or ODT file.
+    #[arg(required_unless_present = "dry_run")]
+    pub docx: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Reports whether a PDF looks like PDF/A.
+    PdfaCheck { pdf: PathBuf },
+    /// Converts every PDF in a directory and its subdirectories.
+    Batch {
+        #[command(flatten)]
+        convert: Box<ConvertArgs>,
+        /// The directory of PDFs.
+        input: PathBuf,
+        /// Where to write the DOCX files, at the same relative paths.
+        output: PathBuf,
+    },
+    /// Converts several PDFs into one DOCX, one after the other.
+    Merge {
+        #[command(flatten)]
+        convert: Box<ConvertArgs>,
+        /// The PDFs, in order.
+        #[arg(required = true)]
+        pdfs: Vec<PathBuf>,
+        /// Where to write the DOCX file.
+        docx: PathBuf,
+    },
+    /// Makes a DOCX with a page for each PNG, JPEG or TIFF image.
+    Images {
+        #[command(flatten)]
+        convert: Box<ConvertArgs>,
+        /// The images, in page order.
+        #[arg(required = true)]
+        images: Vec<PathBuf>,
+        /// Where to write the DOCX file.
+        docx: PathBuf,
+    },
+}
+
+/// The kind of file the pages are written into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Docx,
+    Pptx,
+    Odt,
+}
+
+/// Maps the names in `choices` to their values, listing them in the help.
+fn choice<T: Clone + Send + Sync + 'static>(
+    choices: &'static [(&'static str, T)],
+) -> impl TypedValueParser<Value = T> {
+    PossibleValuesParser::new(choices.iter().map(|(name, _)| *name)).map(move |name| {
+        let (_, value) = choices.iter().find(|(choice, _)| *choice == name).unwrap();
+        value.clone()
+    })
+}
+
+const FORMATS: &[(&str, Format)] = &[
+    ("docx", Format::Docx),
+    ("pptx", Format::Pptx),
+    ("odt", Format::Odt),
+];
+
+const SANDBOXES: &[(&str, Sandbox)] = &[
+    ("none", Sandbox::None),
+    ("bwrap", Sandbox::Bubblewrap),
+    ("firejail", Sandbox::Firejail),
+];
+
+#[cfg(not(feature = "pdfium"))]
+const BACKENDS: &[(&str, Backend)] = &[
+    ("inkscape", Backend::Inkscape),
+    ("pdftocairo", Backend::Pdftocairo),
+    ("mutool", Backend::Mutool),
+];
+
+#[cfg(feature = "pdfium")]
+const BACKENDS: &[(&str, Backend)] = &[
+    ("inkscape", Backend::Inkscape),
+    ("pdftocairo", Backend::Pdftocairo),
+    ("mutool", Backend::Mutool),
+    ("pdfium", Backend::Pdfium),
+];
+
+const BACKGROUNDS: &[(&str, Background)] = &[
+    ("keep", Background::Keep),
+    ("white", Background::White(Background::DEFAULT_LEVEL)),
+    (
+        "transparent",
+        Background::Transparent(Background::DEFAULT_LEVEL),
+    ),
+];
+
+const PAGE_BREAKS: &[(&str, PageBreaks)] = &[
+    ("flow", PageBreaks::Flow),
+    ("before-each-page", PageBreaks::BeforeEachPage),
+];
+
+const FIT_MODES: &[(&str, FitMode)] = &[
+    ("actual-size", FitMode::ActualSize),
+    ("contain", FitMode::Contain),
+    ("fit-width", FitMode::FitWidth),
+];
+
+const SEPARATORS: &[(&str, SourceSeparator)] = &[
+    ("none", SourceSeparator::None),
+    ("page-break", SourceSeparator::PageBreak),
+    ("blank-page", SourceSeparator::BlankPage),
+];
+
+const TEXT_LAYERS: &[(&str, TextLayer)] = &[
+    ("none", TextLayer::None),
+    ("hidden", TextLayer::Hidden),
+    ("appendix", TextLayer::Appendix),
+];
+
+const PROGRESS: &[(&str, Progress)] = &[
+    ("bar", Progress::Bar),
+    ("dots", Progress::Dots),
+    ("plain", Progress::Plain),
+    ("none", Progress::None),
+];
+
+/// `source`, `a4`, `letter` or `WIDTHxHEIGHT` in points.
+fn page_size(size: &str) -> Result<PageSize, String> {
+    match size {
+        "source" => Ok(PageSize::Source),
+        "a4" => Ok(PageSize::A4),
+        "letter" => Ok(PageSize::Letter),
+        size => match size.split_once('x').map(|(w, h)| (w.parse(), h.parse())) {
+            Some((Ok(width), Ok(height))) if width > 0.0 && height > 0.0 => {
+                Ok(PageSize::Custom { width, height })
+            }
+            _ => Err(String::from("expected source, a4, letter or WIDTHxHEIGHT")),
+        },
+    }
+}
+
+/// `NAME=VALUE`.
+fn variable(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((name, value)) => Ok((name.to_owned(), value.to_owned())),
+        None => Err(String::from("expected NAME=VALUE")),
+    }
+}
+
+/// The options shared by converting one PDF and a batch of them.
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    /// Start from the hardened options, for untrusted PDFs.
+    #[arg(long)]
+    pub hardened: bool,
+    /// Pages to convert, like `1,3,7-9` or `5-`.
+    #[arg(long, value_name = "PAGES")]
+    pub pages: Option<PageSelection>,
+    /// Run the renderer inside this sandbox.
+    #[arg(long, value_parser = choice(SANDBOXES))]
+    pub sandbox: Option<Sandbox>,
+    /// CPU time allowed to the renderer.
+    #[arg(long, value_name = "N")]
+    pub max_cpu_seconds: Option<u64>,
+    /// Memory allowed to the renderer.
+    #[arg(long, value_name = "N")]
+    pub max_memory_mb: Option<u64>,
+    /// Largest file the renderer may write.
+    #[arg(long, value_name = "N")]
+    pub max_file_size_mb: Option<u64>,
+    /// Wall-clock time allowed to the whole conversion.
+    #[arg(long, value_name = "N")]
+    pub time_budget_seconds: Option<u64>,
+    /// Wall-clock time allowed to rendering one page, after which the
+    /// renderer is killed.
+    #[arg(long, value_name = "N")]
+    pub page_timeout_seconds: Option<u64>,
+    /// Most pixels in the PNG fallback of a page.
+    #[arg(long, value_name = "N")]
+    pub max_raster_px: Option<u64>,
+    /// Resolution of the PNG fallback.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub dpi: Option<u64>,
+    /// Deflate the DOCX at this level; 0 stores it uncompressed.
+    #[arg(long, value_name = "LEVEL", value_parser = clap::value_parser!(u32).range(0..=9))]
+    pub compression_level: Option<u32>,
+    /// Strip comments, metadata and blank space from the SVGs.
+    #[arg(long)]
+    pub minify_svg: bool,
+    /// Embed pages as just their SVG, without rasterizing a PNG fallback;
+    /// programs that cannot draw SVG show empty pages.
+    #[arg(long)]
+    pub no_png_fallback: bool,
+    /// Make the DOCX smaller: deflate it, minify the SVGs and render the PNG
+    /// fallback at 72 dpi unless --dpi is given. Reports the sizes.
+    #[arg(long)]
+    pub optimize: bool,
+    /// The program that renders the pages.
+    #[arg(long, value_parser = choice(BACKENDS))]
+    pub backend: Option<Backend>,
+    /// Pages rendered at a time; PDFs converted at a time in a batch.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub jobs: Option<u64>,
+    /// Remove specks from scanned pages.
+    #[arg(long)]
+    pub despeckle: bool,
+    /// Turn scanned pages black and white.
+    #[arg(long)]
+    pub binarize: bool,
+    /// Sharpen scanned pages.
+    #[arg(long)]
+    pub sharpen: bool,
+    /// What to do with the paper color of scanned pages.
+    #[arg(long, value_parser = choice(BACKGROUNDS))]
+    pub background: Option<Background>,
+    /// Lightness from which a pixel counts as paper, 0 to 255.
+    #[arg(long, value_name = "N", requires = "background")]
+    pub background_level: Option<u8>,
+    /// Draw a gray border around each page.
+    #[arg(long)]
+    pub page_border: bool,
+    /// Draw a shadow under each page.
+    #[arg(long)]
+    pub page_shadow: bool,
+    /// Keep the page images from being moved, resized or selected.
+    #[arg(long)]
+    pub lock_pages: bool,
+    /// Keep the pages from being edited.
+    #[arg(long)]
+    pub protect_pages: bool,
+    /// Text at the top of every page; `{page}` and `{pages}` are the page
+    /// number and count.
+    #[arg(long, value_name = "TEXT")]
+    pub header: Option<String>,
+    /// Text at the bottom of every page, like `--header`.
+    #[arg(long, value_name = "TEXT")]
+    pub footer: Option<String>,
+    /// Text across every page, faintly.
+    #[arg(long, value_name = "TEXT")]
+    pub watermark: Option<String>,
+    /// An image behind every page, faintly.
+    #[arg(long, value_name = "IMAGE", conflicts_with = "watermark")]
+    pub watermark_image: Option<PathBuf>,
+    /// How pages are kept on pages of their own.
+    #[arg(long, value_parser = choice(PAGE_BREAKS))]
+    pub page_breaks: Option<PageBreaks>,
+    /// What comes between the pages of the PDFs merged.
+    #[arg(long, value_parser = choice(SEPARATORS))]
+    pub separator: Option<SourceSeparator>,
+    /// `source`, `a4`, `letter` or `WIDTHxHEIGHT` in points.
+    #[arg(long, value_name = "SIZE", value_parser = page_size)]
+    pub page_size: Option<PageSize>,
+    /// Turn fixed page sizes sideways.
+    #[arg(long)]
+    pub landscape: bool,
+    /// Margins on all sides, in points.
+    #[arg(long, value_name = "PT")]
+    pub margins: Option<f64>,
+    /// How pages are sized on a fixed page size less its margins.
+    #[arg(long, value_parser = choice(FIT_MODES))]
+    pub fit: Option<FitMode>,
+    /// Leave out timestamps, file names and renderer metadata.
+    #[arg(long)]
+    pub scrub_metadata: bool,
+    /// The title of the document.
+    #[arg(long)]
+    pub title: Option<String>,
+    /// The author of the document.
+    #[arg(long)]
+    pub author: Option<String>,
+    /// The subject of the document.
+    #[arg(long)]
+    pub subject: Option<String>,
+    /// Keywords of the document.
+    #[arg(long)]
+    pub keywords: Option<String>,
+    /// Take the title, author and the like from the PDF where not given.
+    #[arg(long)]
+    pub pdf_metadata: bool,
+    /// Turn the bookmarks of the PDF into headings for the navigation pane.
+    #[arg(long)]
+    pub pdf_outline: bool,
+    /// Keep the links of the PDF clickable.
+    #[arg(long)]
+    pub pdf_links: bool,
+    /// Record whether the PDF looks like PDF/A in the output.
+    #[arg(long)]
+    pub record_pdfa: bool,
+    /// Start with a page of thumbnails linking to each page.
+    #[arg(long)]
+    pub thumbnail_index: bool,
+    /// Overwrite intermediate files before deleting them.
+    #[arg(long)]
+    pub secure_delete: bool,
+    /// Keep intermediate files in memory.
+    #[arg(long)]
+    pub in_memory: bool,
+    /// Write a SHA-256 checksum next to the output.
+    #[arg(long)]
+    pub checksum: bool,
+    /// Wait for another conversion writing the same output.
+    #[arg(long)]
+    pub wait_for_lock: bool,
+    /// Write the accessibility problems found to this file.
+    #[arg(long, value_name = "REPORT.txt")]
+    pub a11y_report: Option<PathBuf>,
+    /// Describe pages by the captions found on them.
+    #[arg(long)]
+    pub caption_alt_text: bool,
+    /// Where to put the text of the pages.
+    #[arg(long, value_parser = choice(TEXT_LAYERS))]
+    pub text_layer: Option<TextLayer>,
+    /// Turn sideways and upside-down pages upright.
+    #[arg(long)]
+    pub auto_orient: bool,
+    /// Recognize the text of scanned pages with Tesseract, hidden over them
+    /// unless `--text-layer` says otherwise.
+    #[arg(long)]
+    pub ocr: bool,
+    /// The Tesseract languages of the scanned pages, as in `eng+deu`.
+    /// Implies `--ocr`.
+    #[arg(long, value_name = "LANGS")]
+    pub ocr_lang: Option<String>,
+    /// Write timings and sizes of each page to this CSV file.
+    #[arg(long, value_name = "METRICS.csv")]
+    pub metrics: Option<PathBuf>,
+    /// Keep rendered pages in this directory and reuse them on later runs.
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+    /// Reuse the pages a stopped run of the same conversion rendered, kept
+    /// in the user's cache directory unless `--cache-dir` is given.
+    #[arg(long)]
+    pub resume: bool,
+    /// How to show progress.
+    #[arg(long, value_parser = choice(PROGRESS), default_value = "bar")]
+    pub progress: Progress,
+    /// Seconds between lines of `--progress plain`.
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub progress_interval: u64,
+    /// Print nothing but errors.
+    #[arg(long, short)]
+    pub quiet: bool,
+    /// Log the commands run and the time each page takes to standard error.
+    #[arg(long, short, conflicts_with = "quiet")]
+    pub verbose: bool,
+    /// On Ctrl-C, write the pages converted so far.
+    #[arg(long)]
+    pub partial_on_interrupt: bool,
+    /// Load renderer plugins from this directory.
+    #[cfg(feature = "plugins")]
+    #[arg(long, value_name = "DIR")]
+    pub plugin_dir: Option<PathBuf>,
+    /// The password of an encrypted PDF, for pdftocairo and mutool. Better
+    /// set in the environment: on the command line, other users see it in
+    /// the process list, and it stays in the shell history.
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "DYW_PDF_PASSWORD",
+        hide_env_values = true
+    )]
+    pub password: Option<String>,
+    /// Encrypt the output with `--output-password`.
+    #[arg(long, requires = "output_password")]
+    pub encrypt_output: bool,
+    /// The password Word asks for to open the output. Better set in the
+    /// environment, as with `--password`.
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "DYW_OUTPUT_PASSWORD",
+        hide_env_values = true,
+        requires = "encrypt_output"
+    )]
+    pub output_password: Option<String>,
+    /// Sign the output with this private key.
+    #[arg(long, value_name = "KEY.pem", requires = "sign_cert")]
+    pub sign_key: Option<PathBuf>,
+    /// The certificate of `--sign-key`.
+    #[arg(long, value_name = "CERT.pem", requires = "sign_key")]
+    pub sign_cert: Option<PathBuf>,
+    /// Run this Lua function on every page.
+    #[cfg(feature = "lua")]
+    #[arg(long, value_name = "SCRIPT.lua")]
+    pub page_script: Option<PathBuf>,
+    /// Upload the output to this WebDAV or HTTP URL.
+    #[cfg(feature = "upload")]
+    #[arg(long, value_name = "URL")]
+    pub upload: Option<String>,
+    /// Name of the sensitivity label to apply.
+    #[arg(long, value_name = "NAME", requires = "label_id")]
+    pub label_name: Option<String>,
+    /// GUID of the sensitivity label.
+    #[arg(long, value_name = "GUID", requires = "label_name")]
+    pub label_id: Option<String>,
+    /// GUID of the tenant of the sensitivity label.
+    #[arg(long, value_name = "GUID", requires = "label_id")]
+    pub label_site_id: Option<String>,
+    /// Use the styles and parts of this DOCX.
+    #[arg(long, value_name = "DOCX")]
+    pub template: Option<PathBuf>,
+    /// A template variable.
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = variable, requires = "template")]
+    pub vars: Vec<(String, String)>,
+}
+
+impl ConvertArgs {
+    /// The options asked for, apart from those read from files.
+    pub fn options(&self) -> DocxOptions {
+        let mut options = if self.hardened {
+            DocxOptions::hardened()
+        } else {
+            DocxOptions::default()
+        };
+        if let Some(sandbox) = self.sandbox {
+            options.sandbox = sandbox;
+        }
+        let mb = |mb: u64| mb * 1024 * 1024;
+        options.limits.cpu_seconds = self.max_cpu_seconds.or(options.limits.cpu_seconds);
+        options.limits.memory_bytes = self.max_memory_mb.map(mb).or(options.limits.memory_bytes);
+        options.limits.file_size_bytes = self
+            .max_file_size_mb
+            .map(mb)
+            .or(options.limits.file_size_bytes);
+        let seconds = Duration::from_secs;
+        options.time_budget = self
+            .time_budget_seconds
+            .map(seconds)
+            .or(options.time_budget);
+        options.page_timeout = self
+            .page_timeout_seconds
+            .map(seconds)
+            .or(options.page_timeout);
+        options.max_raster_px = self.max_raster_px.or(options.max_raster_px);
+        if self.optimize {
+            options.optimization = Optimization::smaller();
+            options.raster_dpi = options.raster_dpi.min(OPTIMIZED_DPI);
+        }
+        if let Some(dpi) = self.dpi {
+            options.raster_dpi = dpi as f64;
+        }
+        options.optimization.deflate_level = match self.compression_level {
+            Some(0) => None,
+            Some(level) => Some(level),
+            None => options.optimization.deflate_level,
+        };
+        options.optimization.minify_svg |= self.minify_svg;
+        options.optimization.svg_only |= self.no_png_fallback;
+        if let Some(backend) = self.backend {
+            options.backend = backend;
+        }
+        if let Some(jobs) = self.jobs {
+            options.jobs = jobs as usize;
+        }
+        options.scan_cleanup.despeckle |= self.despeckle;
+        options.scan_cleanup.binarize |= self.binarize;
+        options.scan_cleanup.sharpen |= self.sharpen;
+        if let Some(background) = self.background {
+            options.scan_cleanup.background = match (background, self.background_level) {
+                (Background::White(_), Some(level)) => Background::White(level),
+                (Background::Transparent(_), Some(level)) => Background::Transparent(level),
+                (background, _) => background,
+            };
+        }
+        options.page_frame.border |= self.page_border;
+        options.page_frame.shadow |= self.page_shadow;
+        let decoration = &mut options.decoration;
+        decoration.header = self.header.clone().or(decoration.header.take());
+        decoration.footer = self.footer.clone().or(decoration.footer.take());
+        if let Some(text) = &self.watermark {
+            decoration.watermark = Some(Watermark::Text(text.clone()));
+        }
+        if let Some(image) = &self.watermark_image {
+            decoration.watermark = Some(Watermark::Image(image.clone()));
+        }
+        if self.lock_pages {
+            options.page_locks.no_move = true;
+            options.page_locks.no_resize = true;
+            options.page_locks.no_select = true;
+        }
+        options.page_locks.content_locked |= self.protect_pages;
+        if let Some(page_breaks) = self.page_breaks {
+            options.page_breaks = page_breaks;
+        }
+        if let Some(separator) = self.separator {
+            options.source_separator = separator;
+        }
+        if let Some(page_size) = self.page_size {
+            options.page_size = page_size;
+        }
+        options.landscape |= self.landscape;
+        if let Some(margins) = self.margins {
+            options.margins = Margins::uniform(margins);
+        }
+        if let Some(fit) = self.fit {
+            options.fit_mode = fit;
+        }
+        options.scrub_metadata |= self.scrub_metadata;
+        let metadata = Metadata {
+            title: self.title.clone(),
+            author: self.author.clone(),
+            subject: self.subject.clone(),
+            keywords: self.keywords.clone(),
+            ..Metadata::default()
+        };
+        options.metadata = metadata.or(options.metadata);
+        options.pdf_metadata |= self.pdf_metadata;
+        options.pdf_outline |= self.pdf_outline;
+        options.pdf_links |= self.pdf_links;
+        options.record_pdfa |= self.record_pdfa;
+        options.thumbnail_index |= self.thumbnail_index;
+        options.secure_delete |= self.secure_delete;
+        options.in_memory |= self.in_memory;
+        options.checksum_sidecar |= self.checksum;
+        options.a11y_report = self.a11y_report.clone().or(options.a11y_report);
+        options.caption_alt_text |= self.caption_alt_text;
+        if let Some(text_layer) = self.text_layer {
+            options.text_layer = text_layer;
+        }
+        options.auto_orient |= self.auto_orient;
+        if self.ocr || self.ocr_lang.is_some() {
+            let mut ocr = options.ocr.take().unwrap_or_default();
+            if let Some(languages) = &self.ocr_lang {
+                ocr.languages = languages.split('+').map(String::from).collect();
+            }
+            options.ocr = Some(ocr);
+            if self.text_layer.is_none() && options.text_layer == TextLayer::None {
+                options.text_layer = TextLayer::Hidden;
+            }
+        }
+        options.metrics = self.metrics.clone().or(options.metrics);
+        options.cache_dir = self.cache_dir.clone().or(options.cache_dir);
+        if self.resume && options.cache_dir.is_none() {
+            options.cache_dir = Some(default_cache_dir());
+        }
+        #[cfg(feature = "plugins")]
+        {
+            options.plugin_dir = self.plugin_dir.clone().or(options.plugin_dir);
+        }
+        options.pdf_password = self.password.clone().or(options.pdf_password);
+        options.output_password = self.output_password.clone().or(options.output_password);
+        if let (Some(name), Some(id)) = (&self.label_name, &self.label_id) {
+            options.sensitivity_label = Some(SensitivityLabel {
+                name: name.clone(),
+                id: id.clone(),
+                site_id: self.label_site_id.clone(),
+            });
+        }
+        options.template = self.template.clone().or(options.template);
+        options.template_variables.extend(self.vars.iter().cloned());
+        options
+    }
+
+    /// The progress output asked for.
+    pub fn progress(&self) -> (Progress, Duration) {
+        let progress = if self.quiet {
+            Progress::None
+        } else {
+            self.progress
+        };
+        (progress, Duration::from_secs(self.progress_interval))
+    }
+
+    pub fn pages(&self) -> PageSelection {
+        self.pages.clone().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+    use std::path::Path;
+
+    #[test]
+    fn test_command() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_pages() {
+        let cli = Cli::try_parse_from([
+            "dyw",
+            "--pages",
+            "2-",
+            "--dpi",
+            "150",
+            "--background",
+            "white",
+            "--background-level",
+            "200",
+            "in.pdf",
+            "out.docx",
+        ])
+        .unwrap();
+        let options = cli.convert.options();
+        assert_eq!(options.raster_dpi, 150.0);
+        assert_eq!(options.scan_cleanup.background, Background::White(200));
+        assert_eq!(cli.convert.pages(), PageSelection::Range(2..=usize::MAX));
+        assert_eq!(cli.format, Format::Docx);
+    }
+
+    #[test]
+    fn test_format() {
+        let cli = Cli::try_parse_from(["dyw", "--format", "pptx", "in.pdf", "out.pptx"]).unwrap();
+        assert_eq!(cli.format, Format::Pptx);
+    }
+
+    #[test]
+    fn test_optimize() {
+        let cli = Cli::try_parse_from(["dyw", "--optimize", "--compression-level", "0", "a", "b"])
+            .unwrap();
+        let options = cli.convert.options();
+        assert_eq!(options.raster_dpi, OPTIMIZED_DPI);
+        assert_eq!(options.optimization.deflate_level, None);
+        assert!(options.optimization.minify_svg);
+        assert!(!options.optimization.svg_only);
+        let cli = Cli::try_parse_from(["dyw", "--no-png-fallback", "a", "b"]).unwrap();
+        assert!(cli.convert.options().optimization.svg_only);
+    }
+
+    #[test]
+    fn test_decoration() {
+        let cli = Cli::try_parse_from([
+            "dyw",
+            "--footer",
+            "{page}",
+            "--watermark",
+            "DRAFT",
+            "a",
+            "b",
+        ])
+        .unwrap();
+        let decoration = cli.convert.options().decoration;
+        assert_eq!(decoration.footer.as_deref(), Some("{page}"));
+        assert_eq!(
+            decoration.watermark,
+            Some(Watermark::Text(String::from("DRAFT")))
+        );
+    }
+
+    #[test]
+    fn test_ocr() {
+        let options = Cli::try_parse_from(["dyw", "--ocr-lang", "eng+deu", "a", "b"])
+            .unwrap()
+            .convert
+            .options();
+        assert_eq!(options.ocr.unwrap().languages, ["eng", "deu"]);
+        assert_eq!(options.text_layer, TextLayer::Hidden);
+        let cli =
+            Cli::try_parse_from(["dyw", "--ocr", "--text-layer", "appendix", "a", "b"]).unwrap();
+        assert_eq!(cli.convert.options().text_layer, TextLayer::Appendix);
+    }
+
+    #[test]
+    fn test_fit() {
+        let cli = Cli::try_parse_from(["dyw", "--page-size", "a4", "--fit", "contain", "a", "b"])
+            .unwrap();
+        assert_eq!(cli.convert.options().fit_mode, FitMode::Contain);
+    }
+
+    #[test]
+    fn test_timeouts() {
+        let cli = Cli::try_parse_from([
+            "dyw",
+            "--hardened",
+            "--page-timeout-seconds",
+            "30",
+            "a",
+            "b",
+        ])
+        .unwrap();
+        let options = cli.convert.options();
+        assert_eq!(options.page_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(options.time_budget, Some(Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn test_cache() {
+        let cli = Cli::try_parse_from(["dyw", "--resume", "a", "b"]).unwrap();
+        assert_eq!(cli.convert.options().cache_dir, Some(default_cache_dir()));
+        let cli = Cli::try_parse_from(["dyw", "--resume", "--cache-dir", "c", "a", "b"]).unwrap();
+        assert_eq!(
+            cli.convert.options().cache_dir.as_deref(),
+            Some(Path::new("c"))
+        );
+    }
+
+    #[test]
+    fn test_subcommands() {
+        assert!(matches!(
+            Cli::try_parse_from(["dyw", "batch", "--jobs", "4", "in", "out"]).unwrap().command,
+            Some(Command::Batch { convert, .. }) if convert.jobs == Some(4)
+        ));
+        assert!(matches!(
+            Cli::try_parse_from(["dyw", "images", "1.png", "2.jpg", "out.docx"]).unwrap().command,
+            Some(Command::Images { images, docx, .. })
+                if images.len() == 2 && docx == Path::new("out.docx")
+        ));
+        assert!(matches!(
+            Cli::try_parse_from(["dyw", "merge", "--separator", "blank-page", "a.pdf", "b.pdf", "out.docx"])
+                .unwrap()
+                .command,
+            Some(Command::Merge { convert, pdfs, docx })
+                if pdfs.len() == 2
+                    && docx == Path::new("out.docx")
+                    && convert.options().source_separator == SourceSeparator::BlankPage
+        ));
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let cli = Cli::try_parse_from(["dyw", "--dry-run", "in.pdf"]).unwrap();
+        assert!(cli.dry_run && cli.docx.is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        for invalid in [
+            &["dyw", "in.pdf"][..],
+            &["dyw", "--dry-run"],
+            &["dyw", "merge", "out.docx"],
+            &["dyw", "images", "out.docx"],
+            &["dyw", "--dpi", "0", "in.pdf", "out.docx"],
+            &["dyw", "--compression-level", "10", "in.pdf", "out.docx"],
+            &["dyw", "--encrypt-output", "in.pdf", "out.docx"],
+            &["dyw", "--page-size", "a5", "in.pdf", "out.docx"],
+            &["dyw", "--format", "pdf", "in.pdf", "out.pdf"],
+            &["dyw", "--quiet", "--verbose", "in.pdf", "out.docx"],
+            &[
+                "dyw",
+                "--watermark",
+                "A",
+                "--watermark-image",
+                "a.png",
+                "in.pdf",
+                "out.docx",
+            ],
+        ] {
+            assert!(Cli::try_parse_from(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_passwords_from_env() {
+        let command = Cli::command();
+        for (id, env) in [
+            ("password", "DYW_PDF_PASSWORD"),
+            ("output_password", "DYW_OUTPUT_PASSWORD"),
+        ] {
+            let arg = command
+                .get_arguments()
+                .find(|arg| arg.get_id() == id)
+                .unwrap();
+            assert_eq!(arg.get_env(), Some(std::ffi::OsStr::new(env)));
+        }
+    }
+}
@@ -0,0 +1,224 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! How the command line tool shows the progress of a conversion: a bar or a
+//! dot per page for terminals, or a timestamped heartbeat line every few
+//! seconds for CI logs, which are often line-buffered and kill jobs that stay
+//! silent too long.
+
+use docx_you_want::{utc_timestamp, ProgressEvent, ProgressHandler};
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The style of progress output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Progress {
+    /// A bar filling up as pages are added, when the number of pages is known
+    /// and stdout is a terminal; otherwise dots.
+    #[default]
+    Bar,
+    /// A `.` for every page added.
+    Dots,
+    /// A line like `[2024-02-29T12:34:56Z] page 120/300, 40%, eta 3m` every
+    /// few seconds.
+    Plain,
+    /// Nothing.
+    None,
+}
+
+/// Characters in a full [`Progress::Bar`].
+const BAR_WIDTH: usize = 30;
+
+fn duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", (secs + 30) / 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs % 3600 / 60),
+    }
+}
+
+/// The heartbeat line after `done` of `total` pages in `elapsed`.
+fn line(done: usize, total: Option<usize>, elapsed: Duration) -> String {
+    match total.filter(|&total| total > 0) {
+        Some(total) => {
+            let mut line = format!(
+                "page {}/{}, {}%",
+                done,
+                total,
+                (done * 100 / total).min(100)
+            );
+            if done > 0 && done < total {
+                let eta = elapsed.mul_f64((total - done) as f64 / done as f64);
+                line += &format!(", eta {}", self::duration(eta));
+            }
+            line
+        }
+        None => format!("page {}, {} elapsed", done, self::duration(elapsed)),
+    }
+}
+
+/// The bar after `done` of `total` pages.
+fn bar(done: usize, total: usize) -> String {
+    let filled = (done * BAR_WIDTH / total.max(1)).min(BAR_WIDTH);
+    format!(
+        "[{}{}] {}/{}",
+        "#".repeat(filled),
+        " ".repeat(BAR_WIDTH - filled),
+        done,
+        total
+    )
+}
+
+/// `YYYY-MM-DDThh:mm:ssZ` for now.
+fn timestamp() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    utc_timestamp(secs)
+}
+
+struct Shared {
+    style: Progress,
+    /// What the bar is drawn after, as it is redrawn from the line start.
+    prefix: String,
+    /// The pages to convert, or 0 while unknown.
+    total: AtomicUsize,
+    done: AtomicUsize,
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+/// The [`ProgressHandler`] of a [`Console`].
+struct Reporter(Arc<Shared>);
+
+impl ProgressHandler for Reporter {
+    fn event(&self, event: ProgressEvent) {
+        let shared = &self.0;
+        match event {
+            ProgressEvent::TotalPages(total) => shared.total.store(total, Ordering::Relaxed),
+            ProgressEvent::PageRendered(_) => {}
+            ProgressEvent::PageEmbedded(done) | ProgressEvent::PageSkipped(done) => {
+                shared.done.store(done, Ordering::Relaxed);
+                let total = shared.total.load(Ordering::Relaxed);
+                let mut stdout = io::stdout();
+                let _ = match shared.style {
+                    Progress::Bar if total > 0 && stdout.is_terminal() => {
+                        write!(stdout, "\r{}{} ", shared.prefix, bar(done, total))
+                    }
+                    Progress::Bar | Progress::Dots => write!(stdout, "."),
+                    Progress::Plain | Progress::None => return,
+                };
+                let _ = stdout.flush();
+            }
+        }
+    }
+}
+
+/// Shows the progress reported to its [`Console::handler`] on stdout, with a
+/// heartbeat thread for [`Progress::Plain`] that runs until it is dropped, so
+/// that a page that takes minutes to render still produces output.
+pub struct Console {
+    shared: Arc<Shared>,
+    heartbeat: Option<JoinHandle<()>>,
+}
+
+impl Console {
+    /// Starts showing progress in `style`, after `prefix` has been printed,
+    /// with heartbeat lines every `interval`.
+    pub fn start(style: Progress, prefix: &str, interval: Duration) -> Console {
+        let shared = Arc::new(Shared {
+            style,
+            prefix: prefix.to_owned(),
+            total: AtomicUsize::new(0),
+            done: AtomicUsize::new(0),
+            stopped: Mutex::new(false),
+            wake: Condvar::new(),
+        });
+        let heartbeat = (style == Progress::Plain).then(|| {
+            let shared = Arc::clone(&shared);
+            let started = Instant::now();
+            thread::spawn(move || {
+                let mut stopped = shared.stopped.lock().unwrap();
+                while !*stopped {
+                    stopped = shared.wake.wait_timeout(stopped, interval).unwrap().0;
+                    if *stopped {
+                        break;
+                    }
+                    let done = shared.done.load(Ordering::Relaxed);
+                    let total = shared.total.load(Ordering::Relaxed);
+                    let line = line(done, Some(total), started.elapsed());
+                    let _ = writeln!(io::stdout(), "[{}] {}", timestamp(), line);
+                }
+            })
+        });
+        Console { shared, heartbeat }
+    }
+
+    pub fn handler(&self) -> impl ProgressHandler {
+        Reporter(Arc::clone(&self.shared))
+    }
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        *self.shared.stopped.lock().unwrap() = true;
+        self.shared.wake.notify_all();
+        if let Some(thread) = self.heartbeat.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line() {
+        let minute = Duration::from_secs(60);
+        assert_eq!(
+            line(120, Some(300), minute * 2),
+            "page 120/300, 40%, eta 3m"
+        );
+        assert_eq!(line(0, Some(300), minute), "page 0/300, 0%");
+        assert_eq!(line(300, Some(300), minute), "page 300/300, 100%");
+        assert_eq!(line(7, None, minute * 125), "page 7, 2h05m elapsed");
+        assert_eq!(line(7, Some(0), minute), "page 7, 1m elapsed");
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(0, 3), format!("[{}] 0/3", " ".repeat(30)));
+        assert_eq!(bar(1, 3), format!("[{}{}] 1/3", "#".repeat(10), " ".repeat(20)));
+        assert_eq!(bar(3, 3), format!("[{}] 3/3", "#".repeat(30)));
+    }
+
+    #[test]
+    fn test_heartbeat_stops() {
+        let started = Instant::now();
+        let console = Console::start(Progress::Plain, "", Duration::from_secs(60));
+        console.handler().event(ProgressEvent::TotalPages(2));
+        console.handler().event(ProgressEvent::PageEmbedded(1));
+        assert_eq!(console.shared.done.load(Ordering::Relaxed), 1);
+        drop(console);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,367 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Headers, footers and watermarks repeated on every page of the document.
+
+use crate::ooxml::{escape, PT_PER_PX};
+use crate::parts::{self, Relationship, Relationships};
+use crate::picture::{Picture, PictureFormat};
+use crate::{Error, Result};
+use std::path::PathBuf;
+
+pub(crate) const HEADER: &str = "word/header1.xml";
+pub(crate) const HEADER_RELS: &str = "word/_rels/header1.xml.rels";
+pub(crate) const FOOTER: &str = "word/footer1.xml";
+
+/// Small grey text.
+const RUN_PROPERTIES: &str = r#"<w:rPr><w:color w:val="808080"/><w:sz w:val="18"/></w:rPr>"#;
+
+const NAMESPACES: &str = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:v="urn:schemas-microsoft-com:vml" xmlns:o="urn:schemas-microsoft-com:office:office" xmlns:w10="urn:schemas-microsoft-com:office:word""#;
+
+/// Text, page numbers and a watermark on every page, in Word headers and
+/// footers. The text is framed at the edge of the paper, so that it takes
+/// no room from the pages.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PageDecoration {
+    /// Centred at the top of every page. `{page}` and `{pages}` become the
+    /// page number and the page count.
+    pub header: Option<String>,
+    /// Centred at the bottom of every page, like `header`.
+    pub footer: Option<String>,
+    pub watermark: Option<Watermark>,
+}
+
+impl PageDecoration {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.header.is_none() && self.footer.is_none() && self.watermark.is_none()
+    }
+}
+
+/// Drawn faintly behind every page.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Watermark {
+    /// Grey text, diagonally across the page.
+    Text(String),
+    /// A PNG, JPEG or TIFF image, washed out and centred on the page.
+    Image(PathBuf),
+}
+
+/// The parts of a [`PageDecoration`], for paper of `paper` px.
+pub(crate) struct Decoration {
+    pub(crate) header: Option<String>,
+    pub(crate) header_rels: Option<String>,
+    pub(crate) footer: Option<String>,
+    /// The watermark image, as a part name and its data.
+    pub(crate) media: Option<(String, PictureFormat, Vec<u8>)>,
+}
+
+impl Decoration {
+    pub(crate) fn new(decoration: &PageDecoration, paper: usvg::Size) -> Result<Decoration> {
+        let mut header = decoration.header.as_deref().map(|text| framed(text, "top"));
+        let mut header_rels = None;
+        let mut media = None;
+        match &decoration.watermark {
+            Some(Watermark::Text(text)) => {
+                *header.get_or_insert_with(String::new) += &text_watermark(text, paper);
+            }
+            Some(Watermark::Image(path)) => {
+                let data = std::fs::read(path).map_err(|e| Error::from(e).at(path))?;
+                let picture = Picture::read(&data).map_err(|e| e.at(path))?;
+                let size = picture.size().ok_or_else(Error::image)?;
+                let name = format!("watermark.{}", picture.format.extension());
+                *header.get_or_insert_with(String::new) += &image_watermark(size, paper);
+                header_rels = Some(image_relationship(&name));
+                media = Some((name, picture.format, data));
+            }
+            None => {}
+        }
+        let part = |root: &str, content: String| {
+            format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?><w:{root} {}>{content}</w:{root}>",
+                NAMESPACES,
+                root = root,
+                content = content
+            )
+        };
+        Ok(Decoration {
+            header: header.map(|header| part("hdr", header)),
+            header_rels,
+            footer: decoration
+                .footer
+                .as_deref()
+                .map(|text| part("ftr", framed(text, "bottom"))),
+            media,
+        })
+    }
+
+    /// The relationships of the document to the header and footer.
+    pub(crate) fn relationships(&self) -> Vec<Relationship> {
+        let mut rels = Vec::new();
+        if self.header.is_some() {
+            rels.push(Relationship::part(
+                "rIdHeader1",
+                parts::HEADER,
+                "header1.xml",
+            ));
+        }
+        if self.footer.is_some() {
+            rels.push(Relationship::part(
+                "rIdFooter1",
+                parts::FOOTER,
+                "footer1.xml",
+            ));
+        }
+        rels
+    }
+
+    /// The references to the header and footer that start every section.
+    pub(crate) fn references(&self) -> String {
+        let mut references = String::new();
+        if self.header.is_some() {
+            references += r#"<w:headerReference w:type="default" r:id="rIdHeader1"/>"#;
+        }
+        if self.footer.is_some() {
+            references += r#"<w:footerReference w:type="default" r:id="rIdFooter1"/>"#;
+        }
+        references
+    }
+}
+
+/// Inserts `references` at the start of every section's properties in
+/// `doc`, where the schema wants them.
+pub(crate) fn reference_sections(doc: &str, references: &str) -> String {
+    let mut out = String::with_capacity(doc.len());
+    let mut rest = doc;
+    while let Some(start) = rest.find("<w:sectPr") {
+        let Some(end) = rest[start..].find('>').map(|end| start + end + 1) else {
+            break;
+        };
+        let tag = &rest[start..end];
+        out += &rest[..end];
+        // `<w:sectPrChange` is another element, and an empty one cannot
+        // hold anything.
+        if matches!(tag.as_bytes()[9], b'>' | b' ') && !tag.ends_with("/>") {
+            out += references;
+        }
+        rest = &rest[end..];
+    }
+    out += rest;
+    out
+}
+
+/// The runs of `text`, with `{page}` and `{pages}` as fields.
+fn runs(text: &str) -> String {
+    let run = |text: &str| {
+        let text = escape(text);
+        format!(
+            "<w:r>{}<w:t xml:space=\"preserve\">{}</w:t></w:r>",
+            RUN_PROPERTIES, text
+        )
+    };
+    let mut runs = String::new();
+    // Text before `literal` is done with.
+    let mut literal = 0;
+    let mut at = 0;
+    while let Some(start) = text[at..].find('{').map(|start| at + start) {
+        let (field, end) = if text[start..].starts_with("{pages}") {
+            ("NUMPAGES", start + 7)
+        } else if text[start..].starts_with("{page}") {
+            ("PAGE", start + 6)
+        } else {
+            at = start + 1;
+            continue;
+        };
+        if start > literal {
+            runs += &run(&text[literal..start]);
+        }
+        runs += &format!(
+            "<w:fldSimple w:instr=\" {} \"><w:r>{}<w:t>1</w:t></w:r></w:fldSimple>",
+            field, RUN_PROPERTIES
+        );
+        literal = end;
+        at = end;
+    }
+    if literal < text.len() {
+        runs += &run(&text[literal..]);
+    }
+    runs
+}
+
+/// A paragraph of `text`, framed across the paper at its `top` or `bottom`.
+fn framed(text: &str, align: &str) -> String {
+    let runs = runs(text);
+    format_xml::xml! {
+        <w:p>
+            <w:pPr>
+                <w:framePr w:wrap="through" w:vAnchor="page" w:hAnchor="page" w:xAlign="center" w:yAlign={align}/>
+                <w:spacing w:before="120" w:after="120"/>
+                <w:jc w:val="center"/>
+            </w:pPr>
+            {runs}
+        </w:p>
+    }
+    .to_string()
+}
+
+/// The style of a shape of `width` × `height` pt, centred on the page behind
+/// its text.
+fn centred(width: f64, height: f64) -> String {
+    format!(
+        "position:absolute;margin-left:0;margin-top:0;width:{:.1}pt;height:{:.1}pt;z-index:-251657216;mso-position-horizontal:center;mso-position-horizontal-relative:page;mso-position-vertical:center;mso-position-vertical-relative:page",
+        width, height
+    )
+}
+
+/// A paragraph holding the shape `pict`, framed in the corner of the page so
+/// that it takes no room from the pages.
+fn anchored(pict: String) -> String {
+    format_xml::xml! {
+        <w:p>
+            <w:pPr>
+                <w:framePr w:w="20" w:h="20" w:hRule="exact" w:wrap="through" w:vAnchor="page" w:hAnchor="page" w:x="0" w:y="0"/>
+                <w:spacing w:before="0" w:after="0" w:line="20" w:lineRule="exact"/>
+            </w:pPr>
+            <w:r>{pict}</w:r>
+        </w:p>
+    }
+    .to_string()
+}
+
+/// Word's WordArt watermark: `text` in silver, rising across `paper`.
+fn text_watermark(text: &str, paper: usvg::Size) -> String {
+    let text = escape(text);
+    // As long as the diagonal allows, and a quarter as tall.
+    let diagonal = paper.width().hypot(paper.height()) * PT_PER_PX * 0.7;
+    let style = format!("{};rotation:315", centred(diagonal, diagonal / 4.0));
+    anchored(format_xml::xml! {
+                <w:pict>
+                    <v:shapetype id="_x0000_t136" coordsize="21600,21600" o:spt="136" adj="10800" path="m@7,l@8,m@5,21600l@6,21600e">
+                        <v:formulas>
+                            <v:f eqn="sum #0 0 10800"/>
+                            <v:f eqn="prod #0 2 1"/>
+                            <v:f eqn="sum 21600 0 @1"/>
+                            <v:f eqn="sum 0 0 @2"/>
+                            <v:f eqn="sum 21600 0 @3"/>
+                            <v:f eqn="if @0 @3 0"/>
+                            <v:f eqn="if @0 21600 @1"/>
+                            <v:f eqn="if @0 0 @2"/>
+                            <v:f eqn="if @0 @4 21600"/>
+                            <v:f eqn="mid @5 @6"/>
+                            <v:f eqn="mid @8 @5"/>
+                            <v:f eqn="mid @7 @8"/>
+                            <v:f eqn="mid @6 @7"/>
+                            <v:f eqn="sum @6 0 @5"/>
+                        </v:formulas>
+                        <v:path textpathok="t" o:connecttype="custom" o:connectlocs="@9,0;@10,10800;@11,21600;@12,10800" o:connectangles="270,180,90,0"/>
+                        <v:textpath on="t" fitshape="t"/>
+                        <o:lock v:ext="edit" text="t" shapetype="t"/>
+                    </v:shapetype>
+                    <v:shape id="PowerPlusWaterMarkObject1" o:spid="_x0000_s2049" type="#_x0000_t136" style={style} o:allowincell="f" fillcolor="silver" stroked="f">
+                        <v:fill opacity=".5"/>
+                        <v:textpath style="font-family:&quot;Calibri&quot;;font-size:1pt" string={text}/>
+                        <w10:wrap anchorx="page" anchory="page"/>
+                    </v:shape>
+                </w:pict>
+    }
+    .to_string())
+}
+
+/// A washed-out picture of `size` px, as large as half of `paper` allows.
+fn image_watermark(size: usvg::Size, paper: usvg::Size) -> String {
+    let scale = (paper.width() / size.width())
+        .min(paper.height() / size.height())
+        .min(1.0)
+        * 0.5;
+    let (width, height) = (
+        size.width() * scale * PT_PER_PX,
+        size.height() * scale * PT_PER_PX,
+    );
+    let style = centred(width, height);
+    anchored(format_xml::xml! {
+                <w:pict>
+                    <v:shape id="WordPictureWatermark1" o:spid="_x0000_s2050" type="#_x0000_t75" style={style} o:allowincell="f">
+                        <v:imagedata r:id="rIdWatermark" o:title="" gain="19661f" blacklevel="22938f"/>
+                        <w10:wrap anchorx="page" anchory="page"/>
+                    </v:shape>
+                </w:pict>
+    }
+    .to_string())
+}
+
+fn image_relationship(name: &str) -> String {
+    let mut rels = Relationships::new();
+    rels.push(Relationship::part(
+        "rIdWatermark",
+        parts::IMAGE,
+        &format!("media/{}", name),
+    ));
+    rels.to_xml()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs() {
+        assert_eq!(runs(""), "");
+        let runs = runs("Page {page} of {pages} {x} & co");
+        assert!(runs.starts_with(&format!("<w:r>{}<w:t", RUN_PROPERTIES)));
+        assert!(runs.contains(r#">Page </w:t></w:r><w:fldSimple w:instr=" PAGE ">"#));
+        assert!(runs.contains(r#"<w:fldSimple w:instr=" NUMPAGES ">"#));
+        assert!(runs.ends_with("> {x} &amp; co</w:t></w:r>"));
+    }
+
+    #[test]
+    fn test_reference_sections() {
+        let doc = r#"<w:sectPr><w:type /></w:sectPr><w:sectPr w:rsidR="1"><w:sectPrChange /></w:sectPr><w:sectPr/>"#;
+        assert_eq!(
+            reference_sections(doc, "<R/>"),
+            r#"<w:sectPr><R/><w:type /></w:sectPr><w:sectPr w:rsidR="1"><R/><w:sectPrChange /></w:sectPr><w:sectPr/>"#
+        );
+    }
+
+    #[test]
+    fn test_decoration() {
+        let a4 = usvg::Size::new(793.707, 1122.52).unwrap();
+        let decoration = Decoration::new(
+            &PageDecoration {
+                footer: Some(String::from("{page}")),
+                watermark: Some(Watermark::Text(String::from("DRAFT"))),
+                ..PageDecoration::default()
+            },
+            a4,
+        )
+        .unwrap();
+        let header = decoration.header.as_deref().unwrap();
+        assert!(header.contains(r#"string="DRAFT""#));
+        assert!(!header.contains(r#"w:yAlign="top""#));
+        assert!(decoration.footer.unwrap().contains("<w:ftr "));
+        assert!(decoration.header_rels.is_none());
+        let decoration = Decoration::new(
+            &PageDecoration {
+                watermark: Some(Watermark::Image(PathBuf::from("missing.png"))),
+                ..PageDecoration::default()
+            },
+            a4,
+        );
+        assert!(matches!(decoration, Err(Error::IoError { .. })));
+    }
+}
@@ -0,0 +1,308 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! ECMA-376 agile encryption (MS-OFFCRYPTO 2.3.4.10), the scheme Word uses for
+//! password-protected documents: the zipped package is AES-256 encrypted and
+//! stored in a compound file next to the XML describing how to derive the key.
+
+use crate::Result;
+use aes::cipher::{block_padding::NoPadding, BlockEncryptMut, KeyIvInit};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha512};
+use std::io::{Cursor, Write};
+
+const SPIN_COUNT: u32 = 100_000;
+const SEGMENT_LENGTH: usize = 4096;
+const BLOCK_SIZE: usize = 16;
+const KEY_BYTES: usize = 32;
+
+const BLOCK_KEY_VERIFIER_INPUT: [u8; 8] = [0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79];
+const BLOCK_KEY_VERIFIER_VALUE: [u8; 8] = [0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e];
+const BLOCK_KEY_ENCRYPTED_KEY: [u8; 8] = [0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6];
+const BLOCK_KEY_HMAC_KEY: [u8; 8] = [0x5f, 0xb2, 0xad, 0x01, 0x0c, 0xb9, 0xe1, 0xf6];
+const BLOCK_KEY_HMAC_VALUE: [u8; 8] = [0xa0, 0x67, 0x7f, 0x02, 0xb2, 0x2c, 0x84, 0x33];
+
+fn sha512(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// AES-256-CBC over data that is already a multiple of the block size.
+fn aes_cbc(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut buf = data.to_vec();
+    cbc::Encryptor::<aes::Aes256>::new_from_slices(key, &iv[..BLOCK_SIZE])
+        .unwrap()
+        .encrypt_padded_mut::<NoPadding>(&mut buf, data.len())
+        .unwrap();
+    buf
+}
+
+fn pad_to_block(data: &[u8]) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.resize(data.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+    padded
+}
+
+fn spin_password(password: &str, salt: &[u8]) -> Vec<u8> {
+    let password: Vec<u8> = password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut hash = sha512(&[salt, &password]);
+    for i in 0..SPIN_COUNT {
+        hash = sha512(&[&i.to_le_bytes(), &hash]);
+    }
+    hash
+}
+
+fn derive_key(spun: &[u8], block_key: &[u8]) -> Vec<u8> {
+    let mut key = sha512(&[spun, block_key]);
+    key.truncate(KEY_BYTES);
+    key
+}
+
+fn random_bytes(n: usize) -> Vec<u8> {
+    let mut bytes = vec![0; n];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Length-prefixed UTF-16 string padded to 4 bytes, as used in the data
+/// spaces streams.
+fn unicode_lp_p4(s: &str) -> Vec<u8> {
+    let chars: Vec<u8> = s.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut out = (chars.len() as u32).to_le_bytes().to_vec();
+    out.extend(&chars);
+    out.resize(out.len().div_ceil(4) * 4, 0);
+    out
+}
+
+fn u32s(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn data_spaces_version() -> Vec<u8> {
+    let mut out = unicode_lp_p4("Microsoft.Container.DataSpaces");
+    // Reader, updater and writer versions, all 1.0.
+    out.extend(u32s(&[1, 1, 1]));
+    out
+}
+
+fn data_space_map() -> Vec<u8> {
+    let mut entry = u32s(&[1, 0]);
+    entry.extend(unicode_lp_p4("EncryptedPackage"));
+    entry.extend(unicode_lp_p4("StrongEncryptionDataSpace"));
+    let mut out = u32s(&[8, 1, entry.len() as u32 + 4]);
+    out.extend(entry);
+    out
+}
+
+fn data_space_definition() -> Vec<u8> {
+    let mut out = u32s(&[8, 1]);
+    out.extend(unicode_lp_p4("StrongEncryptionTransform"));
+    out
+}
+
+fn transform_primary() -> Vec<u8> {
+    let id = unicode_lp_p4("{FF9A3F03-56EF-4613-BDD5-5A41C1D07246}");
+    let mut out = u32s(&[8 + id.len() as u32, 1]);
+    out.extend(id);
+    out.extend(unicode_lp_p4("Microsoft.Container.EncryptionTransform"));
+    out.extend(u32s(&[1, 1, 1]));
+    // Empty encryption name, block size, cipher mode and the reserved 4.
+    out.extend(u32s(&[0, 0, 0, 4]));
+    out
+}
+
+struct Encrypted {
+    info: Vec<u8>,
+    package: Vec<u8>,
+}
+
+fn encrypt_streams(package: &[u8], password: &str) -> Encrypted {
+    let key_salt = random_bytes(BLOCK_SIZE);
+    let password_salt = random_bytes(BLOCK_SIZE);
+    let secret_key = random_bytes(KEY_BYTES);
+    let verifier = random_bytes(BLOCK_SIZE);
+    let hmac_key = random_bytes(64);
+
+    let mut encrypted_package = (package.len() as u64).to_le_bytes().to_vec();
+    for (i, segment) in package.chunks(SEGMENT_LENGTH).enumerate() {
+        let iv = sha512(&[&key_salt, &(i as u32).to_le_bytes()]);
+        encrypted_package.extend(aes_cbc(&secret_key, &iv, &pad_to_block(segment)));
+    }
+
+    let mut mac = Hmac::<Sha512>::new_from_slice(&hmac_key).unwrap();
+    mac.update(&encrypted_package);
+    let hmac_value = mac.finalize().into_bytes();
+    let encrypted_hmac_key = aes_cbc(
+        &secret_key,
+        &sha512(&[&key_salt, &BLOCK_KEY_HMAC_KEY]),
+        &hmac_key,
+    );
+    let encrypted_hmac_value = aes_cbc(
+        &secret_key,
+        &sha512(&[&key_salt, &BLOCK_KEY_HMAC_VALUE]),
+        &hmac_value,
+    );
+
+    let spun = spin_password(password, &password_salt);
+    let encrypted_verifier_input = aes_cbc(
+        &derive_key(&spun, &BLOCK_KEY_VERIFIER_INPUT),
+        &password_salt,
+        &verifier,
+    );
+    let encrypted_verifier_value = aes_cbc(
+        &derive_key(&spun, &BLOCK_KEY_VERIFIER_VALUE),
+        &password_salt,
+        &sha512(&[&verifier]),
+    );
+    let encrypted_key = aes_cbc(
+        &derive_key(&spun, &BLOCK_KEY_ENCRYPTED_KEY),
+        &password_salt,
+        &secret_key,
+    );
+
+    let key_salt = BASE64.encode(key_salt);
+    let password_salt = BASE64.encode(password_salt);
+    let encrypted_hmac_key = BASE64.encode(encrypted_hmac_key);
+    let encrypted_hmac_value = BASE64.encode(encrypted_hmac_value);
+    let encrypted_verifier_input = BASE64.encode(encrypted_verifier_input);
+    let encrypted_verifier_value = BASE64.encode(encrypted_verifier_value);
+    let encrypted_key = BASE64.encode(encrypted_key);
+    let xml = format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <encryption xmlns="http://schemas.microsoft.com/office/2006/encryption" xmlns:p="http://schemas.microsoft.com/office/2006/keyEncryptor/password" xmlns:c="http://schemas.microsoft.com/office/2006/keyEncryptor/certificate">
+            <keyData saltSize="16" blockSize="16" keyBits="256" hashSize="64" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512" saltValue={key_salt}/>
+            <dataIntegrity encryptedHmacKey={encrypted_hmac_key} encryptedHmacValue={encrypted_hmac_value}/>
+            <keyEncryptors>
+                <keyEncryptor uri="http://schemas.microsoft.com/office/2006/keyEncryptor/password">
+                    <p:encryptedKey spinCount={SPIN_COUNT} saltSize="16" blockSize="16" keyBits="256" hashSize="64" cipherAlgorithm="AES" cipherChaining="ChainingModeCBC" hashAlgorithm="SHA512" saltValue={password_salt} encryptedVerifierHashInput={encrypted_verifier_input} encryptedVerifierHashValue={encrypted_verifier_value} encryptedKeyValue={encrypted_key}/>
+                </keyEncryptor>
+            </keyEncryptors>
+        </encryption>
+    }
+    .to_string();
+    // Version 4.4 with the agile flag.
+    let mut info = vec![4, 0, 4, 0, 0x40, 0, 0, 0];
+    info.extend(xml.into_bytes());
+    Encrypted {
+        info,
+        package: encrypted_package,
+    }
+}
+
+/// Encrypts a zipped DOCX with a password, returning the compound file Word
+/// opens after prompting for it.
+pub(crate) fn encrypt(package: &[u8], password: &str) -> Result<Vec<u8>> {
+    let encrypted = encrypt_streams(package, password);
+    let mut cfb = cfb::CompoundFile::create_with_version(cfb::Version::V3, Cursor::new(Vec::new()))?;
+    let streams: [(&str, Vec<u8>); 6] = [
+        ("/EncryptionInfo", encrypted.info),
+        ("/EncryptedPackage", encrypted.package),
+        ("/\u{6}DataSpaces/Version", data_spaces_version()),
+        ("/\u{6}DataSpaces/DataSpaceMap", data_space_map()),
+        (
+            "/\u{6}DataSpaces/DataSpaceInfo/StrongEncryptionDataSpace",
+            data_space_definition(),
+        ),
+        (
+            "/\u{6}DataSpaces/TransformInfo/StrongEncryptionTransform/\u{6}Primary",
+            transform_primary(),
+        ),
+    ];
+    for (path, data) in streams {
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            cfb.create_storage_all(parent)?;
+        }
+        cfb.create_stream(path)?.write_all(&data)?;
+    }
+    cfb.flush()?;
+    Ok(cfb.into_inner().into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockDecryptMut;
+
+    fn aes_cbc_decrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut buf = data.to_vec();
+        cbc::Decryptor::<aes::Aes256>::new_from_slices(key, &iv[..BLOCK_SIZE])
+            .unwrap()
+            .decrypt_padded_mut::<NoPadding>(&mut buf)
+            .unwrap();
+        buf
+    }
+
+    fn attribute(xml: &str, name: &str) -> Vec<u8> {
+        let start = xml.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+        let end = start + xml[start..].find('"').unwrap();
+        BASE64.decode(&xml[start..end]).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let package: Vec<u8> = (0..10000u32).map(|i| (i % 251) as u8).collect();
+        let encrypted = encrypt_streams(&package, "pässword");
+        let xml = std::str::from_utf8(&encrypted.info[8..]).unwrap();
+        let encryptor = &xml[xml.find("<p:encryptedKey").unwrap()..];
+        let spun = spin_password("pässword", &attribute(encryptor, "saltValue"));
+        let salt = attribute(encryptor, "saltValue");
+        let verifier = aes_cbc_decrypt(
+            &derive_key(&spun, &BLOCK_KEY_VERIFIER_INPUT),
+            &salt,
+            &attribute(encryptor, "encryptedVerifierHashInput"),
+        );
+        let verifier_hash = aes_cbc_decrypt(
+            &derive_key(&spun, &BLOCK_KEY_VERIFIER_VALUE),
+            &salt,
+            &attribute(encryptor, "encryptedVerifierHashValue"),
+        );
+        assert_eq!(sha512(&[&verifier]), verifier_hash);
+        let secret_key = aes_cbc_decrypt(
+            &derive_key(&spun, &BLOCK_KEY_ENCRYPTED_KEY),
+            &salt,
+            &attribute(encryptor, "encryptedKeyValue"),
+        );
+        let key_salt = attribute(xml, "saltValue");
+        let size = u64::from_le_bytes(encrypted.package[..8].try_into().unwrap());
+        let mut decrypted = Vec::new();
+        for (i, segment) in encrypted.package[8..].chunks(SEGMENT_LENGTH).enumerate() {
+            let iv = sha512(&[&key_salt, &(i as u32).to_le_bytes()]);
+            decrypted.extend(aes_cbc_decrypt(&secret_key, &iv, segment));
+        }
+        decrypted.truncate(size as usize);
+        assert_eq!(decrypted, package);
+    }
+
+    #[test]
+    fn test_compound_file() {
+        let encrypted = encrypt(b"PK", "password").unwrap();
+        let mut cfb = cfb::CompoundFile::open(Cursor::new(encrypted)).unwrap();
+        for path in [
+            "/EncryptionInfo",
+            "/EncryptedPackage",
+            "/\u{6}DataSpaces/DataSpaceMap",
+            "/\u{6}DataSpaces/TransformInfo/StrongEncryptionTransform/\u{6}Primary",
+        ] {
+            assert!(cfb.open_stream(path).is_ok(), "{}", path);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The messages of the command line tool, in Fluent files under `i18n/`.
+
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+/// Language tags and their messages, the first one being the fallback.
+const LOCALES: [(&str, &str); 2] = [
+    ("en", include_str!("../i18n/en/main.ftl")),
+    ("zh-CN", include_str!("../i18n/zh-CN/main.ftl")),
+];
+
+static BUNDLE: OnceLock<FluentBundle<FluentResource>> = OnceLock::new();
+
+fn bundle(tag: &str, source: &str) -> FluentBundle<FluentResource> {
+    let id: LanguageIdentifier = tag.parse().expect("invalid language tag");
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // Unicode isolation marks show up as garbage in many terminals.
+    bundle.set_use_isolating(false);
+    let resource = FluentResource::try_new(source.to_owned()).expect("invalid Fluent file");
+    bundle.add_resource(resource).expect("duplicate message");
+    bundle
+}
+
+/// The locale matching `lang`, or the one from the environment.
+fn choose(lang: Option<&str>) -> (&'static str, &'static str) {
+    let requested = lang.map(str::to_owned).or_else(|| {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+    });
+    let language = |tag: &str| tag.split(['-', '_', '.']).next().unwrap_or("").to_owned();
+    requested
+        .and_then(|requested| {
+            LOCALES
+                .into_iter()
+                .find(|(tag, _)| language(tag) == language(&requested))
+        })
+        .unwrap_or(LOCALES[0])
+}
+
+/// Picks the language of the messages. Without `lang`, follows
+/// `LC_ALL`, `LC_MESSAGES` or `LANG`, falling back to English.
+pub fn init(lang: Option<&str>) {
+    let (tag, source) = choose(lang);
+    let _ = BUNDLE.set(bundle(tag, source));
+}
+
+/// The message `id` with the named arguments filled in.
+pub fn message(id: &str, args: &[(&str, &str)]) -> String {
+    let bundle = BUNDLE.get_or_init(|| bundle(LOCALES[0].0, LOCALES[0].1));
+    let pattern = match bundle.get_message(id).and_then(|message| message.value()) {
+        Some(pattern) => pattern,
+        None => return id.to_owned(),
+    };
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, *value);
+    }
+    let mut errors = Vec::new();
+    bundle
+        .format_pattern(pattern, Some(&fluent_args), &mut errors)
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[test]
+    fn test_locales() {
+        for (tag, source) in LOCALES {
+            bundle(tag, source);
+            assert_eq!(ids(source), ids(LOCALES[0].1), "{} differs from en", tag);
+        }
+        assert_eq!(choose(Some("zh_CN.UTF-8")).0, "zh-CN");
+        assert_eq!(choose(Some("zh")).0, "zh-CN");
+        assert_eq!(choose(Some("fr-FR")).0, "en");
+    }
+
+    #[test]
+    fn test_message() {
+        let bundle = bundle(LOCALES[1].0, LOCALES[1].1);
+        let message = bundle.get_message("batch-failed").unwrap();
+        let mut args = FluentArgs::new();
+        args.set("file", "a.pdf");
+        args.set("error", "无效的 PDF。");
+        let mut errors = Vec::new();
+        assert_eq!(
+            bundle.format_pattern(message.value().unwrap(), Some(&args), &mut errors),
+            "a.pdf：无效的 PDF。"
+        );
+        assert!(errors.is_empty());
+    }
+}
@@ -0,0 +1,522 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Parsing the rendered SVG and producing the PNG fallback of a page.
+
+use crate::pdf::find;
+use crate::{DocxOptions, Error, Result};
+
+pub(crate) fn parse_svg(svg_data: &[u8], options: &DocxOptions) -> Result<usvg::Tree> {
+    if options.max_svg_bytes.is_some_and(|max| svg_data.len() as u64 > max) {
+        return Err(Error::LimitExceeded);
+    }
+    let mut opt = usvg::Options::default();
+    if !options.external_images {
+        opt.image_href_resolver.resolve_string = Box::new(|_, _| None);
+    }
+    let tree = usvg::Tree::from_data(svg_data, &opt.to_ref())?;
+    if let Some(max) = options.max_page_px {
+        let size = tree.svg_node().size;
+        if size.width() > max || size.height() > max {
+            return Err(Error::LimitExceeded);
+        }
+    }
+    Ok(tree)
+}
+
+/// Scales a page of `size` px to `dpi`, then down to at most `max_px` pixels.
+fn raster_fit(size: usvg::ScreenSize, dpi: f64, max_px: Option<u64>) -> usvg::FitTo {
+    let mut zoom = dpi / 96.0;
+    let pixels = size.width() as f64 * size.height() as f64 * zoom * zoom;
+    if let Some(max) = max_px {
+        if pixels > max as f64 {
+            zoom *= (max as f64 / pixels).sqrt();
+        }
+    }
+    if (zoom - 1.0).abs() < f64::EPSILON {
+        usvg::FitTo::Original
+    } else {
+        usvg::FitTo::Zoom(zoom as f32)
+    }
+}
+
+/// Most pixels of a pixmap, a gigabyte of RGBA, so that a giant page that
+/// [`DocxOptions::max_raster_px`] does not scale down fails instead of
+/// exhausting memory.
+const MAX_PIXMAP_PX: u64 = 1 << 28;
+
+/// A transparent pixmap of the size the page is rasterized at.
+pub(crate) fn blank_pixmap(rtree: &usvg::Tree, options: &DocxOptions) -> Result<tiny_skia::Pixmap> {
+    let screen_size = rtree.svg_node().size.to_screen_size();
+    let size = raster_fit(screen_size, options.raster_dpi, options.max_raster_px)
+        .fit_to(screen_size)
+        .ok_or_else(Error::image)?;
+    if size.width() as u64 * size.height() as u64 > MAX_PIXMAP_PX {
+        return Err(Error::LimitExceeded);
+    }
+    tiny_skia::Pixmap::new(size.width(), size.height()).ok_or_else(Error::image)
+}
+
+pub(crate) fn render_png(rtree: &usvg::Tree, options: &DocxOptions) -> Result<tiny_skia::Pixmap> {
+    let fit = raster_fit(
+        rtree.svg_node().size.to_screen_size(),
+        options.raster_dpi,
+        options.max_raster_px,
+    );
+    let mut pixmap = blank_pixmap(rtree, options)?;
+    resvg::render(rtree, fit, tiny_skia::Transform::identity(), pixmap.as_mut())
+        .ok_or_else(Error::image)?;
+    options.scan_cleanup.apply(&mut pixmap);
+    Ok(pixmap)
+}
+
+/// Turns the page clockwise by `degrees`, a multiple of 90, by wrapping its
+/// content in a rotated group.
+pub(crate) fn rotate(rtree: &mut usvg::Tree, degrees: u32) -> Result<()> {
+    let mut root = rtree.root();
+    let mut svg = *rtree.svg_node();
+    let (x, y) = (svg.view_box.rect.x(), svg.view_box.rect.y());
+    let (w, h) = (svg.view_box.rect.width(), svg.view_box.rect.height());
+    let (transform, view_box, size) = match degrees % 360 {
+        0 => return Ok(()),
+        90 => (
+            usvg::Transform::new(0.0, 1.0, -1.0, 0.0, h + y, -x),
+            (h, w),
+            (svg.size.height(), svg.size.width()),
+        ),
+        180 => (
+            usvg::Transform::new(-1.0, 0.0, 0.0, -1.0, w + x, h + y),
+            (w, h),
+            (svg.size.width(), svg.size.height()),
+        ),
+        270 => (
+            usvg::Transform::new(0.0, -1.0, 1.0, 0.0, -y, w + x),
+            (h, w),
+            (svg.size.height(), svg.size.width()),
+        ),
+        _ => return Err(Error::image()),
+    };
+    svg.view_box.rect =
+        usvg::Rect::new(0.0, 0.0, view_box.0, view_box.1).ok_or_else(Error::image)?;
+    svg.size = usvg::Size::new(size.0, size.1).ok_or_else(Error::image)?;
+    *root.borrow_mut() = usvg::NodeKind::Svg(svg);
+    let mut group = usvg::Node::new(usvg::NodeKind::Group(usvg::Group {
+        transform,
+        ..usvg::Group::default()
+    }));
+    // The first child is the defs, which stay where they are.
+    let content: Vec<_> = root.children().skip(1).collect();
+    for mut node in content {
+        node.detach();
+        group.append(node);
+    }
+    root.append(group);
+    Ok(())
+}
+
+/// Filters for scanned pages. Enabling any of them embeds the page as PNG
+/// only, since the SVG would still show the raw scan. All but `background`
+/// turn the page grayscale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ScanCleanup {
+    pub despeckle: bool,
+    pub binarize: bool,
+    pub sharpen: bool,
+    pub background: Background,
+}
+
+/// What to do with the off-white paper of a scan or photographed page. The
+/// level is the lightness, in every channel, from which a pixel is paper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Background {
+    #[default]
+    Keep,
+    /// Stretch the levels so that the paper becomes pure white, which also
+    /// makes the PNG much smaller.
+    White(u8),
+    /// Like `White`, then make the paper transparent.
+    Transparent(u8),
+}
+
+impl Background {
+    /// A level that clears the shadows and yellowing of most scans but
+    /// keeps light colors.
+    pub const DEFAULT_LEVEL: u8 = 230;
+
+    fn apply(self, pixmap: &mut tiny_skia::Pixmap) {
+        let (level, transparent) = match self {
+            Background::Keep => return,
+            Background::White(level) => (level.max(1) as u32, false),
+            Background::Transparent(level) => (level.max(1) as u32, true),
+        };
+        for pixel in pixmap.data_mut().chunks_exact_mut(4) {
+            // Leave what is already see-through, and the edges of it, alone.
+            if pixel[3] != 255 {
+                continue;
+            }
+            for channel in &mut pixel[..3] {
+                *channel = (*channel as u32 * 255 / level).min(255) as u8;
+            }
+            if transparent && pixel[..3] == [255, 255, 255] {
+                pixel.copy_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+    }
+}
+
+impl ScanCleanup {
+    pub(crate) fn is_enabled(self) -> bool {
+        self.is_grayscale() || self.background != Background::Keep
+    }
+
+    fn is_grayscale(self) -> bool {
+        self.despeckle || self.binarize || self.sharpen
+    }
+
+    pub(crate) fn apply(self, pixmap: &mut tiny_skia::Pixmap) {
+        if self.is_grayscale() {
+            self.apply_grayscale(pixmap);
+        }
+        self.background.apply(pixmap);
+    }
+
+    fn apply_grayscale(self, pixmap: &mut tiny_skia::Pixmap) {
+        let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+        let mut gray = to_gray(pixmap);
+        if self.despeckle {
+            gray = despeckle(&gray, width, height);
+        }
+        if self.sharpen {
+            gray = sharpen(&gray, width, height);
+        }
+        if self.binarize {
+            gray = binarize(&gray, width, height);
+        }
+        for (pixel, &v) in pixmap.data_mut().chunks_exact_mut(4).zip(gray.iter()) {
+            pixel.copy_from_slice(&[v, v, v, 255]);
+        }
+    }
+}
+
+fn to_gray(pixmap: &tiny_skia::Pixmap) -> Vec<u8> {
+    pixmap
+        .data()
+        .chunks_exact(4)
+        .map(|p| {
+            // Premultiplied, so compositing over white adds the missing coverage.
+            let white = 255 - p[3] as u32;
+            let luma = (299 * p[0] as u32 + 587 * p[1] as u32 + 114 * p[2] as u32) / 1000;
+            (luma + white).min(255) as u8
+        })
+        .collect()
+}
+
+fn neighbourhood(gray: &[u8], width: usize, height: usize, x: usize, y: usize) -> [u8; 9] {
+    let mut n = [0; 9];
+    for (i, (dx, dy)) in (0..3).flat_map(|dy| (0..3).map(move |dx| (dx, dy))).enumerate() {
+        let nx = (x + dx).saturating_sub(1).min(width - 1);
+        let ny = (y + dy).saturating_sub(1).min(height - 1);
+        n[i] = gray[ny * width + nx];
+    }
+    n
+}
+
+fn despeckle(gray: &[u8], width: usize, height: usize) -> Vec<u8> {
+    (0..gray.len())
+        .map(|i| {
+            let mut n = neighbourhood(gray, width, height, i % width, i / width);
+            n.sort_unstable();
+            n[4]
+        })
+        .collect()
+}
+
+fn sharpen(gray: &[u8], width: usize, height: usize) -> Vec<u8> {
+    (0..gray.len())
+        .map(|i| {
+            let n = neighbourhood(gray, width, height, i % width, i / width);
+            let blur = n.iter().map(|&v| v as i32).sum::<i32>() / 9;
+            let v = gray[i] as i32;
+            (v + (v - blur) / 2).clamp(0, 255) as u8
+        })
+        .collect()
+}
+
+/// Bradley's adaptive thresholding: a pixel turns black when it is noticeably
+/// darker than the mean of the window around it.
+fn binarize(gray: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut integral = vec![0u64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        let mut row = 0u64;
+        for x in 0..width {
+            row += gray[y * width + x] as u64;
+            integral[(y + 1) * (width + 1) + x + 1] = integral[y * (width + 1) + x + 1] + row;
+        }
+    }
+    let half = (width.max(height) / 32).max(1);
+    (0..gray.len())
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let (x0, y0) = (x.saturating_sub(half), y.saturating_sub(half));
+            let (x1, y1) = ((x + half + 1).min(width), (y + half + 1).min(height));
+            let count = ((x1 - x0) * (y1 - y0)) as u64;
+            let sum = integral[y1 * (width + 1) + x1] + integral[y0 * (width + 1) + x0]
+                - integral[y0 * (width + 1) + x1]
+                - integral[y1 * (width + 1) + x0];
+            if gray[i] as u64 * count * 100 < sum * 85 {
+                0
+            } else {
+                255
+            }
+        })
+        .collect()
+}
+
+/// Elements that do not change how the SVG looks.
+const UNSEEN_ELEMENTS: [&[u8]; 2] = [b"metadata", b"sodipodi:namedview"];
+
+/// Whether `data` starts with the start or end tag `tag`, as `<text` or
+/// `</text`.
+fn is_tag(data: &[u8], tag: &[u8]) -> bool {
+    data.strip_prefix(tag).is_some_and(|rest| {
+        rest.first()
+            .is_some_and(|&b| b.is_ascii_whitespace() || b == b'>' || b == b'/')
+    })
+}
+
+/// Where the tag at the start of `data` ends, past its `>`.
+fn tag_end(data: &[u8]) -> usize {
+    let mut quote = None;
+    for (i, &b) in data.iter().enumerate() {
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            None if b == b'>' => return i + 1,
+            None => {}
+        }
+    }
+    data.len()
+}
+
+/// The SVG without comments, metadata and blank space between tags, except
+/// inside `<text>`, where the space is part of the text.
+pub(crate) fn minify_svg(data: &[u8]) -> Vec<u8> {
+    let mut svg = Vec::with_capacity(data.len());
+    let mut at = 0;
+    let mut text_depth = 0usize;
+    while at < data.len() {
+        let rest = &data[at..];
+        let unseen = UNSEEN_ELEMENTS
+            .iter()
+            .find(|name| rest.starts_with(b"<") && is_tag(&rest[1..], name));
+        if rest.starts_with(b"<!--") {
+            at += find(rest, b"-->").map_or(rest.len(), |end| end + 3);
+        } else if rest.starts_with(b"<![CDATA[") {
+            let end = find(rest, b"]]>").map_or(rest.len(), |end| end + 3);
+            svg.extend_from_slice(&rest[..end]);
+            at += end;
+        } else if let Some(name) = unseen {
+            let start = tag_end(rest);
+            let end = if rest[..start].ends_with(b"/>") {
+                start
+            } else {
+                let close = [b"</", *name, b">"].concat();
+                find(rest, &close).map_or(rest.len(), |end| end + close.len())
+            };
+            at += end;
+        } else if rest.starts_with(b"<") {
+            let end = tag_end(rest);
+            let tag = &rest[..end];
+            if is_tag(tag, b"<text") && !tag.ends_with(b"/>") {
+                text_depth += 1;
+            } else if is_tag(tag, b"</text") {
+                text_depth = text_depth.saturating_sub(1);
+            }
+            svg.extend_from_slice(tag);
+            at += end;
+        } else {
+            let end = find(rest, b"<").unwrap_or(rest.len());
+            let content = &rest[..end];
+            if text_depth > 0 || !content.iter().all(u8::is_ascii_whitespace) {
+                svg.extend_from_slice(content);
+            }
+            at += end;
+        }
+    }
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_svg() -> std::path::PathBuf {
+        std::path::PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg"))
+    }
+
+    #[test]
+    fn test_minify_svg() {
+        let svg = b"<?xml version=\"1.0\"?>\n<!-- Created with Inkscape -->\n\
+                    <svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\n  \
+                    <metadata><rdf:RDF>a > b</rdf:RDF></metadata>\n  \
+                    <sodipodi:namedview id=\"v\" pagecolor=\"#fff\"/>\n  \
+                    <style><![CDATA[ rect { fill: red } ]]></style>\n  \
+                    <rect title=\"x > y\" width=\"5\" height=\"5\"/>\n  \
+                    <text><tspan>a</tspan> <tspan>b</tspan></text>\n</svg>\n";
+        let minified = minify_svg(svg);
+        assert_eq!(
+            String::from_utf8(minified.clone()).unwrap(),
+            "<?xml version=\"1.0\"?><svg xmlns=\"http://www.w3.org/2000/svg\" width=\"10\" height=\"10\">\
+             <style><![CDATA[ rect { fill: red } ]]></style><rect title=\"x > y\" width=\"5\" height=\"5\"/>\
+             <text><tspan>a</tspan> <tspan>b</tspan></text></svg>"
+        );
+        let svg_data = std::fs::read(get_test_svg()).unwrap();
+        let minified = minify_svg(&svg_data);
+        assert!(minified.len() < svg_data.len());
+        assert!(parse_svg(&minified, &DocxOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_svg_limits() {
+        let options = DocxOptions {
+            max_page_px: Some(100.0),
+            ..DocxOptions::default()
+        };
+        assert!(matches!(
+            parse_svg(&std::fs::read(get_test_svg()).unwrap(), &options),
+            Err(Error::LimitExceeded)
+        ));
+        let options = DocxOptions {
+            max_svg_bytes: Some(16),
+            ..DocxOptions::default()
+        };
+        assert!(matches!(
+            parse_svg(&std::fs::read(get_test_svg()).unwrap(), &options),
+            Err(Error::LimitExceeded)
+        ));
+        let svg_data = std::fs::read(get_test_svg()).unwrap();
+        assert!(parse_svg(&svg_data, &DocxOptions::hardened()).is_ok());
+    }
+
+    #[test]
+    fn test_raster_fit() {
+        let size = usvg::ScreenSize::new(8000, 5000).unwrap();
+        assert!(matches!(
+            raster_fit(size, 96.0, None),
+            usvg::FitTo::Original
+        ));
+        assert!(matches!(
+            raster_fit(size, 96.0, Some(40_000_000)),
+            usvg::FitTo::Original
+        ));
+        let fitted = raster_fit(size, 96.0, Some(10_000_000))
+            .fit_to(size)
+            .unwrap();
+        assert_eq!((fitted.width(), fitted.height()), (4000, 2500));
+        let fitted = raster_fit(size, 192.0, None).fit_to(size).unwrap();
+        assert_eq!((fitted.width(), fitted.height()), (16000, 10000));
+        let fitted = raster_fit(size, 192.0, Some(10_000_000))
+            .fit_to(size)
+            .unwrap();
+        assert_eq!((fitted.width(), fitted.height()), (4000, 2500));
+    }
+
+    #[test]
+    fn test_max_pixmap() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="20000" height="20000"/>"#;
+        let options = DocxOptions::default();
+        let tree = parse_svg(svg, &options).unwrap();
+        assert!(matches!(
+            blank_pixmap(&tree, &options),
+            Err(Error::LimitExceeded)
+        ));
+        let options = DocxOptions {
+            max_raster_px: Some(1_000_000),
+            ..DocxOptions::default()
+        };
+        let pixmap = blank_pixmap(&tree, &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (1000, 1000));
+    }
+
+    #[test]
+    fn test_rotate() {
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20" viewBox="10 0 40 20">
+            <rect x="10" y="0" width="10" height="20" fill="black"/></svg>"#;
+        let options = DocxOptions::default();
+        let pixel = |tree: &usvg::Tree, x: u32, y: u32| {
+            let pixmap = render_png(tree, &options).unwrap();
+            pixmap.pixel(x, y).unwrap().alpha()
+        };
+        for (degrees, size, dark, light) in [
+            (90, (20.0, 40.0), (10, 5), (10, 35)),
+            (180, (40.0, 20.0), (35, 10), (5, 10)),
+            (270, (20.0, 40.0), (10, 35), (10, 5)),
+        ] {
+            let mut tree = parse_svg(svg, &options).unwrap();
+            assert_eq!(pixel(&tree, 5, 10), 255);
+            rotate(&mut tree, degrees).unwrap();
+            let svg_size = tree.svg_node().size;
+            assert_eq!((svg_size.width(), svg_size.height()), size);
+            assert_eq!(pixel(&tree, dark.0, dark.1), 255, "{}", degrees);
+            assert_eq!(pixel(&tree, light.0, light.1), 0, "{}", degrees);
+        }
+    }
+
+    #[test]
+    fn test_background() {
+        let mut pixmap = tiny_skia::Pixmap::new(4, 1).unwrap();
+        let pixels = [
+            [240, 235, 220, 255],
+            [23, 46, 115, 255],
+            [0, 0, 0, 0],
+            [250, 250, 250, 128],
+        ];
+        pixmap.data_mut().copy_from_slice(&pixels.concat());
+        let mut white = pixmap.clone();
+        Background::White(230).apply(&mut white);
+        assert_eq!(
+            white.data(),
+            [
+                [255, 255, 243, 255],
+                [25, 51, 127, 255],
+                pixels[2],
+                pixels[3]
+            ]
+            .concat()
+        );
+        let mut transparent = pixmap.clone();
+        Background::Transparent(220).apply(&mut transparent);
+        assert_eq!(transparent.data()[..8], [0, 0, 0, 0, 26, 53, 133, 255]);
+        let mut kept = pixmap.clone();
+        Background::Keep.apply(&mut kept);
+        assert_eq!(kept, pixmap);
+    }
+
+    #[test]
+    fn test_scan_cleanup() {
+        let mut gray = vec![255; 25];
+        gray[12] = 0;
+        assert_eq!(despeckle(&gray, 5, 5), vec![255; 25]);
+        let binary = binarize(&gray, 5, 5);
+        assert_eq!(binary[12], 0);
+        assert_eq!(binary.iter().filter(|&&v| v == 255).count(), 24);
+    }
+}
@@ -0,0 +1,92 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Handing the converted pages over to documents built with `docx-rs`.
+
+use crate::ooxml::{media_part, px_to_emu, px_to_twenties_of_pt};
+use crate::{Docx, Result};
+use docx_rs::{AlignmentType, BreakType, Paragraph, Pic, Run};
+
+impl Docx {
+    /// Appends the converted pages to `doc`, each in its own paragraph with
+    /// its caption and page break. Only the PNG renderings are carried over,
+    /// since docx-rs cannot embed SVG images, so pages embedded without one
+    /// are left out.
+    pub fn append_to_docx_rs(&self, mut doc: docx_rs::Docx) -> Result<docx_rs::Docx> {
+        for page in &self.pages {
+            let Some(png) = &page.png else {
+                continue;
+            };
+            let png = self.package.get(&media_part(png))?;
+            let pic = Pic::new_with_dimensions(png, 0, 0).size(
+                px_to_emu(page.size.width()) as u32,
+                px_to_emu(page.size.height()) as u32,
+            );
+            doc = doc.add_paragraph(Paragraph::new().add_run(Run::new().add_image(pic)));
+            if let Some(caption) = &page.caption {
+                doc = doc.add_paragraph(
+                    Paragraph::new()
+                        .align(AlignmentType::Center)
+                        .add_run(Run::new().add_text(caption)),
+                );
+            }
+            if page.break_after {
+                doc = doc.add_paragraph(
+                    Paragraph::new().add_run(Run::new().add_break(BreakType::Page)),
+                );
+            }
+        }
+        Ok(doc)
+    }
+
+    /// A new docx-rs document holding the converted pages, sized like the
+    /// first of them.
+    pub fn to_docx_rs(&self) -> Result<docx_rs::Docx> {
+        let size = self.pages.first().map_or(self.size, |page| page.size);
+        let doc = docx_rs::Docx::new().page_size(
+            px_to_twenties_of_pt(size.width()) as u32,
+            px_to_twenties_of_pt(size.height()) as u32,
+        );
+        self.append_to_docx_rs(doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_docx_rs() {
+        let mut docx = Docx::new().unwrap();
+        docx.add_svg_data(
+            "1.svg",
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg")).unwrap(),
+        )
+        .unwrap();
+        docx.set_page_caption(1, "Page one").unwrap();
+        let mut buf = std::io::Cursor::new(Vec::new());
+        docx.to_docx_rs().unwrap().build().pack(&mut buf).unwrap();
+        let mut zip = zip::ZipArchive::new(buf).unwrap();
+        let mut document = String::new();
+        std::io::Read::read_to_string(
+            &mut zip.by_name("word/document.xml").unwrap(),
+            &mut document,
+        )
+        .unwrap();
+        assert!(document.contains("Page one"));
+        assert!(document.contains(r#"<wp:extent cx="7560000" cy="10692000" />"#));
+    }
+}
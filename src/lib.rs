@@ -15,320 +15,1448 @@
 */
 
 #![recursion_limit = "512"]
+// Without `native`, what only the PDF pipeline uses is dead; the default
+// build still catches the rest.
+#![cfg_attr(not(feature = "native"), allow(dead_code))]
 
-use std::ffi::OsStr;
-use std::fs::{copy, read_to_string, remove_file, write};
-use std::io::{self, ErrorKind, Write};
+use decoration::{reference_sections, Decoration, FOOTER, HEADER, HEADER_RELS};
+use image::minify_svg;
+use ooxml::{
+    app_properties, core_properties, custom_properties, hyperlink_relationship, link_bookmark,
+    link_rid, media_part, now, page_bookmark, pt_to_twenties_of_pt, px_to_twenties_of_pt,
+    relationship, write_bookmark, write_page, write_text_appendix, write_thumbnail_index, Geometry,
+    LinkTarget, Thumbnail, APP_PROPERTIES, CONTENT_TYPES, CORE_PROPERTIES, CUSTOM_PROPERTIES,
+    DOCUMENT, DOCUMENT_RELS, PACKAGE_RELS,
+};
+use package::Package;
+#[cfg(feature = "native")]
+use package::{long_path, write_checksum, WorkDir};
+use parts::{ContentTypes, Relationship, Relationships};
+#[cfg(feature = "native")]
+use picture::Picture;
+use picture::PictureFormat;
+use std::fmt;
+use std::fs::write;
+#[cfg(feature = "native")]
+use std::fs::File;
+use std::io::{self, Seek, Write};
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use tempfile::TempDir;
-use zip_extensions::zip_create_from_directory;
+use std::time::Duration;
+use tracing::{debug, info, info_span};
 
+pub use tiny_skia;
+#[cfg(feature = "async")]
+pub use tokio_util::sync::CancellationToken;
+pub use usvg;
+
+mod a11y;
+#[cfg(feature = "native")]
+mod analysis;
+#[cfg(feature = "native")]
+mod batch;
+#[cfg(feature = "native")]
+mod cache;
+mod decoration;
+mod encryption;
+mod image;
+#[cfg(feature = "docx-rs")]
+mod interop;
+mod metrics;
+#[cfg(feature = "async")]
+mod nonblocking;
+mod ocr;
+mod odt;
+mod ooxml;
+mod package;
+mod parts;
+mod pdf;
+mod pdfa;
+#[cfg(feature = "pdfium")]
+mod pdfium;
+mod picture;
+#[cfg(feature = "plugins")]
+mod plugins;
+mod pptx;
+mod progress;
+mod raster;
+#[cfg(feature = "native")]
+mod render;
+#[cfg(feature = "native")]
+mod renderer;
+#[cfg(feature = "lua")]
+mod scripting;
+mod selection;
+mod signing;
+mod template;
+#[cfg(feature = "upload")]
+mod upload;
+
+pub use a11y::A11yIssue;
+#[cfg(feature = "native")]
+pub use analysis::{PageReport, PdfReport};
+#[cfg(feature = "native")]
+pub use batch::{convert_batch, BatchResult};
+#[cfg(feature = "native")]
+pub use cache::default_cache_dir;
+pub use decoration::{PageDecoration, Watermark};
+pub use image::{Background, ScanCleanup};
+pub use metrics::PageMetrics;
+pub use ocr::Ocr;
+pub use odt::Odt;
+pub use ooxml::{
+    utc_timestamp, FitMode, Margins, Metadata, Page, PageBreaks, PageFrame, PageLocks, PageSize,
+    Pages, SensitivityLabel, SourceSeparator, TextLayer,
+};
+#[cfg(feature = "native")]
+pub use package::{lock_output, prepare_output, OutputLock};
+pub use package::{Optimization, OPTIMIZED_DPI};
+pub use pdfa::{check_pdfa, PdfaReport};
+#[cfg(feature = "pdfium")]
+pub use pdfium::Pdfium;
+#[cfg(feature = "plugins")]
+pub use plugins::ABI_VERSION as PLUGIN_ABI_VERSION;
+pub use pptx::Pptx;
+pub use progress::{ProgressEvent, ProgressHandler};
+#[cfg(feature = "native")]
+pub use render::{handle_interrupts, ResourceLimits, Sandbox};
+#[cfg(feature = "native")]
+pub use renderer::{
+    Backend, Inkscape, Mutool, Pdftocairo, PdfRenderer, RenderContext, SvgPage,
+};
+use raster::{Converter, RasterPage};
+pub use selection::PageSelection;
+pub use signing::SigningIdentity;
+#[cfg(feature = "upload")]
+pub use upload::Upload;
+
+/// Why a conversion failed. Failures of one page are wrapped in
+/// [`Error::PageRender`]; the sources of I/O and image errors are kept.
 #[derive(Debug)]
 pub enum Error {
-    IoError,
-    ImageError,
+    /// Reading or writing failed, for the file at `path` if it is known.
+    IoError {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// An SVG could not be parsed, or a page rasterized or encoded.
+    ImageError {
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
     InkscapeNotFound,
     PDFInvalid,
+    SandboxNotFound,
+    RendererKilled,
+    LimitExceeded,
+    TimeBudgetExceeded,
+    InvalidSigningKey,
+    OutputNotWritable,
+    PageOutOfRange,
+    TemplateInvalid,
+    Interrupted,
+    OutputLocked,
+    PluginInvalid,
+    PluginFailed,
+    ScriptInvalid,
+    ScriptFailed,
+    UploadInvalid,
+    UploadFailed,
+    TesseractNotFound,
+    RendererNotFound,
+    /// The PDF is encrypted, and no pages could be rendered without the
+    /// right password.
+    PdfEncrypted,
+    /// Page `page` of the PDF (1-based) could not be converted.
+    PageRender { page: usize, source: Box<Error> },
+    /// The renderer was killed for taking longer than
+    /// [`DocxOptions::page_timeout`] on page `page`.
+    PageTimeout { page: usize },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// An [`Error::IoError`] for data that is not what it should be.
+    pub(crate) fn invalid_data(
+        error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Error {
+        io::Error::new(io::ErrorKind::InvalidData, error).into()
+    }
+
+    /// An [`Error::ImageError`] with no more to say.
+    pub(crate) fn image() -> Error {
+        Error::ImageError { source: None }
+    }
+
+    /// Names `path` as the file an [`Error::IoError`] is about, unless one is
+    /// named already.
+    pub fn at(self, path: &Path) -> Error {
+        match self {
+            Error::IoError { path: None, source } => Error::IoError {
+                path: Some(path.to_owned()),
+                source,
+            },
+            e => e,
+        }
+    }
+
+    /// Wraps an error in [`Error::PageRender`] if it is about page `page`
+    /// only, rather than the whole conversion.
+    pub(crate) fn on_page(self, page: usize) -> Error {
+        match self {
+            Error::IoError { .. }
+            | Error::ImageError { .. }
+            | Error::RendererKilled
+            | Error::PluginFailed
+            | Error::ScriptFailed => Error::PageRender {
+                page,
+                source: Box::new(self),
+            },
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::IoError {
+                path: Some(path),
+                source,
+            } => write!(f, "{}: {}", path.display(), source),
+            Error::IoError { path: None, source } => write!(f, "I/O error: {}", source),
+            Error::ImageError {
+                source: Some(source),
+            } => write!(f, "image error: {}", source),
+            Error::ImageError { source: None } => write!(f, "image error"),
+            Error::InkscapeNotFound => write!(f, "Inkscape not found"),
+            Error::PDFInvalid => write!(f, "invalid PDF"),
+            Error::SandboxNotFound => write!(f, "sandbox program not found"),
+            Error::RendererKilled => write!(f, "the renderer was killed"),
+            Error::LimitExceeded => write!(f, "size or page limit exceeded"),
+            Error::TimeBudgetExceeded => write!(f, "time budget exceeded"),
+            Error::InvalidSigningKey => write!(f, "invalid signing key or certificate"),
+            Error::OutputNotWritable => write!(f, "the output is not writable"),
+            Error::PageOutOfRange => write!(f, "no such page"),
+            Error::TemplateInvalid => write!(f, "invalid template"),
+            Error::Interrupted => write!(f, "interrupted"),
+            Error::OutputLocked => write!(f, "the output is locked by another conversion"),
+            Error::PluginInvalid => write!(f, "incompatible plugin"),
+            Error::PluginFailed => write!(f, "a plugin failed"),
+            Error::ScriptInvalid => write!(f, "the page script is not a function"),
+            Error::ScriptFailed => write!(f, "the page script failed"),
+            Error::UploadInvalid => write!(f, "unsupported upload URL"),
+            Error::UploadFailed => write!(f, "upload failed"),
+            Error::TesseractNotFound => write!(f, "Tesseract not found"),
+            Error::RendererNotFound => write!(f, "PDF renderer not found"),
+            Error::PdfEncrypted => write!(f, "the PDF is encrypted and needs a password"),
+            Error::PageRender { page, source } => write!(f, "page {}: {}", page, source),
+            Error::PageTimeout { page } => write!(f, "page {} took too long to render", page),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IoError { source, .. } => Some(source),
+            Error::ImageError {
+                source: Some(source),
+            } => Some(&**source),
+            Error::PageRender { source, .. } => Some(&**source),
+            _ => None,
+        }
+    }
+}
+
 impl From<std::io::Error> for Error {
-    fn from(_: std::io::Error) -> Error {
-        Error::IoError
+    fn from(source: std::io::Error) -> Error {
+        Error::IoError { path: None, source }
     }
 }
 
 impl From<usvg::Error> for Error {
-    fn from(_: usvg::Error) -> Error {
-        Error::ImageError
+    fn from(source: usvg::Error) -> Error {
+        Error::ImageError {
+            source: Some(Box::new(source)),
+        }
     }
 }
 
 impl From<png::EncodingError> for Error {
-    fn from(_: png::EncodingError) -> Error {
-        Error::ImageError
+    fn from(source: png::EncodingError) -> Error {
+        Error::ImageError {
+            source: Some(Box::new(source)),
+        }
     }
 }
 
 impl From<zip::result::ZipError> for Error {
-    fn from(_: zip::result::ZipError) -> Error {
-        Error::IoError
+    fn from(e: zip::result::ZipError) -> Error {
+        match e {
+            zip::result::ZipError::Io(source) => source.into(),
+            e => Error::invalid_data(e),
+        }
     }
 }
 
-fn px_to_emu(px: f64) -> i32 {
-    let dpi = 96.0;
-    let emus_per_inch = 914400.0;
-    (px / dpi * emus_per_inch) as i32
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct DocxOptions {
+    #[cfg(feature = "native")]
+    pub sandbox: Sandbox,
+    #[cfg(feature = "native")]
+    pub limits: ResourceLimits,
+    pub max_pages: Option<usize>,
+    /// Largest accepted page width or height, in px.
+    pub max_page_px: Option<f64>,
+    pub max_svg_bytes: Option<u64>,
+    /// Pixel count above which the PNG fallback is rendered downscaled.
+    pub max_raster_px: Option<u64>,
+    /// Resolution of the PNG fallback, 96 being one pixel per CSS pixel.
+    pub raster_dpi: f64,
+    pub optimization: Optimization,
+    pub scan_cleanup: ScanCleanup,
+    pub page_frame: PageFrame,
+    pub page_locks: PageLocks,
+    pub page_breaks: PageBreaks,
+    /// What comes between the pages of PDFs added with [`Docx::append_pdf`].
+    pub source_separator: SourceSeparator,
+    /// Headers, footers and a watermark on every page.
+    pub decoration: PageDecoration,
+    /// The paper of the document. Pages are sized on it as the
+    /// [`DocxOptions::fit_mode`] says.
+    pub page_size: PageSize,
+    /// Turn fixed page sizes sideways.
+    pub landscape: bool,
+    pub margins: Margins,
+    /// How pages are scaled to paper of a fixed size less its margins.
+    pub fit_mode: FitMode,
+    /// Start with a page of thumbnails linking to each page.
+    pub thumbnail_index: bool,
+    /// Whether images referenced by path from the rendered SVGs may be loaded.
+    pub external_images: bool,
+    /// Wall-clock time allowed for a whole `convert_pdf` call.
+    pub time_budget: Option<Duration>,
+    /// Wall-clock time allowed for rendering one page, after which the
+    /// renderer is killed. Renderers that run no program, as PDFium, are
+    /// not stopped.
+    pub page_timeout: Option<Duration>,
+    /// Leave no timestamps, renderer metadata or file names in the output.
+    pub scrub_metadata: bool,
+    /// Overwrite intermediate files before deleting them. Rendered pages
+    /// still pass through ordinary, swappable memory.
+    pub secure_delete: bool,
+    /// Read the renderer output from pipes and keep the whole package in
+    /// memory; pair with [`Docx::to_bytes`] to never touch the disk. Always
+    /// the case without the `native` feature.
+    pub in_memory: bool,
+    /// Encrypt the output so Word asks for this password when opening it.
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub output_password: Option<String>,
+    /// The password that opens an encrypted source PDF. Inkscape cannot take
+    /// one, so converting with it fails; use another [`Backend`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing))]
+    pub pdf_password: Option<String>,
+    pub sensitivity_label: Option<SensitivityLabel>,
+    /// Check whether the source PDF looks like PDF/A and record the result
+    /// in the `SourcePdfA` custom property.
+    pub record_pdfa: bool,
+    /// The title, author and dates of the document.
+    pub metadata: Metadata,
+    /// Take what [`DocxOptions::metadata`] leaves unset from the document
+    /// information of the source PDF, unless metadata is scrubbed.
+    pub pdf_metadata: bool,
+    /// Turn the bookmarks of the source PDF into hidden headings before the
+    /// pages they lead to, for the navigation pane and tables of contents.
+    pub pdf_outline: bool,
+    /// Make the links of the source PDF clickable, both those to web pages
+    /// and those to other pages converted.
+    pub pdf_links: bool,
+    /// A DOCX whose parts are used instead of the built-in ones. Its
+    /// document must contain `{{pages}}`; see [`check_template`].
+    pub template: Option<PathBuf>,
+    /// Values for the other `{{name}}` placeholders of the template.
+    pub template_variables: BTreeMap<String, String>,
+    /// Write `<output>.sha256` next to the output, in `sha256sum` format.
+    pub checksum_sidecar: bool,
+    /// Audit the finished document and write the [`A11yIssue`]s found to
+    /// this file, one per line.
+    pub a11y_report: Option<PathBuf>,
+    /// Describe pages that have no alt text by the figure and table
+    /// captions `pdftotext` finds on them.
+    pub caption_alt_text: bool,
+    /// Add the text `pdftotext` finds on each page, so that the document can
+    /// be searched.
+    pub text_layer: TextLayer,
+    /// Turn sideways and upside-down pages upright, as detected from their
+    /// text by `tesseract`.
+    pub auto_orient: bool,
+    /// Recognize the text of pages that have none, as scans, with
+    /// `tesseract`, for the [`DocxOptions::text_layer`] to place. The
+    /// confidence of each page is in its [`PageMetrics`].
+    pub ocr: Option<Ocr>,
+    /// How many pages to render and rasterize at once. Pages seen by an SVG
+    /// or raster hook are rasterized one at a time.
+    pub jobs: usize,
+    /// The program [`Docx::convert_pdf`] renders pages with.
+    #[cfg(feature = "native")]
+    pub backend: Backend,
+    /// Write the [`PageMetrics`] of every page to this CSV file once the PDF
+    /// is converted.
+    pub metrics: Option<PathBuf>,
+    /// Keep rendered pages in a directory of this one named after the PDF
+    /// and the options they depend on, and reuse them when the same PDF is
+    /// converted again, as after an interrupted conversion. Nothing is ever
+    /// removed from it, and pages of encrypted PDFs are kept decrypted, so
+    /// it is made for the current user only, and one that other users own
+    /// or may open is refused.
+    pub cache_dir: Option<PathBuf>,
+    /// Load renderer and rasterizer plugins from the shared libraries in
+    /// this directory; see [`PLUGIN_ABI_VERSION`]. They are called from
+    /// several threads at once, so they must be thread-safe.
+    #[cfg(feature = "plugins")]
+    pub plugin_dir: Option<PathBuf>,
+    /// Lua source evaluating to a function that is given the [`PageInfo`]
+    /// fields of each page as a table and returns `nil` or a table of
+    /// [`PageDecision`] fields. Takes precedence over the page callback. It
+    /// cannot reach files or the system, and a call running past the page
+    /// timeout or the time budget fails.
+    #[cfg(feature = "lua")]
+    pub page_script: Option<String>,
+    /// Sign the package so recipients can check it was not altered after
+    /// conversion. Not part of serialized options; load it with
+    /// [`SigningIdentity::from_pem`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub signing: Option<SigningIdentity>,
 }
 
-fn px_to_twenties_of_pt(px: f64) -> i32 {
-    let dpi = 96.0;
-    let pt_per_inch = 72.0;
-    (px / dpi * pt_per_inch * 20.0) as i32
+impl Default for DocxOptions {
+    fn default() -> DocxOptions {
+        DocxOptions {
+            #[cfg(feature = "native")]
+            sandbox: Sandbox::None,
+            #[cfg(feature = "native")]
+            limits: ResourceLimits::default(),
+            max_pages: None,
+            max_page_px: None,
+            max_svg_bytes: None,
+            max_raster_px: None,
+            raster_dpi: 96.0,
+            optimization: Optimization::default(),
+            scan_cleanup: ScanCleanup::default(),
+            page_frame: PageFrame::default(),
+            page_locks: PageLocks::default(),
+            page_breaks: PageBreaks::default(),
+            source_separator: SourceSeparator::default(),
+            decoration: PageDecoration::default(),
+            page_size: PageSize::default(),
+            landscape: false,
+            margins: Margins::default(),
+            fit_mode: FitMode::default(),
+            thumbnail_index: false,
+            external_images: true,
+            time_budget: None,
+            page_timeout: None,
+            scrub_metadata: false,
+            secure_delete: false,
+            in_memory: false,
+            output_password: None,
+            pdf_password: None,
+            sensitivity_label: None,
+            record_pdfa: false,
+            metadata: Metadata::default(),
+            pdf_metadata: false,
+            pdf_outline: false,
+            pdf_links: false,
+            template: None,
+            template_variables: BTreeMap::new(),
+            checksum_sidecar: false,
+            a11y_report: None,
+            caption_alt_text: false,
+            text_layer: TextLayer::None,
+            auto_orient: false,
+            ocr: None,
+            jobs: 1,
+            #[cfg(feature = "native")]
+            backend: Backend::default(),
+            metrics: None,
+            cache_dir: None,
+            #[cfg(feature = "plugins")]
+            plugin_dir: None,
+            #[cfg(feature = "lua")]
+            page_script: None,
+            signing: None,
+        }
+    }
 }
 
-fn get_filename(svg: &Path) -> &str {
-    svg.file_name().unwrap().to_str().unwrap()
+impl DocxOptions {
+    /// Conservative caps for converting untrusted uploads.
+    pub fn hardened() -> DocxOptions {
+        DocxOptions {
+            #[cfg(feature = "native")]
+            sandbox: Sandbox::Bubblewrap,
+            #[cfg(feature = "native")]
+            limits: ResourceLimits {
+                cpu_seconds: Some(120),
+                memory_bytes: Some(2 * 1024 * 1024 * 1024),
+                file_size_bytes: Some(256 * 1024 * 1024),
+            },
+            max_pages: Some(500),
+            max_page_px: Some(5000.0),
+            max_svg_bytes: Some(64 * 1024 * 1024),
+            max_raster_px: Some(40_000_000),
+            external_images: false,
+            time_budget: Some(Duration::from_secs(600)),
+            page_timeout: Some(Duration::from_secs(120)),
+            ..DocxOptions::default()
+        }
+    }
 }
 
-fn read_svg(src: &Path) -> Result<usvg::Tree> {
-    let opt = usvg::Options::default();
-    let svg_data = std::fs::read(src)?;
-    Ok(usvg::Tree::from_data(&svg_data, &opt.to_ref())?)
+/// Called with the [`Page::number`] and the parsed SVG of each page before
+/// it is embedded. Changes to the tree are written back into the package.
+pub type SvgHook = Box<dyn FnMut(usize, &mut usvg::Tree) -> Result<()>>;
+
+/// Called with the [`Page::number`] and the PNG fallback of each page
+/// before it is saved. The pixmap may be modified in place or replaced.
+pub type RasterHook = Box<dyn FnMut(usize, &mut tiny_skia::Pixmap) -> Result<()>>;
+
+/// What is known about a page right before it is added to the document.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageInfo {
+    /// The [`Page::number`] the page is added as.
+    pub number: usize,
+    /// Width in px.
+    pub width: f64,
+    /// Height in px.
+    pub height: f64,
+    pub svg_bytes: u64,
+    pub png_bytes: u64,
 }
 
-fn save_png(dst: &Path, rtree: &usvg::Tree) -> Result<()> {
-    let size = rtree.svg_node().size.to_screen_size();
-    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height()).unwrap();
-    resvg::render(rtree, usvg::FitTo::Original, tiny_skia::Transform::identity() ,pixmap.as_mut()).ok_or(Error::ImageError)?;
-    pixmap.save_png(dst)?;
-    Ok(())
+/// How a [`PageCallback`] wants a page to be added.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageDecision {
+    pub skip: bool,
+    /// Text placed in a paragraph right below the page image.
+    pub caption: Option<String>,
+    pub alt_text: Option<String>,
+    /// Embed the PNG only, without the SVG.
+    pub raster_only: bool,
+    /// Factor applied to the displayed size of the page.
+    pub scale: Option<f64>,
+}
+
+pub type PageCallback = Box<dyn FnMut(&PageInfo) -> PageDecision>;
+
+/// Loads the template of `options` and lists what is wrong with it, in words
+/// meant for the user. An empty list means it can be used.
+pub fn check_template(options: &DocxOptions) -> Result<Vec<String>> {
+    let package = Package::new(&DocxOptions {
+        in_memory: true,
+        ..options.clone()
+    })?;
+    Ok(template::problems(&package, &options.template_variables))
+}
+
+/// Compares names with runs of digits as numbers, so that `page2` sorts
+/// before `page10`.
+fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let ordering = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let a_end = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let b_end = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let x = a[..a_end].trim_start_matches('0');
+            let y = b[..b_end].trim_start_matches('0');
+            let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+            (a, b) = (&a[a_end..], &b[b_end..]);
+            ordering
+        } else {
+            (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+            x.cmp(&y)
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
 }
 
-fn get_png_path(prefix: &Path, svg_path: &Path) -> Result<PathBuf> {
-    let filename = svg_path
-        .file_name()
-        .unwrap()
-        .to_str()
-        .ok_or(Error::IoError)?
-        .replace("svg", "png");
-    Ok(prefix.join(Path::new(&filename)))
+/// Sets up a [`Docx`] one setting at a time, starting from
+/// [`DocxOptions::default`] or from the given options.
+#[derive(Debug, Clone, Default)]
+pub struct DocxBuilder {
+    options: DocxOptions,
+}
+
+impl DocxBuilder {
+    pub fn new() -> DocxBuilder {
+        DocxBuilder::default()
+    }
+
+    /// Replaces every setting with `options`.
+    pub fn options(mut self, options: DocxOptions) -> DocxBuilder {
+        self.options = options;
+        self
+    }
+
+    pub fn page_size(mut self, page_size: PageSize) -> DocxBuilder {
+        self.options.page_size = page_size;
+        self
+    }
+
+    /// Keeps every page at its size in the PDF, unscaled, with the paper
+    /// sized after the first page.
+    pub fn preserve_page_sizes(self) -> DocxBuilder {
+        self.page_size(PageSize::Source)
+    }
+
+    pub fn landscape(mut self, landscape: bool) -> DocxBuilder {
+        self.options.landscape = landscape;
+        self
+    }
+
+    pub fn margins(mut self, margins: Margins) -> DocxBuilder {
+        self.options.margins = margins;
+        self
+    }
+
+    pub fn fit_mode(mut self, fit_mode: FitMode) -> DocxBuilder {
+        self.options.fit_mode = fit_mode;
+        self
+    }
+
+    pub fn raster_dpi(mut self, dpi: f64) -> DocxBuilder {
+        self.options.raster_dpi = dpi;
+        self
+    }
+
+    pub fn optimization(mut self, optimization: Optimization) -> DocxBuilder {
+        self.options.optimization = optimization;
+        self
+    }
+
+    /// Makes the DOCX smaller with [`Optimization::smaller`], and renders the
+    /// PNG fallback at no more than [`OPTIMIZED_DPI`].
+    pub fn optimize(mut self) -> DocxBuilder {
+        self.options.raster_dpi = self.options.raster_dpi.min(OPTIMIZED_DPI);
+        self.optimization(Optimization::smaller())
+    }
+
+    /// Embeds pages as just their SVG, without rasterizing them for a PNG
+    /// fallback; see [`Optimization::svg_only`].
+    pub fn no_png_fallback(mut self) -> DocxBuilder {
+        self.options.optimization.svg_only = true;
+        self
+    }
+
+    pub fn metadata(mut self, metadata: Metadata) -> DocxBuilder {
+        self.options.metadata = metadata;
+        self
+    }
+
+    /// Puts `text` at the top of every page; see [`PageDecoration::header`].
+    pub fn header(mut self, text: impl Into<String>) -> DocxBuilder {
+        self.options.decoration.header = Some(text.into());
+        self
+    }
+
+    /// Puts `text` at the bottom of every page, as with
+    /// [`DocxBuilder::header`].
+    pub fn footer(mut self, text: impl Into<String>) -> DocxBuilder {
+        self.options.decoration.footer = Some(text.into());
+        self
+    }
+
+    pub fn watermark(mut self, watermark: Watermark) -> DocxBuilder {
+        self.options.decoration.watermark = Some(watermark);
+        self
+    }
+
+    pub fn build(self) -> Result<Docx> {
+        Docx::with_options(self.options)
+    }
 }
 
 pub struct Docx {
-    dir: TempDir,
-    media_dir: PathBuf,
-    doc: PathBuf,
-    rels: PathBuf,
-    next_id: i32,
-    doc_string: String,
-    rels_string: String,
+    options: DocxOptions,
+    svg_hook: Option<SvgHook>,
+    raster_hook: Option<RasterHook>,
+    page_callback: Option<PageCallback>,
+    progress: Option<std::sync::Arc<dyn ProgressHandler>>,
+    /// How many pages have been rendered so far, skipped ones included.
+    rendered: usize,
+    /// Bytes of the images of the pages as rendered, before optimization.
+    rendered_bytes: u64,
+    pages: Vec<Page>,
+    metrics: Vec<PageMetrics>,
+    package: Package,
+    /// Where the renderer writes its output, unless it runs in memory.
+    #[cfg(feature = "native")]
+    scratch: Option<WorkDir>,
+    /// Page size of an empty document.
     size: usvg::Size,
+    /// File name of the PDF being converted, unless metadata is scrubbed.
+    source: Option<String>,
+    /// How the source fared in the PDF/A check, if one was asked for.
+    pdfa: Option<PdfaReport>,
+    /// The document information of the source PDF, with
+    /// [`DocxOptions::pdf_metadata`].
+    source_metadata: Metadata,
+    /// Set to stop the conversion as an interrupt would, from another
+    /// thread.
+    cancelled: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    #[cfg(feature = "plugins")]
+    plugins: std::sync::Arc<plugins::Plugins>,
+    #[cfg(feature = "lua")]
+    page_script: Option<scripting::PageScript>,
 }
 
 impl Docx {
     pub fn new() -> Result<Docx> {
-        let dir = TempDir::new()?;
-        Docx::copy_base_files(&dir)?;
-        let path = dir.path();
-        let doc: PathBuf = [path.as_os_str(), OsStr::new("word/document.xml")]
-            .iter()
-            .collect();
-        let rels: PathBuf = [path.as_os_str(), OsStr::new("word/_rels/document.xml.rels")]
-            .iter()
-            .collect();
-        let media_dir = [path.as_os_str(), OsStr::new("word/media")]
-            .iter()
-            .collect();
+        Docx::with_options(DocxOptions::default())
+    }
+
+    pub fn builder() -> DocxBuilder {
+        DocxBuilder::new()
+    }
+
+    pub fn with_options(options: DocxOptions) -> Result<Docx> {
+        let package = Package::new(&options)?;
+        if !template::problems(&package, &options.template_variables).is_empty() {
+            return Err(Error::TemplateInvalid);
+        }
+        #[cfg(feature = "plugins")]
+        let plugins = match &options.plugin_dir {
+            Some(dir) => plugins::Plugins::load(dir)?,
+            None => plugins::Plugins::default(),
+        };
+        #[cfg(feature = "lua")]
+        let page_script = options
+            .page_script
+            .as_deref()
+            .map(|source| scripting::PageScript::new(source, &options))
+            .transpose()?;
+        #[cfg(feature = "native")]
+        let scratch = if options.in_memory {
+            None
+        } else {
+            Some(WorkDir::new(options.secure_delete)?)
+        };
         Ok(Docx {
-            dir,
-            media_dir,
-            doc,
-            rels,
-            next_id: 0,
-            doc_string: String::new(),
-            rels_string: String::new(),
+            options,
+            svg_hook: None,
+            raster_hook: None,
+            page_callback: None,
+            progress: None,
+            rendered: 0,
+            rendered_bytes: 0,
+            pages: Vec::new(),
+            metrics: Vec::new(),
+            package,
+            #[cfg(feature = "native")]
+            scratch,
             size: usvg::Size::new(793.707, 1122.52).unwrap(),
+            source: None,
+            pdfa: None,
+            source_metadata: Metadata::default(),
+            cancelled: std::sync::Arc::default(),
+            #[cfg(feature = "plugins")]
+            plugins: std::sync::Arc::new(plugins),
+            #[cfg(feature = "lua")]
+            page_script,
         })
     }
 
-    fn copy_base_files(dir: &TempDir) -> Result<()> {
-        let fixtures_zip = include_bytes!("../fixtures/fixtures.zip");
-        let mut zip_path = dir.path().to_owned();
-        zip_path.push("fixtures.zip");
-        std::fs::write(&zip_path, fixtures_zip)?;
-        zip_extensions::read::zip_extract(&zip_path, &dir.path().to_owned())?;
-        remove_file(zip_path)?;
+    pub fn set_svg_hook(
+        &mut self,
+        hook: impl FnMut(usize, &mut usvg::Tree) -> Result<()> + 'static,
+    ) {
+        self.svg_hook = Some(Box::new(hook));
+    }
+
+    pub fn set_raster_hook(
+        &mut self,
+        hook: impl FnMut(usize, &mut tiny_skia::Pixmap) -> Result<()> + 'static,
+    ) {
+        self.raster_hook = Some(Box::new(hook));
+    }
+
+    pub fn set_page_callback(&mut self, callback: impl FnMut(&PageInfo) -> PageDecision + 'static) {
+        self.page_callback = Some(Box::new(callback));
+    }
+
+    pub fn set_metadata(&mut self, metadata: Metadata) {
+        self.options.metadata = metadata;
+    }
+
+    pub fn set_progress_handler(&mut self, handler: impl ProgressHandler + 'static) {
+        self.progress = Some(std::sync::Arc::new(handler));
+    }
+
+    fn report(&self, event: ProgressEvent) {
+        if let Some(progress) = &self.progress {
+            progress.event(event);
+        }
+    }
+
+    /// Adds `svg` as the next page, sized like the SVG, for tools that make
+    /// their own SVG pages.
+    #[cfg(feature = "native")]
+    pub fn add_svg(&mut self, svg: &Path) -> Result<()> {
+        let svg_data = std::fs::read(svg).map_err(|e| Error::from(e).at(svg))?;
+        self.add_svg_bytes(&svg_data)
+    }
+
+    /// Adds the SVG in `svg` as the next page, like [`Docx::add_svg`], for
+    /// pages rendered elsewhere, as in the browser.
+    pub fn add_svg_bytes(&mut self, svg: &[u8]) -> Result<()> {
+        let name = format!("{}.svg", self.rendered + 1);
+        self.add_svg_data(&name, svg.to_vec())
+            .map_err(|e| e.on_page(self.rendered))
+    }
+
+    /// Adds every `.svg` file in `dir` like [`Docx::add_svg`], in natural
+    /// order of their names: `2.svg` comes before `10.svg`. Subdirectories
+    /// are not searched.
+    #[cfg(feature = "native")]
+    pub fn add_svg_dir(&mut self, dir: &Path) -> Result<()> {
+        let mut svgs = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(|e| Error::from(e).at(dir))? {
+            let path = entry.map_err(|e| Error::from(e).at(dir))?.path();
+            if path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+            {
+                svgs.push(path);
+            }
+        }
+        svgs.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+        for svg in svgs {
+            self.add_svg(&svg)?;
+        }
         Ok(())
     }
 
-    fn add_image_svg(&mut self, svg: &Path) -> Result<()> {
-        let tree = read_svg(svg)?;
-        let png = get_png_path(&self.media_dir, svg)?;
-        save_png(&png, &tree)?;
-        let svg_copy = &self
-            .media_dir
-            .join(Path::new(svg.file_name().ok_or(Error::IoError)?));
-        if svg != svg_copy {
-            copy(svg, svg_copy)?;
+    /// How large the images of the pages added so far are as rendered,
+    /// before [`Optimization`]: what they would take up in the DOCX stored
+    /// as they are.
+    pub fn rendered_bytes(&self) -> u64 {
+        self.rendered_bytes
+    }
+
+    /// Timings and sizes of every page rendered so far, skipped ones
+    /// included.
+    pub fn metrics(&self) -> &[PageMetrics] {
+        &self.metrics
+    }
+
+    fn add_svg_data(&mut self, name: &str, svg_data: Vec<u8>) -> Result<()> {
+        self.add_svg_with(name, |converter, number, svg_hook, raster_hook| {
+            converter.raster_page(number, svg_data, svg_hook, raster_hook)
+        })
+    }
+
+    /// Adds the page that `raster` rasterizes from [`Docx::converter`], as
+    /// page number [`Docx::rendered`], with the hooks.
+    fn add_svg_with(
+        &mut self,
+        name: &str,
+        raster: impl FnOnce(
+            Converter,
+            usize,
+            Option<&mut SvgHook>,
+            Option<&mut RasterHook>,
+        ) -> Result<RasterPage>,
+    ) -> Result<()> {
+        self.rendered += 1;
+        // The renderer borrows `self`, so lend it the hooks.
+        let mut svg_hook = self.svg_hook.take();
+        let mut raster_hook = self.raster_hook.take();
+        let page = raster(
+            self.converter(),
+            self.rendered,
+            svg_hook.as_mut(),
+            raster_hook.as_mut(),
+        );
+        self.svg_hook = svg_hook;
+        self.raster_hook = raster_hook;
+        self.add_raster_page(name, page?)
+    }
+
+    /// Adds page [`Docx::rendered`] as the page script or callback decides.
+    fn add_raster_page(&mut self, name: &str, page: RasterPage) -> Result<()> {
+        let RasterPage {
+            svg_data,
+            png_data,
+            size,
+            raster,
+        } = page;
+        let png = format!("{}.png", name.strip_suffix(".svg").unwrap_or(name));
+        let png_bytes = png_data.as_ref().map_or(0, Vec::len) as u64;
+        self.rendered_bytes += svg_data.len() as u64 + png_bytes;
+        let svg_data = if self.options.optimization.minify_svg {
+            minify_svg(&svg_data)
+        } else {
+            svg_data
+        };
+        self.metrics.push(PageMetrics {
+            number: self.rendered,
+            render: Duration::ZERO,
+            raster,
+            svg_bytes: svg_data.len() as u64,
+            png_bytes,
+            ocr_confidence: None,
+        });
+        let decision = self.decide(&PageInfo {
+            number: self.rendered,
+            width: size.width(),
+            height: size.height(),
+            svg_bytes: svg_data.len() as u64,
+            png_bytes,
+        })?;
+        if decision.skip {
+            debug!(page = self.rendered, "skipped");
+            self.report(ProgressEvent::PageSkipped(self.rendered));
+            return Ok(());
         }
-        self.add_to_doc(svg_copy, &png, &tree.svg_node().size);
-        print!(".");
-        io::stdout().flush()?;
+        if decision.raster_only || self.options.scan_cleanup.is_enabled() {
+            let png_data = match png_data {
+                Some(png_data) => png_data,
+                None => {
+                    let mut raster_hook = self.raster_hook.take();
+                    let png_data = self.converter().png_fallback(
+                        self.rendered,
+                        &svg_data,
+                        raster_hook.as_mut(),
+                    );
+                    self.raster_hook = raster_hook;
+                    png_data?
+                }
+            };
+            self.package.put(&media_part(&png), png_data)?;
+            self.add_to_doc(None, Some(&png), &size, &decision);
+        } else if let Some(png_data) = png_data.filter(|_| !self.options.optimization.svg_only) {
+            self.package.put(&media_part(&png), png_data)?;
+            self.package.put(&media_part(name), svg_data)?;
+            self.add_to_doc(Some(name), Some(&png), &size, &decision);
+        } else {
+            self.package.put(&media_part(name), svg_data)?;
+            self.add_to_doc(Some(name), None, &size, &decision);
+        }
+        self.report(ProgressEvent::PageEmbedded(self.rendered));
         Ok(())
     }
 
-    fn next_id(&mut self) -> i32 {
-        let ret = self.next_id;
-        self.next_id += 1;
-        ret
-    }
-
-    fn add_to_doc(&mut self, svg: &Path, png: &Path, size: &usvg::Size) {
-        let svg_id = self.next_id();
-        let png_id = self.next_id();
-        let svg_rid = format!("rId{}", svg_id);
-        let png_rid = format!("rId{}", png_id);
-        let width = px_to_emu(size.width());
-        let height = px_to_emu(size.height());
-        self.doc_string = format!(
-            "{}{}",
-            self.doc_string,
-            format_xml::xml! {
-              <w:p>
-                <w:pPr>
-                    <w:widowControl/>
-                    <w:jc w:val="left"/>
-                </w:pPr>
-                <w:r>
-                    <w:rPr>
-                        <w:noProof/>
-                    </w:rPr>
-                    <w:drawing>
-                        <wp:inline distT="0" distB="0" distL="0" distR="0">
-                            <wp:extent cx={width} cy={height}/>
-                            <wp:effectExtent l="0" t="0" r="0" b="0"/>
-                            <wp:docPr id={svg_id} name={svg_id}/>
-                            <wp:cNvGraphicFramePr>
-                                <a:graphicFrameLocks xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" noChangeAspect="1"/>
-                            </wp:cNvGraphicFramePr>
-                            <a:graphic xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
-                                <a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture">
-                                    <pic:pic xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture">
-                                        <pic:nvPicPr>
-                                            <pic:cNvPr id="1" name=""/>
-                                            <pic:cNvPicPr/>
-                                        </pic:nvPicPr>
-                                        <pic:blipFill>
-                                            <a:blip r:embed={png_rid}>
-                                                <a:extLst>
-                                                    <a:ext uri="{{96DAC541-7B7A-43D3-8B79-37D633B846F1}}">
-                                                        <asvg:svgBlip xmlns:asvg="http://schemas.microsoft.com/office/drawing/2016/SVG/main" r:embed={svg_rid}/>
-                                                    </a:ext>
-                                                </a:extLst>
-                                            </a:blip>
-                                            <a:stretch>
-                                                <a:fillRect/>
-                                            </a:stretch>
-                                        </pic:blipFill>
-                                        <pic:spPr>
-                                            <a:xfrm>
-                                                <a:off x="0" y="0"/>
-                                                <a:ext cx={width} cy={height}/>
-                                            </a:xfrm>
-                                            <a:prstGeom prst="rect">
-                                                <a:avLst/>
-                                            </a:prstGeom>
-                                        </pic:spPr>
-                                    </pic:pic>
-                                </a:graphicData>
-                            </a:graphic>
-                        </wp:inline>
-                    </w:drawing>
-                </w:r>
-              </w:p>
+    /// What the page script, or else the page callback, makes of a page.
+    fn decide(&mut self, info: &PageInfo) -> Result<PageDecision> {
+        #[cfg(feature = "lua")]
+        let scripted = self
+            .page_script
+            .as_ref()
+            .map(|script| script.decide(info))
+            .transpose()?;
+        #[cfg(not(feature = "lua"))]
+        let scripted = None;
+        Ok(match (scripted, &mut self.page_callback) {
+            (Some(decision), _) => decision,
+            (None, Some(callback)) => callback(info),
+            (None, None) => PageDecision::default(),
+        })
+    }
+
+    /// Adds a PNG, JPEG or TIFF image as the next page, embedded as it is.
+    /// The page is as large as the image prints at the resolution it
+    /// records, or at 96 dpi if it records none.
+    #[cfg(feature = "native")]
+    pub fn add_image(&mut self, path: &Path) -> Result<()> {
+        let data = std::fs::read(path).map_err(|e| Error::from(e).at(path))?;
+        let picture = Picture::read(&data).map_err(|e| e.at(path))?;
+        if let Some(max) = self.options.max_page_px {
+            if picture.width as f64 > max || picture.height as f64 > max {
+                return Err(Error::LimitExceeded);
             }
-        );
-        self.add_relationship(&svg_rid, get_filename(svg));
-        self.add_relationship(&png_rid, get_filename(png))
+        }
+        let size = picture.size().ok_or_else(Error::image)?;
+        self.rendered += 1;
+        self.rendered_bytes += data.len() as u64;
+        let name = format!("{}.{}", self.rendered, picture.format.extension());
+        self.metrics.push(PageMetrics {
+            number: self.rendered,
+            render: Duration::ZERO,
+            raster: Duration::ZERO,
+            svg_bytes: 0,
+            png_bytes: data.len() as u64,
+            ocr_confidence: None,
+        });
+        let decision = self.decide(&PageInfo {
+            number: self.rendered,
+            width: size.width(),
+            height: size.height(),
+            svg_bytes: 0,
+            png_bytes: data.len() as u64,
+        })?;
+        if decision.skip {
+            self.report(ProgressEvent::PageSkipped(self.rendered));
+            return Ok(());
+        }
+        self.package.put(&media_part(&name), data)?;
+        self.add_to_doc(None, Some(&name), &size, &decision);
+        self.report(ProgressEvent::PageEmbedded(self.rendered));
+        Ok(())
+    }
+
+    fn add_to_doc(
+        &mut self,
+        svg: Option<&str>,
+        png: Option<&str>,
+        size: &usvg::Size,
+        decision: &PageDecision,
+    ) {
+        self.pages.push(Page {
+            number: self.rendered,
+            svg: svg.map(str::to_owned),
+            png: png.map(str::to_owned),
+            size: decision
+                .scale
+                .and_then(|scale| usvg::Size::new(size.width() * scale, size.height() * scale))
+                .unwrap_or(*size),
+            caption: decision.caption.clone(),
+            alt_text: decision.alt_text.clone(),
+            text: None,
+            headings: Vec::new(),
+            links: Vec::new(),
+            source: self.source.clone(),
+            break_after: false,
+            blank_after: false,
+            ends_section: false,
+        });
+    }
+
+    /// The paper and text area, sized after the first page if it comes from
+    /// the source.
+    fn geometry(&self) -> Geometry {
+        let first = self.pages.first().map_or(self.size, |page| page.size);
+        Geometry::new(&self.options, first)
     }
 
-    fn add_relationship(&mut self, rid: &str, filename: &str) {
-        let target = format!("media/{}", filename);
-        self.rels_string = format!(
-            "{}{}",
-            self.rels_string,
-            format_xml::xml! {
-                <Relationship Id={rid} Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target={target}/>
+    /// The paragraphs and relationships of all pages, numbered in order.
+    fn render_pages(&self) -> (String, Relationships) {
+        let mut doc = String::new();
+        let mut rels = Relationships::new();
+        let mut next_id = 0;
+        let mut rids = Vec::new();
+        let targets: HashSet<usize> = self
+            .pages
+            .iter()
+            .flat_map(|page| &page.links)
+            .filter_map(|link| match link.target {
+                LinkTarget::Page(number) => Some(number),
+                LinkTarget::Uri(_) => None,
+            })
+            .collect();
+        for (i, page) in self.pages.iter().enumerate() {
+            let id = next_id;
+            let svg_rid = page.svg.as_ref().map(|_| format!("rId{}", id));
+            let png_id = if svg_rid.is_some() { id + 1 } else { id };
+            next_id = png_id + 1;
+            if self.options.text_layer == TextLayer::Hidden && page.text.is_some() {
+                // For the text box of the page.
+                next_id = next_id.max(id + 2);
             }
-        )
+            if !page.links.is_empty() {
+                next_id = next_id.max(id + 2 + page.links.len() as i32);
+            }
+            let png_rid = page.png.as_ref().map(|_| format!("rId{}", png_id));
+            if self.options.thumbnail_index {
+                write_bookmark(&mut doc, i, &page_bookmark(i + 1));
+            }
+            if targets.contains(&page.number) {
+                let id = self.pages.len() + i;
+                write_bookmark(&mut doc, id, &link_bookmark(page.number));
+            }
+            write_page(
+                &mut doc,
+                page,
+                &self.options,
+                id,
+                i + 1,
+                svg_rid.as_deref(),
+                png_rid.as_deref(),
+            );
+            if let (Some(svg_rid), Some(svg)) = (&svg_rid, &page.svg) {
+                rels.push(relationship(svg_rid, svg));
+            }
+            if let (Some(png_rid), Some(png)) = (&png_rid, &page.png) {
+                rels.push(relationship(png_rid, png));
+            }
+            for (k, link) in page.links.iter().enumerate() {
+                if let LinkTarget::Uri(uri) = &link.target {
+                    rels.push(hyperlink_relationship(&link_rid(i + 1, k), uri));
+                }
+            }
+            rids.push((svg_rid, png_rid));
+        }
+        if self.options.text_layer == TextLayer::Appendix {
+            write_text_appendix(&mut doc, &self.pages);
+        }
+        if self.options.thumbnail_index && !self.pages.is_empty() {
+            let thumbnails: Vec<_> = self
+                .pages
+                .iter()
+                .zip(&rids)
+                .enumerate()
+                .map(|(i, (page, (svg_rid, png_rid)))| Thumbnail {
+                    id: next_id + i as i32,
+                    position: i + 1,
+                    svg_rid: svg_rid.as_deref(),
+                    png_rid: png_rid.as_deref(),
+                    bookmark: page_bookmark(i + 1),
+                    size: page.size,
+                })
+                .collect();
+            let mut index = String::new();
+            write_thumbnail_index(&mut index, &thumbnails, self.geometry().text.width());
+            doc = index + &doc;
+        }
+        (doc, rels)
     }
 
-    pub fn generate_docx(self, p: &PathBuf) -> Result<()> {
-        self.write_to_files()?;
-        zip_create_from_directory(p, &PathBuf::from(self.dir.path()))?;
+    /// Sets the caption shown below the page of [`Page::number`] `number`,
+    /// its position in the document, replacing any set by the page
+    /// callback.
+    pub fn set_page_caption(&mut self, number: usize, text: impl Into<String>) -> Result<()> {
+        self.page_mut(number)?.caption = Some(text.into());
         Ok(())
     }
 
-    fn write_to_files(&self) -> Result<()> {
-        Docx::insert_in_file(&self.doc, &self.doc_string)?;
-        Docx::insert_in_file(&self.rels, &self.rels_string)?;
-        self.change_size()?;
+    /// Sets the alt text of the page of [`Page::number`] `number`.
+    pub fn set_page_alt_text(&mut self, number: usize, text: impl Into<String>) -> Result<()> {
+        self.page_mut(number)?.alt_text = Some(text.into());
         Ok(())
     }
 
-    fn change_size(&self) -> Result<()> {
-        let s = read_to_string(&self.doc)?
-            .replace(
-                "!WIDTH!",
-                &px_to_twenties_of_pt(self.size.width()).to_string(),
-            )
-            .replace(
-                "!HEIGHT!",
-                &px_to_twenties_of_pt(self.size.height()).to_string(),
-            );
-        write(&self.doc, s)?;
+    /// Sets the text of the page of [`Page::number`] `number`, as
+    /// [`DocxOptions::text_layer`] places it.
+    pub fn set_page_text(&mut self, number: usize, text: impl Into<String>) -> Result<()> {
+        self.page_mut(number)?.text = Some(text.into());
         Ok(())
     }
 
-    fn insert_in_file(path: &Path, content: &str) -> Result<()> {
-        let s = read_to_string(path)?.replace("!INSERT_HERE!", content);
-        write(path, s)?;
+    fn page_mut(&mut self, number: usize) -> Result<&mut Page> {
+        self.pages
+            .iter_mut()
+            .find(|page| page.number == number)
+            .ok_or(Error::PageOutOfRange)
+    }
+
+    /// Lets the converted pages be removed, reordered or separated by breaks
+    /// before the document is generated.
+    pub fn pages(&mut self) -> Pages<'_> {
+        Pages {
+            pages: &mut self.pages,
+            package: &mut self.package,
+        }
+    }
+
+    /// Writes the images of the pages into the DOCX at `path` as they are
+    /// added, instead of keeping them in a temporary directory until
+    /// [`Docx::generate_docx`] zips them, so that they are on disk once.
+    /// The DOCX is `<path>.partial` until `generate_docx` finishes it and
+    /// moves it to `path`, and is removed if the document is dropped
+    /// unfinished. Streamed images cannot be read back, so the pages only go
+    /// to this DOCX, not to a PPTX or ODT, and cannot be taken out of it:
+    /// [`Pages::remove`] fails for the pages whose images are in it.
+    ///
+    /// Returns whether the DOCX is streamed: an encrypted or signed one
+    /// needs every part at the end, so its images stay where they are.
+    #[cfg(feature = "native")]
+    pub fn stream_to(&mut self, path: &Path) -> Result<bool> {
+        if self.options.output_password.is_some() || self.options.signing.is_some() {
+            return Ok(false);
+        }
+        let mut partial = long_path(path).into_os_string();
+        partial.push(".partial");
+        self.package.stream_to(
+            Path::new(&partial),
+            self.options.scrub_metadata,
+            self.options.optimization.deflate_level,
+        )?;
+        Ok(true)
+    }
+
+    /// Writes the DOCX to `p`, or finishes the one [`Docx::stream_to`]
+    /// started and moves it there.
+    #[cfg(feature = "native")]
+    pub fn generate_docx(mut self, p: &Path) -> Result<()> {
+        let p = &long_path(p);
+        let checksum_sidecar = self.options.checksum_sidecar;
+        if let Package::Streaming(_) = self.package {
+            let _span = info_span!("generate_docx").entered();
+            self.write_to_files()?;
+            info!(pages = self.pages.len(), "finishing");
+            let partial = self.package.finish()?;
+            std::fs::rename(&partial, p).map_err(|e| Error::from(e).at(p))?;
+        } else {
+            let file = File::create(p).map_err(|e| Error::from(e).at(p))?;
+            self.generate_docx_to_writer(file).map_err(|e| e.at(p))?;
+        }
+        if checksum_sidecar {
+            write_checksum(p)?;
+        }
         Ok(())
     }
 
-    pub fn convert_pdf(&mut self, pdf: &Path) -> Result<()> {
-        let mut page = 0;
-        let mut images: Vec<PathBuf> = Vec::new();
-        print!("Calling Inkscape to generate images ");
-        loop {
-            page += 1;
-            let image = PathBuf::from(&self.media_dir).join(format! {"{}.svg", page});
-            let output = match Command::new("inkscape")
-                .arg(pdf)
-                .arg(format!("--pdf-page={}", page))
-                .arg("-o")
-                .arg(&image)
-                .arg("--pdf-poppler")
-                .output()
-            {
-                Err(e) => {
-                    return if let ErrorKind::NotFound = e.kind() {
-                        Err(Error::InkscapeNotFound)
-                    } else {
-                        Err(Error::IoError)
-                    };
-                }
-                Ok(output) => (output),
+    /// Writes the zipped DOCX to `writer`, such as the body of a response.
+    /// With [`DocxOptions::in_memory`] the package is zipped straight from
+    /// memory.
+    pub fn generate_docx_to_writer<W: Write + Seek>(mut self, mut writer: W) -> Result<()> {
+        let _span = info_span!("generate_docx").entered();
+        self.write_to_files()?;
+        info!(pages = self.pages.len(), "writing");
+        let scrub_metadata = self.options.scrub_metadata;
+        let deflate_level = self.options.optimization.deflate_level;
+        match &self.options.output_password {
+            Some(password) => {
+                let mut buf = io::Cursor::new(Vec::new());
+                self.package
+                    .write_zip(&mut buf, scrub_metadata, deflate_level)?;
+                writer.write_all(&encryption::encrypt(&buf.into_inner(), password)?)?;
+                Ok(())
+            }
+            None => self
+                .package
+                .write_zip(writer, scrub_metadata, deflate_level),
+        }
+    }
+
+    /// Builds the whole package and returns the zipped DOCX.
+    pub fn to_bytes(self) -> Result<Vec<u8>> {
+        let mut buf = io::Cursor::new(Vec::new());
+        self.generate_docx_to_writer(&mut buf)?;
+        Ok(buf.into_inner())
+    }
+
+    fn write_to_files(&mut self) -> Result<()> {
+        let geometry = self.geometry();
+        if self.options.page_size == PageSize::Source {
+            // Each run of pages of the same size gets a section of its own.
+            let twips = |page: &Page| {
+                (
+                    px_to_twenties_of_pt(page.size.width()),
+                    px_to_twenties_of_pt(page.size.height()),
+                )
             };
-            print!(".");
-            io::stdout().flush()?;
-            if output.stderr.is_empty() {
-                images.push(image);
-                continue;
+            let sizes: Vec<_> = self.pages.iter().map(twips).collect();
+            for (i, page) in self.pages.iter_mut().enumerate() {
+                page.ends_section = sizes.get(i + 1).is_some_and(|next| *next != sizes[i]);
             }
-            remove_file(&image)?;
-            println!(" Done.");
-            break;
-        }
-        print!("Getting the size of the first page ... ");
-        self.size = read_svg(images.get(0).ok_or(Error::PDFInvalid)?)?
-            .svg_node()
-            .size;
-        println!("Done.");
-        print!("Adding all the images ");
-        io::stdout().flush()?;
-        images.iter().try_for_each(|i| self.add_image_svg(i))
+        } else {
+            for page in &mut self.pages {
+                page.size = geometry.fit(page.size);
+            }
+        }
+        let (doc_string, page_rels) = self.render_pages();
+        // The last section is the one of the body.
+        let last = self.pages.last().map_or(self.size, |page| page.size);
+        let paper = Geometry::new(&self.options, last).paper;
+        let decoration = if self.options.decoration.is_empty() {
+            None
+        } else {
+            Some(Decoration::new(&self.options.decoration, paper)?)
+        };
+        let (width, height) = (
+            px_to_twenties_of_pt(paper.width()),
+            px_to_twenties_of_pt(paper.height()),
+        );
+        let orient = if width > height {
+            "landscape"
+        } else {
+            "portrait"
+        };
+        let twips_of_pt = |pt: f64| pt_to_twenties_of_pt(pt).to_string();
+        let margins = self.options.margins;
+        let variables = &self.options.template_variables;
+        let doc = template::fill(
+            &self.read_part(DOCUMENT)?,
+            &[
+                ("pages", &doc_string),
+                ("page_width", &width.to_string()),
+                ("page_height", &height.to_string()),
+                ("page_orient", orient),
+                ("margin_top", &twips_of_pt(margins.top)),
+                ("margin_right", &twips_of_pt(margins.right)),
+                ("margin_bottom", &twips_of_pt(margins.bottom)),
+                ("margin_left", &twips_of_pt(margins.left)),
+            ],
+            variables,
+        )?;
+        let doc = match &decoration {
+            Some(decoration) => reference_sections(&doc, &decoration.references()),
+            None => doc,
+        };
+        self.package.put(DOCUMENT, doc.into_bytes())?;
+        // The template's own relationships come first, with any placeholder
+        // left empty.
+        let rels = template::fill(
+            &self.read_part(DOCUMENT_RELS)?,
+            &[("relationships", "")],
+            variables,
+        )?;
+        let mut document_rels = Relationships::parse(&rels);
+        document_rels.extend(page_rels);
+        let mut types = ContentTypes::parse(&self.read_part(CONTENT_TYPES)?);
+        let mut package_rels = Relationships::parse(&self.read_part(PACKAGE_RELS)?);
+        let watermark_format = match decoration {
+            Some(decoration) => {
+                self.write_decoration(decoration, &mut types, &mut document_rels)?
+            }
+            None => None,
+        };
+        self.package
+            .put(DOCUMENT_RELS, document_rels.to_xml().into_bytes())?;
+        for format in [PictureFormat::Png, PictureFormat::Jpeg, PictureFormat::Tiff] {
+            let extension = format.extension();
+            let used = watermark_format == Some(format)
+                || self
+                    .pages
+                    .iter()
+                    .filter_map(|page| page.png.as_ref())
+                    .any(|png| png.ends_with(&format!(".{}", extension)));
+            if used {
+                types.add_default(extension, format.content_type());
+            }
+        }
+        if self.pages.iter().any(|page| page.svg.is_some()) {
+            types.add_default("svg", "image/svg+xml");
+        }
+        let scrub_metadata = self.options.scrub_metadata;
+        let core = core_properties(&self.metadata());
+        self.package.put(CORE_PROPERTIES, core.into_bytes())?;
+        let app = app_properties("Pages", self.pages.len(), !scrub_metadata);
+        self.package.put(APP_PROPERTIES, app.into_bytes())?;
+        types.add_override(
+            CORE_PROPERTIES,
+            "application/vnd.openxmlformats-package.core-properties+xml",
+        );
+        package_rels.add(Relationship::part(
+            "rIdCoreProperties",
+            parts::CORE_PROPERTIES,
+            CORE_PROPERTIES,
+        ));
+        types.add_override(
+            APP_PROPERTIES,
+            "application/vnd.openxmlformats-officedocument.extended-properties+xml",
+        );
+        package_rels.add(Relationship::part(
+            "rIdAppProperties",
+            parts::EXTENDED_PROPERTIES,
+            APP_PROPERTIES,
+        ));
+        let mut properties = Vec::new();
+        if let Some(label) = &self.options.sensitivity_label {
+            let set_date = (!self.options.scrub_metadata).then(|| utc_timestamp(now()));
+            properties.extend(label.properties(set_date.as_deref()));
+        }
+        if let Some(report) = &self.pdfa {
+            properties.push((String::from("SourcePdfA"), report.to_string()));
+        }
+        if !properties.is_empty() {
+            self.package.put(
+                CUSTOM_PROPERTIES,
+                custom_properties(&properties).into_bytes(),
+            )?;
+            types.add_override(
+                CUSTOM_PROPERTIES,
+                "application/vnd.openxmlformats-officedocument.custom-properties+xml",
+            );
+            package_rels.add(Relationship::part(
+                "rIdCustomProperties",
+                parts::CUSTOM_PROPERTIES,
+                CUSTOM_PROPERTIES,
+            ));
+        }
+        self.package
+            .put(CONTENT_TYPES, types.to_xml().into_bytes())?;
+        self.package
+            .put(PACKAGE_RELS, package_rels.to_xml().into_bytes())?;
+        if let Some(path) = &self.options.a11y_report {
+            let report = a11y::report(&a11y::audit(&self.package)?);
+            write(path, report).map_err(|e| Error::from(e).at(path))?;
+        }
+        if let Some(identity) = &self.options.signing {
+            signing::sign(&mut self.package, identity, self.options.scrub_metadata)?;
+        }
+        Ok(())
+    }
+
+    /// Puts the header and footer parts of `decoration` in the package,
+    /// registering them in `types` and `rels`. Returns the format of its
+    /// watermark image, if any.
+    fn write_decoration(
+        &mut self,
+        decoration: Decoration,
+        types: &mut ContentTypes,
+        rels: &mut Relationships,
+    ) -> Result<Option<PictureFormat>> {
+        rels.extend(decoration.relationships());
+        if let Some(header) = decoration.header {
+            self.package.put(HEADER, header.into_bytes())?;
+            types.add_override(
+                HEADER,
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.header+xml",
+            );
+        }
+        if let Some(footer) = decoration.footer {
+            self.package.put(FOOTER, footer.into_bytes())?;
+            types.add_override(
+                FOOTER,
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.footer+xml",
+            );
+        }
+        if let Some(rels) = decoration.header_rels {
+            self.package.put(HEADER_RELS, rels.into_bytes())?;
+        }
+        let Some((name, format, data)) = decoration.media else {
+            return Ok(None);
+        };
+        self.package.put(&media_part(&name), data)?;
+        Ok(Some(format))
+    }
+
+    /// The metadata given, filled in from the source PDF, dated now unless
+    /// metadata is scrubbed.
+    fn metadata(&self) -> Metadata {
+        let source_metadata = self.source_metadata.clone();
+        let mut metadata = self.options.metadata.clone().or(source_metadata);
+        if !self.options.scrub_metadata {
+            let now = now();
+            metadata.created.get_or_insert(now);
+            metadata.modified.get_or_insert(now);
+        }
+        metadata
+    }
+
+    fn read_part(&self, name: &str) -> Result<String> {
+        String::from_utf8(self.package.get(name)?).map_err(Error::invalid_data)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 mod tests {
     use super::*;
+    use crate::ooxml::PageLink;
     use std::fs::read_dir;
+    use std::path::PathBuf;
 
     fn get_children(fixtures_dir: &Path) -> Result<Vec<PathBuf>> {
         let children: std::result::Result<Vec<_>, _> = read_dir(fixtures_dir)?.collect();
@@ -336,10 +1464,18 @@ mod tests {
         Ok(children)
     }
 
+    fn package_dir(docx: &Docx) -> &Path {
+        match &docx.package {
+            Package::Disk(dir) => dir.path(),
+            Package::Memory(_) => panic!("package is in memory"),
+            Package::Streaming(_) => panic!("package is streamed"),
+        }
+    }
+
     #[test]
     fn test_dir() -> Result<()> {
         let docx = Docx::new().unwrap();
-        let dir = docx.dir.path();
+        let dir = package_dir(&docx);
         assert!(dir.exists());
         let children = get_children(dir)?;
         let mut children_str: Vec<&str> = children
@@ -357,7 +1493,7 @@ mod tests {
     #[test]
     fn test_tmp_dir_drop() {
         let docx = Docx::new().unwrap();
-        let dir = docx.dir.path();
+        let dir = package_dir(&docx);
         let dir_string = String::from(dir.to_str().unwrap());
         drop(docx);
         let should_be_deleted = Path::new(&dir_string);
@@ -376,12 +1512,15 @@ mod tests {
     #[test]
     fn test_add_svg() {
         let mut docx = Docx::new().unwrap();
-        docx.add_image_svg(&get_test_svg()).unwrap();
-        assert_eq!(docx.doc_string,
+        docx.add_svg(&get_test_svg()).unwrap();
+        let (doc_string, rels) = docx.render_pages();
+        let rels_string = rels.elements();
+        assert_eq!(doc_string,
                    format_xml::xml! {
 <w:p>
     <w:pPr>
         <w:widowControl />
+        <w:spacing w:before="0" w:after="0" w:line="240" w:lineRule="auto" />
         <w:jc w:val="left" />
     </w:pPr>
     <w:r>
@@ -392,7 +1531,7 @@ mod tests {
             <wp:inline distT="0" distB="0" distL="0" distR="0">
                 <wp:extent cx="7560000" cy="10692000" />
                 <wp:effectExtent l="0" t="0" r="0" b="0" />
-                <wp:docPr id="0" name="0" />
+                <wp:docPr id="0" name="Page 1" descr="Page 1" />
                 <wp:cNvGraphicFramePr>
                     <a:graphicFrameLocks xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" noChangeAspect="1" />
                 </wp:cNvGraphicFramePr>
@@ -432,30 +1571,537 @@ mod tests {
     </w:r>
 </w:p>
             }.to_string());
-        assert_eq!(docx.rels_string,
+        assert_eq!(rels_string,
                    format_xml::xml! {
-<Relationship Id="rId0" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/2.svg" />
-<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/2.png" />
+<Relationship Id="rId0" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/1.svg" />
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/1.png" />
             }.to_string())
     }
 
     #[test]
     fn test_write_to_file() {
         let mut docx = Docx::new().unwrap();
-        docx.doc_string = String::from("<p></p>");
         docx.write_to_files().unwrap();
     }
 
     #[test]
     fn test_generate_docx() {
         let mut docx = Docx::new().unwrap();
-        docx.add_image_svg(&get_test_svg()).unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
         docx.generate_docx(&PathBuf::from(get_tests_dir() + "a.docx"))
             .unwrap();
     }
 
     #[test]
-    fn test_size() {
-        assert_eq!(px_to_twenties_of_pt(793.707), 11905)
+    fn test_add_png_only() {
+        let mut docx = Docx::new().unwrap();
+        docx.options.scan_cleanup.binarize = true;
+        docx.add_svg(&get_test_svg()).unwrap();
+        let (doc_string, rels) = docx.render_pages();
+        let rels_string = rels.elements();
+        assert!(!doc_string.contains("svgBlip"));
+        assert!(doc_string.contains(r#"<a:blip r:embed="rId0" />"#));
+        assert!(!rels_string.contains("1.svg"));
+    }
+
+    #[test]
+    fn test_hooks() {
+        let mut docx = Docx::new().unwrap();
+        docx.set_svg_hook(|page, _| {
+            assert_eq!(page, 1);
+            Ok(())
+        });
+        docx.set_raster_hook(|_, pixmap| {
+            *pixmap = tiny_skia::Pixmap::new(10, 10).unwrap();
+            Ok(())
+        });
+        docx.add_svg(&get_test_svg()).unwrap();
+        let png = docx.package.get("word/media/1.png").unwrap();
+        assert_eq!(image_dimensions(&png), (10, 10));
+        assert!(docx.package.get("word/media/1.svg").is_ok());
+    }
+
+    #[test]
+    fn test_page_callback() {
+        let mut docx = Docx::new().unwrap();
+        docx.set_page_callback(|info| PageDecision {
+            skip: info.number == 2,
+            caption: Some(String::from("Fish & chips")),
+            alt_text: Some(format!("Page {}", info.number)),
+            raster_only: true,
+            scale: Some(0.5),
+        });
+        docx.add_svg(&get_test_svg()).unwrap();
+        let (doc_string, _) = docx.render_pages();
+        assert!(doc_string.contains(r#"descr="Page 1""#));
+        assert!(doc_string.contains(r#"<wp:extent cx="3780000" cy="5346000" />"#));
+        assert!(doc_string.contains("Fish &amp; chips"));
+        assert!(!doc_string.contains("svgBlip"));
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = std::sync::Arc::clone(&events);
+        docx.set_progress_handler(move |event| sink.lock().unwrap().push(event));
+        docx.add_svg(&get_test_svg()).unwrap();
+        assert_eq!(docx.render_pages().0, doc_string);
+        assert_eq!(*events.lock().unwrap(), [ProgressEvent::PageSkipped(2)]);
+    }
+
+    #[test]
+    fn test_add_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = dir.path().join("scan.jpg");
+        // A JFIF header at 200 dpi and a frame of 400 × 600 pixels.
+        let mut data = b"\xff\xd8\xff\xe0\x00\x10JFIF\0\x01\x01\x01\x00\xc8\x00\xc8\0\0".to_vec();
+        data.extend(b"\xff\xc0\x00\x0b\x08\x02\x58\x01\x90\x01\x01\x11\x00");
+        std::fs::write(&jpeg, &data).unwrap();
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        docx.add_image(&jpeg).unwrap();
+        assert_eq!(docx.package.get("word/media/1.jpeg").unwrap(), data);
+        let page = &docx.pages[0];
+        assert_eq!(page.svg, None);
+        assert_eq!((page.size.width(), page.size.height()), (192.0, 288.0));
+        assert!(matches!(
+            docx.add_image(&get_test_svg()),
+            Err(Error::IoError { path: Some(_), .. })
+        ));
+        docx.write_to_files().unwrap();
+        let types = docx.read_part(CONTENT_TYPES).unwrap();
+        assert_eq!(types.matches("Extension=\"jpeg\"").count(), 1);
+        assert!(!types.contains("Extension=\"tiff\""));
+    }
+
+    #[test]
+    fn test_add_svg_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["slide10.svg", "slide2.SVG", "notes.txt"] {
+            std::fs::copy(get_test_svg(), dir.path().join(name)).unwrap();
+        }
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        docx.add_svg_dir(dir.path()).unwrap();
+        assert_eq!(docx.metrics().len(), 2);
+        assert!(docx.package.get("word/media/2.svg").is_ok());
+        std::fs::write(dir.path().join("notes.txt"), "not an SVG").unwrap();
+        assert!(matches!(
+            docx.add_svg(&dir.path().join("notes.txt")),
+            Err(Error::PageRender { page: 3, .. })
+        ));
+        let mut names = ["a10", "a2", "a02b", "b", "a2a", "a"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["a", "a2", "a2a", "a02b", "a10", "b"]);
+    }
+
+    #[test]
+    fn test_pages() {
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        for i in 1..=3 {
+            docx.add_svg_data(&format!("{}.svg", i), std::fs::read(get_test_svg()).unwrap())
+                .unwrap();
+        }
+        let mut pages = docx.pages();
+        pages.remove(1).unwrap();
+        assert!(matches!(pages.remove(2), Err(Error::PageOutOfRange)));
+        pages.reorder(&[1, 0]).unwrap();
+        assert!(matches!(pages.reorder(&[0, 0]), Err(Error::PageOutOfRange)));
+        pages.insert_break_after(0).unwrap();
+        let numbers: Vec<_> = pages.iter().map(Page::number).collect();
+        assert_eq!(numbers, [3, 1]);
+        assert!(docx.package.get("word/media/2.svg").is_err());
+        let (doc_string, rels) = docx.render_pages();
+        let rels_string = rels.elements();
+        assert_eq!(doc_string.matches(r#"<w:br w:type="page" />"#).count(), 1);
+        assert!(rels_string.starts_with(
+            r#"<Relationship Id="rId0" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/3.svg" />"#
+        ));
+    }
+
+    #[test]
+    fn test_error() {
+        use std::error::Error as _;
+        let missing = io::Error::new(io::ErrorKind::NotFound, "gone");
+        let e = Error::from(missing).at(Path::new("in.pdf")).on_page(3);
+        assert_eq!(e.to_string(), "page 3: in.pdf: gone");
+        assert!(matches!(&e, Error::PageRender { page: 3, .. }));
+        let source = e.source().unwrap().downcast_ref::<Error>().unwrap();
+        assert!(matches!(source, Error::IoError { path: Some(_), .. }));
+        assert_eq!(source.source().unwrap().to_string(), "gone");
+        assert!(matches!(Error::Interrupted.on_page(3), Error::Interrupted));
+        let e = Error::invalid_data("bad").at(Path::new("a"));
+        let e = e.at(Path::new("b"));
+        assert_eq!(e.to_string(), "a: bad");
+    }
+
+    #[test]
+    fn test_set_page_caption_and_alt_text() {
+        let mut docx = Docx::new().unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.set_page_caption(1, "Figure <1>").unwrap();
+        docx.set_page_alt_text(1, "A scanned page").unwrap();
+        assert!(matches!(
+            docx.set_page_caption(2, "Missing"),
+            Err(Error::PageOutOfRange)
+        ));
+        let (doc_string, _) = docx.render_pages();
+        assert!(doc_string.contains("Figure &lt;1&gt;"));
+        assert!(doc_string.contains(r#"descr="A scanned page""#));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_options() {
+        let options: DocxOptions = serde_json::from_str(
+            r#"{"sandbox": "bubblewrap", "limits": {"cpu_seconds": 10}, "max_pages": 3}"#,
+        )
+        .unwrap();
+        assert_eq!(options.sandbox, Sandbox::Bubblewrap);
+        assert_eq!(options.limits.cpu_seconds, Some(10));
+        assert_eq!(options.max_pages, Some(3));
+        assert!(options.external_images);
+        let json = serde_json::to_string(&DocxOptions {
+            output_password: Some(String::from("secret")),
+            ..options
+        })
+        .unwrap();
+        assert!(!json.contains("secret"));
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mut docx = Docx::new().unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        let metrics = docx.metrics();
+        assert_eq!(metrics.len(), 1);
+        assert_eq!(metrics[0].number, 1);
+        assert_eq!(
+            metrics[0].svg_bytes,
+            std::fs::metadata(get_test_svg()).unwrap().len()
+        );
+        assert!(metrics[0].png_bytes > 0);
+    }
+
+    #[test]
+    fn test_thumbnail_index() {
+        let mut docx = Docx::new().unwrap();
+        docx.options.thumbnail_index = true;
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.add_svg_data("3.svg", std::fs::read(get_test_svg()).unwrap())
+            .unwrap();
+        let (doc_string, _) = docx.render_pages();
+        assert!(doc_string.starts_with("<w:p>"));
+        assert!(doc_string.contains(r#"<w:hyperlink w:anchor="page_2" w:history="1">"#));
+        assert!(doc_string.contains(r#"<w:bookmarkStart w:id="1" w:name="page_2" />"#));
+        // Both thumbnails reuse the PNGs of the pages, with new drawing ids.
+        assert_eq!(doc_string.matches(r#"<a:blip r:embed="rId3""#).count(), 2);
+        assert!(doc_string.contains(r#"<wp:docPr id="5" name="Thumbnail 2" descr="Go to page 2" />"#));
+    }
+
+    #[test]
+    fn test_links() {
+        let mut docx = Docx::new().unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.pages[0].links = vec![
+            PageLink {
+                area: [0.0, 0.0, 0.5, 0.5],
+                target: LinkTarget::Uri(String::from("https://example.com/?a&b")),
+            },
+            PageLink {
+                area: [0.5, 0.5, 1.0, 1.0],
+                target: LinkTarget::Page(2),
+            },
+        ];
+        let (doc_string, rels) = docx.render_pages();
+        let rels = rels.elements();
+        assert!(rels.contains(r#"Id="rIdLink1_0""#));
+        assert!(rels.contains(r#"Target="https://example.com/?a&amp;b" TargetMode="External""#));
+        assert!(doc_string.contains(r#"<w:hyperlink r:id="rIdLink1_0""#));
+        assert!(doc_string.contains(r#"<w:hyperlink w:anchor="_Link_page_2""#));
+        assert!(doc_string.contains(r#"<w:bookmarkStart w:id="3" w:name="_Link_page_2" />"#));
+        // The links take the drawing ids after the page's, before the next.
+        assert!(doc_string.contains(r#"<wp:docPr id="3" name="Link 3""#));
+        assert!(doc_string.contains(r#"<wp:docPr id="4" name="Page 2""#));
+    }
+
+    fn image_dimensions(png: &[u8]) -> (u32, u32) {
+        let decoder = png::Decoder::new(png);
+        let info = decoder.read_info().unwrap();
+        (info.info().width, info.info().height)
+    }
+
+    #[test]
+    fn test_metadata() {
+        let mut docx = Docx::builder()
+            .options(DocxOptions {
+                in_memory: true,
+                ..DocxOptions::default()
+            })
+            .metadata(Metadata {
+                title: Some(String::from("Q3 & Q4")),
+                created: Some(0),
+                ..Metadata::default()
+            })
+            .build()
+            .unwrap();
+        docx.source_metadata = Metadata {
+            title: Some(String::from("From the PDF")),
+            author: Some(String::from("Ada")),
+            ..Metadata::default()
+        };
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.write_to_files().unwrap();
+        let core = docx.read_part(CORE_PROPERTIES).unwrap();
+        assert!(core.contains("<dc:title>Q3 &amp; Q4</dc:title>"));
+        assert!(core.contains("<dc:creator>Ada</dc:creator>"));
+        assert!(core.contains(">1970-01-01T00:00:00Z</dcterms:created>"));
+        assert!(core.contains("<dcterms:modified "));
+        assert!(docx
+            .read_part(APP_PROPERTIES)
+            .unwrap()
+            .contains("<Pages>1</Pages>"));
+        let rels = docx.read_part(PACKAGE_RELS).unwrap();
+        assert_eq!(rels.matches("Target=\"docProps/core.xml\"").count(), 1);
+        assert!(docx
+            .read_part(CONTENT_TYPES)
+            .unwrap()
+            .contains("PartName=\"/docProps/app.xml\""));
+        assert!(a11y::audit(&docx.package)
+            .unwrap()
+            .iter()
+            .all(|issue| *issue != A11yIssue::MissingTitle));
+    }
+
+    #[test]
+    fn test_scrub_metadata() {
+        let mut docx = Docx::new().unwrap();
+        docx.options.scrub_metadata = true;
+        docx.add_svg(&get_test_svg()).unwrap();
+        let svg = String::from_utf8(docx.package.get("word/media/1.svg").unwrap()).unwrap();
+        assert!(!svg.contains("sodipodi"));
+        let dst = tempfile::NamedTempFile::new().unwrap();
+        docx.generate_docx(dst.path()).unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(dst.path()).unwrap()).unwrap();
+        assert_eq!(zip.by_index(0).unwrap().name(), "[Content_Types].xml");
+        for i in 0..zip.len() {
+            let time = zip.by_index(i).unwrap().last_modified();
+            assert_eq!((time.year(), time.month(), time.day()), (1980, 1, 1));
+        }
+        let mut core = String::new();
+        io::Read::read_to_string(&mut zip.by_name("docProps/core.xml").unwrap(), &mut core)
+            .unwrap();
+        assert!(!core.contains("dcterms:"));
+    }
+
+    #[test]
+    fn test_stream_to() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out.docx");
+        let partial = dir.path().join("out.docx.partial");
+        let mut docx = Docx::new().unwrap();
+        docx.options.scrub_metadata = true;
+        assert!(docx.stream_to(&output).unwrap());
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        assert!(partial.exists());
+        assert!(docx.package.get("word/media/1.png").is_err());
+        // Streamed images cannot be taken out again.
+        assert!(docx.pages().remove(1).is_err());
+        assert_eq!(docx.pages().len(), 2);
+        docx.generate_docx(&output).unwrap();
+        assert!(!partial.exists());
+        let mut zip = zip::ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        assert_eq!(zip.by_index(0).unwrap().name(), "word/media/1.png");
+        for name in [DOCUMENT, DOCUMENT_RELS, CONTENT_TYPES, "word/media/2.svg"] {
+            assert!(zip.by_name(name).is_ok(), "{}", name);
+        }
+
+        let mut docx = Docx::new().unwrap();
+        docx.stream_to(&output).unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        drop(docx);
+        assert!(!partial.exists());
+        let mut docx = Docx::new().unwrap();
+        docx.options.output_password = Some(String::from("secret"));
+        assert!(!docx.stream_to(&output).unwrap());
+    }
+
+    #[test]
+    fn test_builder() {
+        let mut docx = Docx::builder()
+            .options(DocxOptions {
+                in_memory: true,
+                ..DocxOptions::default()
+            })
+            .page_size(PageSize::Letter)
+            .landscape(true)
+            .margins(Margins::uniform(36.0))
+            .raster_dpi(48.0)
+            .build()
+            .unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        let png = docx.package.get("word/media/1.png").unwrap();
+        assert_eq!(image_dimensions(&png), (397, 562));
+        docx.write_to_files().unwrap();
+        let doc = String::from_utf8(docx.package.get(DOCUMENT).unwrap()).unwrap();
+        assert!(doc.contains(r#"<w:pgSz w:w="15840" w:h="12240""#));
+        assert!(doc.contains(r#"<w:pgMar w:top="720" w:right="720" w:bottom="720" w:left="720""#));
+        // The A4 page is scaled to the 7.5in height of the text area.
+        assert!(doc.contains(r#"<wp:extent cx="4849090" cy="6858000" />"#));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let svg_data = std::fs::read(get_test_svg()).unwrap();
+        let mut docx = Docx::builder().raster_dpi(150.0).optimize().build().unwrap();
+        assert_eq!(docx.options.raster_dpi, OPTIMIZED_DPI);
+        docx.add_svg(&get_test_svg()).unwrap();
+        let png = docx.package.get("word/media/1.png").unwrap();
+        let svg = docx.package.get("word/media/1.svg").unwrap();
+        assert!(svg.len() < svg_data.len());
+        assert_eq!(docx.rendered_bytes(), (svg_data.len() + png.len()) as u64);
+        let optimized = docx.to_bytes().unwrap();
+
+        let mut docx = Docx::builder().raster_dpi(150.0).build().unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        assert!(optimized.len() < docx.to_bytes().unwrap().len());
+
+        let mut docx = Docx::builder().no_png_fallback().build().unwrap();
+        docx.options.thumbnail_index = true;
+        docx.add_svg(&get_test_svg()).unwrap();
+        assert!(docx.package.get("word/media/1.png").is_err());
+        assert_eq!(docx.metrics()[0].png_bytes, 0);
+        let (doc_string, rels) = docx.render_pages();
+        let rels = rels.elements();
+        assert!(!doc_string.contains("<a:blip r:embed"));
+        assert_eq!(doc_string.matches(r#"<asvg:svgBlip"#).count(), 2);
+        assert!(rels.contains(r#"Target="media/1.svg""#));
+        assert!(!rels.contains(".png"));
+    }
+
+    #[test]
+    fn test_mixed_page_sizes() {
+        let mut docx = Docx::builder()
+            .preserve_page_sizes()
+            .margins(Margins::uniform(10.0))
+            .build()
+            .unwrap();
+        let landscape = br#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300"/>"#;
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.add_svg_data("3.svg", landscape.to_vec()).unwrap();
+        docx.add_svg_data("4.svg", landscape.to_vec()).unwrap();
+        docx.write_to_files().unwrap();
+        let doc = String::from_utf8(docx.package.get(DOCUMENT).unwrap()).unwrap();
+        let sections: Vec<_> = doc.match_indices("<w:sectPr>").map(|(i, _)| i).collect();
+        assert_eq!(sections.len(), 2);
+        // The paragraph of the A4 page closes its section; the landscape pages
+        // share the one of the body.
+        assert!(doc[sections[0]..].starts_with(
+            r#"<w:sectPr><w:type w:val="nextPage" /><w:pgSz w:w="12305" w:h="17237" w:orient="portrait" />"#
+        ));
+        assert_eq!(doc[..sections[0]].matches("<w:p>").count(), 1);
+        assert!(doc.contains(r#"<w:pgSz w:w="6400" w:h="4900" w:orient="landscape"/>"#));
+    }
+
+    #[test]
+    fn test_decoration() {
+        let dir = tempfile::tempdir().unwrap();
+        let jpeg = dir.path().join("logo.jpg");
+        let mut data = b"\xff\xd8\xff\xe0\x00\x10JFIF\0\x01\x01\x01\x00\xc8\x00\xc8\0\0".to_vec();
+        data.extend(b"\xff\xc0\x00\x0b\x08\x02\x58\x01\x90\x01\x01\x11\x00");
+        std::fs::write(&jpeg, &data).unwrap();
+        let mut docx = Docx::builder()
+            .preserve_page_sizes()
+            .footer("Page {page} of {pages}")
+            .watermark(Watermark::Image(jpeg))
+            .build()
+            .unwrap();
+        let landscape = br#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300"/>"#;
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.add_svg_data("2.svg", landscape.to_vec()).unwrap();
+        docx.write_to_files().unwrap();
+        let doc = docx.read_part(DOCUMENT).unwrap();
+        let references = r#"<w:sectPr><w:headerReference w:type="default" r:id="rIdHeader1"/><w:footerReference w:type="default" r:id="rIdFooter1"/>"#;
+        assert_eq!(doc.matches(references).count(), 2);
+        let rels = docx.read_part(DOCUMENT_RELS).unwrap();
+        assert!(rels.contains(r#"Id="rIdHeader1""#) && rels.contains(r#"Id="rIdFooter1""#));
+        let part = |name| docx.read_part(name).unwrap();
+        assert!(part(FOOTER).contains(r#"w:instr=" NUMPAGES ""#));
+        assert!(part(HEADER).contains(r#"r:id="rIdWatermark""#));
+        assert!(part(HEADER_RELS).contains(r#"Target="media/watermark.jpeg""#));
+        assert_eq!(docx.package.get("word/media/watermark.jpeg").unwrap(), data);
+        let types = docx.read_part(CONTENT_TYPES).unwrap();
+        assert!(types.contains(r#"PartName="/word/header1.xml""#));
+        assert!(types.contains(r#"PartName="/word/footer1.xml""#));
+        assert!(types.contains(r#"Extension="jpeg""#));
+    }
+
+    #[test]
+    fn test_in_memory() {
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let mut docx = Docx::with_options(options).unwrap();
+        assert!(docx.scratch.is_none());
+        docx.add_svg(&get_test_svg()).unwrap();
+        let bytes = docx.to_bytes().unwrap();
+        let mut zip = zip::ZipArchive::new(io::Cursor::new(bytes)).unwrap();
+        assert!(zip.by_name("word/media/1.svg").is_ok());
+        assert!(zip.by_name("word/media/1.png").is_ok());
+        let mut doc = String::new();
+        io::Read::read_to_string(&mut zip.by_name("word/document.xml").unwrap(), &mut doc).unwrap();
+        assert!(doc.contains("svgBlip"));
+        assert!(!doc.contains("!INSERT_HERE!"));
+    }
+
+    #[test]
+    fn test_generate_docx_to_writer() {
+        let options = DocxOptions {
+            in_memory: true,
+            scrub_metadata: true,
+            ..DocxOptions::default()
+        };
+        let new_docx = || {
+            let mut docx = Docx::with_options(options.clone()).unwrap();
+            docx.add_svg(&get_test_svg()).unwrap();
+            docx
+        };
+        let mut buf = io::Cursor::new(Vec::new());
+        new_docx().generate_docx_to_writer(&mut buf).unwrap();
+        assert_eq!(buf.into_inner(), new_docx().to_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_sensitivity_label() {
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            scrub_metadata: true,
+            sensitivity_label: Some(SensitivityLabel {
+                name: "Confidential & Internal".to_owned(),
+                id: "00000000-0000-0000-0000-000000000001".to_owned(),
+                site_id: None,
+            }),
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        docx.add_svg(&get_test_svg()).unwrap();
+        docx.write_to_files().unwrap();
+        let custom = docx.read_part(CUSTOM_PROPERTIES).unwrap();
+        assert!(custom.contains(
+            r#"name="MSIP_Label_00000000-0000-0000-0000-000000000001_Name"><vt:lpwstr>Confidential &amp; Internal<"#
+        ));
+        assert!(!custom.contains("SetDate"));
+        assert!(!custom.contains("SourcePdfA"));
+        assert!(docx.read_part(PACKAGE_RELS).unwrap().contains("docProps/custom.xml"));
+        assert!(docx.read_part(CONTENT_TYPES).unwrap().contains("/docProps/custom.xml"));
     }
 }
@@ -14,43 +14,436 @@
    along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use clap::Parser;
 use docx_you_want as dyw;
-use docx_you_want::Error;
-use std::env::args;
+use docx_you_want::{Backend, DocxOptions, Error, SigningIdentity};
+use std::fs::read_to_string;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
+mod cli;
+mod console;
+mod i18n;
+
+use cli::{Cli, Command, ConvertArgs, Format};
+use console::{Console, Progress};
+use i18n::message;
+
+/// `print!` unless `--quiet` was given, flushing so that the text shows
+/// before the long step that follows.
+macro_rules! status {
+    ($quiet:expr, $($arg:tt)*) => {
+        if !$quiet {
+            print!($($arg)*);
+            io::stdout().flush()?;
+        }
+    };
+}
+
 fn main() {
-    let args: Vec<_> = args().collect();
-    if args.len() != 3 {
+    dyw::handle_interrupts();
+    let cli = Cli::parse();
+    i18n::init(cli.lang.as_deref());
+    let verbose = match &cli.command {
+        Some(
+            Command::Batch { convert, .. }
+            | Command::Merge { convert, .. }
+            | Command::Images { convert, .. },
+        ) => convert.verbose,
+        _ => cli.convert.verbose,
+    };
+    if verbose {
+        tracing_subscriber::fmt()
+            .with_writer(io::stderr)
+            .with_max_level(tracing::Level::DEBUG)
+            .init();
+    }
+    match &cli.command {
+        Some(Command::PdfaCheck { pdf }) => pdfa_check(pdf),
+        Some(Command::Batch {
+            convert,
+            input,
+            output,
+        }) => {
+            let options = options(convert).unwrap_or_else(|e| fail(e));
+            convert_batch(options, input, output, convert.quiet)
+        }
+        Some(Command::Merge {
+            convert,
+            pdfs,
+            docx,
+        }) => {
+            let options = options(convert).unwrap_or_else(|e| fail(e));
+            merge(options, pdfs, docx, convert)
+        }
+        Some(Command::Images {
+            convert,
+            images,
+            docx,
+        }) => {
+            let options = options(convert).unwrap_or_else(|e| fail(e));
+            add_images(options, images, docx, convert)
+        }
+        None => {}
+    }
+    let args = &cli.convert;
+    let options = options(args).unwrap_or_else(|e| fail(e));
+    #[cfg(feature = "upload")]
+    let upload = args
+        .upload
+        .as_deref()
+        .map(dyw::Upload::from_env)
+        .transpose()
+        .unwrap_or_else(|e| fail(e));
+    // The PDF is required without a subcommand, and the output unless
+    // nothing is written.
+    let Some(src) = &cli.pdf else { unreachable!() };
+    if cli.dry_run {
+        dry_run(options, src)
+    }
+    let Some(dst) = &cli.docx else { unreachable!() };
+    if let Err(e) = dyw::prepare_output(dst) {
+        fail(e)
+    }
+    let lock = dyw::lock_output(dst, args.wait_for_lock).unwrap_or_else(|e| fail(e));
+    let result = convert(options, src, dst, cli.format, args);
+    #[cfg(feature = "upload")]
+    let result = result.and_then(|()| {
+        if let Some(upload) = &upload {
+            status!(args.quiet, "{}", message("uploading", &[]));
+            upload.put(dst)?;
+            status!(args.quiet, "{}\n", message("done-generating", &[]));
+        }
+        Ok(())
+    });
+    // `fail` exits without running destructors.
+    drop(lock);
+    if let Err(e) = result {
+        fail(e)
+    }
+}
+
+/// The options `args` ask for, with the files they name read and the
+/// template checked.
+fn options(args: &ConvertArgs) -> dyw::Result<DocxOptions> {
+    let mut options = args.options();
+    if let (Some(key), Some(cert)) = (&args.sign_key, &args.sign_cert) {
+        options.signing = Some(signing_identity(key, cert)?);
+    }
+    #[cfg(feature = "lua")]
+    if let Some(script) = &args.page_script {
+        let source = read_to_string(script).map_err(|e| Error::from(e).at(script))?;
+        options.page_script = Some(source);
+    }
+    let problems = dyw::check_template(&options)?;
+    if !problems.is_empty() {
+        for problem in problems {
+            eprintln!("{}", problem);
+        }
+        return Err(Error::TemplateInvalid);
+    }
+    Ok(options)
+}
+
+fn fail(e: Error) -> ! {
+    eprint!("{}", describe(&e));
+    exit(exit_code(&e));
+}
+
+/// The exit status for `e`, as listed in `--help`.
+fn exit_code(e: &Error) -> i32 {
+    match e {
+        Error::PageRender { source, .. } => exit_code(source),
+        Error::PDFInvalid | Error::PageOutOfRange | Error::PdfEncrypted => 3,
+        Error::InkscapeNotFound
+        | Error::SandboxNotFound
+        | Error::TesseractNotFound
+        | Error::RendererNotFound => 4,
+        Error::RendererKilled
+        | Error::LimitExceeded
+        | Error::TimeBudgetExceeded
+        | Error::PageTimeout { .. } => 5,
+        Error::OutputNotWritable
+        | Error::OutputLocked
+        | Error::UploadInvalid
+        | Error::UploadFailed => 6,
+        Error::Interrupted => 130,
+        _ => 1,
+    }
+}
+
+/// The message for `e`, with the page, file and cause it carries.
+fn describe(e: &Error) -> String {
+    let id = match e {
+        Error::PageRender { page, source } => {
+            let page = page.to_string();
+            let error = describe(source);
+            return message("error-page", &[("page", &page), ("error", &error)]);
+        }
+        Error::PageTimeout { page } => {
+            let page = page.to_string();
+            return message("error-page-timeout", &[("page", &page)]);
+        }
+        Error::IoError {
+            path: Some(path),
+            source,
+        } => {
+            let path = path.display().to_string();
+            let cause = source.to_string();
+            return message("error-io-path", &[("path", &path), ("cause", &cause)]);
+        }
+        Error::IoError { path: None, source } => {
+            let error = message("error-io", &[]);
+            let cause = source.to_string();
+            return message("error-cause", &[("error", &error), ("cause", &cause)]);
+        }
+        Error::ImageError {
+            source: Some(source),
+        } => {
+            let error = message("error-image", &[]);
+            let cause = source.to_string();
+            return message("error-cause", &[("error", &error), ("cause", &cause)]);
+        }
+        Error::ImageError { source: None } => "error-image",
+        Error::InkscapeNotFound => "error-inkscape-not-found",
+        Error::PDFInvalid => "error-pdf-invalid",
+        Error::SandboxNotFound => "error-sandbox-not-found",
+        Error::RendererKilled => "error-renderer-killed",
+        Error::LimitExceeded => "error-limit-exceeded",
+        Error::TimeBudgetExceeded => "error-time-budget-exceeded",
+        Error::InvalidSigningKey => "error-invalid-signing-key",
+        Error::OutputNotWritable => "error-output-not-writable",
+        Error::PageOutOfRange => "error-page-out-of-range",
+        Error::TemplateInvalid => "error-template-invalid",
+        Error::Interrupted => "error-interrupted",
+        Error::OutputLocked => "error-output-locked",
+        Error::PluginInvalid => "error-plugin-invalid",
+        Error::PluginFailed => "error-plugin-failed",
+        Error::ScriptInvalid => "error-script-invalid",
+        Error::ScriptFailed => "error-script-failed",
+        Error::UploadInvalid => "error-upload-invalid",
+        Error::UploadFailed => "error-upload-failed",
+        Error::TesseractNotFound => "error-tesseract-not-found",
+        Error::RendererNotFound => "error-renderer-not-found",
+        Error::PdfEncrypted => "error-pdf-encrypted",
+    };
+    message(id, &[])
+}
+
+/// Reports what converting `pdf` would take, and exits.
+fn dry_run(options: DocxOptions, pdf: &Path) -> ! {
+    let report = dyw::Docx::with_options(options)
+        .and_then(|docx| docx.analyze_pdf(pdf))
+        .unwrap_or_else(|e| fail(e))
+        .to_string();
+    let file = pdf.display().to_string();
+    println!(
+        "{}",
+        message("dry-run-result", &[("file", &file), ("report", &report)])
+    );
+    exit(0)
+}
+
+fn pdfa_check(pdf: &Path) -> ! {
+    let report = dyw::check_pdfa(pdf).unwrap_or_else(|e| fail(e));
+    let result = report.to_string();
+    let file = pdf.display().to_string();
+    println!(
+        "{}",
+        message("pdfa-result", &[("file", &file), ("result", &result)])
+    );
+    exit(if report.is_compliant() { 0 } else { 1 })
+}
+
+fn signing_identity(key: &Path, cert: &Path) -> dyw::Result<SigningIdentity> {
+    let key = read_to_string(key).map_err(|_| Error::InvalidSigningKey)?;
+    let cert = read_to_string(cert).map_err(|_| Error::InvalidSigningKey)?;
+    SigningIdentity::from_pem(&key, &cert)
+}
+
+/// Writes the `images` into `dst` as pages, and exits.
+fn add_images(options: DocxOptions, images: &[PathBuf], dst: &Path, args: &ConvertArgs) -> ! {
+    let quiet = args.quiet;
+    let run = || -> dyw::Result<()> {
+        dyw::prepare_output(dst)?;
+        let _lock = dyw::lock_output(dst, args.wait_for_lock)?;
+        let mut docx = dyw::Docx::with_options(options)?;
+        for image in images {
+            docx.add_image(image)?;
+        }
+        let rendered = docx.rendered_bytes();
+        status!(quiet, "{}", message("generating", &[]));
+        docx.generate_docx(dst)?;
+        status!(quiet, "{}\n", message("done-generating", &[]));
+        if args.optimize {
+            report_size(rendered, dst, quiet)?;
+        }
+        Ok(())
+    };
+    if let Err(e) = run() {
+        fail(e)
+    }
+    exit(0)
+}
+
+/// Converts `pdfs` one after the other into `dst`, and exits.
+fn merge(options: DocxOptions, pdfs: &[PathBuf], dst: &Path, args: &ConvertArgs) -> ! {
+    let quiet = args.quiet;
+    let renderer = renderer_name(options.backend);
+    let run = || -> dyw::Result<()> {
+        dyw::prepare_output(dst)?;
+        let _lock = dyw::lock_output(dst, args.wait_for_lock)?;
+        let mut docx = dyw::Docx::with_options(options)?;
+        docx.stream_to(dst)?;
+        let converting = message("converting", &[("renderer", renderer)]);
+        status!(quiet, "{}\n", converting);
+        for pdf in pdfs {
+            docx.append_pdf(pdf)?;
+            let file = pdf.display().to_string();
+            status!(quiet, "{}\n", message("batch-done", &[("file", &file)]));
+        }
+        let rendered = docx.rendered_bytes();
+        status!(quiet, "{}", message("generating", &[]));
+        docx.generate_docx(dst)?;
+        status!(quiet, "{}\n", message("done-generating", &[]));
+        if args.optimize {
+            report_size(rendered, dst, quiet)?;
+        }
+        Ok(())
+    };
+    if let Err(e) = run() {
+        fail(e)
+    }
+    exit(0)
+}
+
+/// Converts the PDFs under `src` into `dst`, `options.jobs` at a time, and
+/// exits with whether all of them were.
+fn convert_batch(mut options: DocxOptions, src: &Path, dst: &Path, quiet: bool) -> ! {
+    let jobs = options.jobs;
+    options.jobs = 1;
+    let results = dyw::convert_batch(src, dst, &options, jobs, |done| {
+        let file = done.source.display().to_string();
+        match &done.result {
+            Ok(()) if quiet => {}
+            Ok(()) => println!("{}", message("batch-done", &[("file", &file)])),
+            Err(e) => {
+                let error = describe(e);
+                eprintln!(
+                    "{}",
+                    message("batch-failed", &[("file", &file), ("error", &error)])
+                )
+            }
+        }
+    })
+    .unwrap_or_else(|e| fail(e));
+    let failed = results.iter().filter(|done| done.result.is_err()).count();
+    let interrupted = results
+        .iter()
+        .any(|done| matches!(done.result, Err(Error::Interrupted)));
+    let total = results.len();
+    if !quiet {
         println!(
-            "Usage: {} <path to PDF> <path to result DOCX file>",
-            args[0]
+            "{}",
+            message(
+                "batch-summary",
+                &[
+                    ("converted", &(total - failed).to_string()),
+                    ("total", &total.to_string()),
+                    ("failed", &failed.to_string()),
+                ]
+            )
         );
-        exit(-1)
-    }
-    let src = Path::new(&args[1]);
-    let dst = Path::new(&args[2]);
-    if let Err(e) = convert(src, dst) {
-        let msg = match e {
-            Error::IoError => "An error occurred during I/O.",
-            Error::ImageError => "Something went wrong while processing the images.",
-            Error::InkscapeNotFound => "Inkscape not found. Consider installing inkscape?",
-            Error::PDFInvalid => "Invalid PDF.",
-        };
-        eprint!("{}", msg);
-        exit(-1);
-    }
-}
-
-fn convert(src: &Path, dst: &Path) -> dyw::Result<()> {
-    let mut docx = dyw::Docx::new()?;
-    docx.convert_pdf(src)?;
-    println!("Done");
-    print!("Generating the final result ... ");
-    io::stdout().flush()?;
-    docx.generate_docx(&dst.to_owned())?;
-    println!("Done.");
+    }
+    exit(match failed {
+        0 => 0,
+        _ if interrupted => exit_code(&Error::Interrupted),
+        _ => 1,
+    })
+}
+
+/// The name of the program `backend` renders pages with, for messages.
+fn renderer_name(backend: Backend) -> &'static str {
+    match backend {
+        Backend::Inkscape => "Inkscape",
+        Backend::Pdftocairo => "pdftocairo",
+        Backend::Mutool => "mutool",
+        #[cfg(feature = "pdfium")]
+        Backend::Pdfium => "PDFium",
+    }
+}
+
+/// Writes the pages of `docx` to `dst` as `format` asks.
+fn generate(docx: dyw::Docx, dst: &Path, format: Format) -> dyw::Result<()> {
+    match format {
+        Format::Docx => docx.generate_docx(dst),
+        Format::Pptx => dyw::Pptx::from(docx).generate_pptx(dst),
+        Format::Odt => dyw::Odt::from(docx).generate_odt(dst),
+    }
+}
+
+fn convert(
+    options: DocxOptions,
+    src: &Path,
+    dst: &Path,
+    format: Format,
+    args: &ConvertArgs,
+) -> dyw::Result<()> {
+    let renderer = renderer_name(options.backend);
+    let quiet = args.quiet;
+    let mut docx = dyw::Docx::with_options(options)?;
+    if format == Format::Docx {
+        docx.stream_to(dst)?;
+    }
+    let (progress, interval) = args.progress();
+    let converting = message("converting", &[("renderer", renderer)]);
+    if progress == Progress::Plain {
+        // Heartbeat lines follow, each on a line of its own.
+        status!(quiet, "{}\n", converting);
+    } else {
+        status!(quiet, "{}", converting);
+    }
+    let console = Console::start(progress, &converting, interval);
+    docx.set_progress_handler(console.handler());
+    let result = docx.convert_pdf_pages(src, &args.pages());
+    drop(console);
+    match result {
+        Err(Error::Interrupted) if args.partial_on_interrupt => {
+            status!(quiet, "{}\n", message("interrupted", &[]));
+            status!(quiet, "{}", message("writing-partial", &[]));
+            generate(docx, dst, format)?;
+            status!(quiet, "{}\n", message("done-generating", &[]));
+            return Err(Error::Interrupted);
+        }
+        result => result?,
+    }
+    status!(quiet, "{}\n", message("done", &[]));
+    let rendered = docx.rendered_bytes();
+    status!(quiet, "{}", message("generating", &[]));
+    generate(docx, dst, format)?;
+    status!(quiet, "{}\n", message("done-generating", &[]));
+    if args.optimize {
+        report_size(rendered, dst, quiet)?;
+    }
+    Ok(())
+}
+
+/// Prints how large the pages were as rendered and how large the file made
+/// of them is.
+fn report_size(rendered: u64, dst: &Path, quiet: bool) -> dyw::Result<()> {
+    let written = std::fs::metadata(dst)
+        .map_err(|e| Error::from(e).at(dst))?
+        .len();
+    let size = |bytes: u64| match bytes {
+        0..=999_999 => format!("{:.1} kB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    };
+    let (before, after) = (size(rendered), size(written));
+    status!(
+        quiet,
+        "{}\n",
+        message("size-report", &[("before", &before), ("after", &after)])
+    );
     Ok(())
 }
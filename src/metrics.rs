@@ -0,0 +1,92 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Per-page timings and sizes, for finding the pages that are slow or huge.
+
+use crate::Result;
+use std::io::Write;
+use std::time::Duration;
+
+/// How long a page took and how large its images came out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PageMetrics {
+    /// The [`Page::number`](crate::Page::number) of the page.
+    pub number: usize,
+    /// Time spent in the renderer, zero for SVGs added directly.
+    pub render: Duration,
+    /// Time spent parsing the SVG and rasterizing the PNG fallback.
+    pub raster: Duration,
+    pub svg_bytes: u64,
+    pub png_bytes: u64,
+    /// The mean confidence of the text recognized on the page, in percent,
+    /// if [`DocxOptions::ocr`](crate::DocxOptions::ocr) recognized any.
+    pub ocr_confidence: Option<u8>,
+}
+
+/// Writes `metrics` as CSV with a header row, times in milliseconds.
+pub(crate) fn write_csv(mut out: impl Write, metrics: &[PageMetrics]) -> Result<()> {
+    writeln!(
+        out,
+        "page,render_ms,raster_ms,svg_bytes,png_bytes,ocr_confidence"
+    )?;
+    for page in metrics {
+        let confidence = page.ocr_confidence.map(|c| c.to_string());
+        writeln!(
+            out,
+            "{},{},{},{},{},{}",
+            page.number,
+            page.render.as_millis(),
+            page.raster.as_millis(),
+            page.svg_bytes,
+            page.png_bytes,
+            confidence.as_deref().unwrap_or("")
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_csv() {
+        let mut csv = Vec::new();
+        let metrics = [
+            PageMetrics {
+                number: 1,
+                render: Duration::from_millis(1500),
+                raster: Duration::from_micros(2700),
+                svg_bytes: 1024,
+                png_bytes: 2048,
+                ocr_confidence: None,
+            },
+            PageMetrics {
+                number: 2,
+                ocr_confidence: Some(91),
+                ..PageMetrics::default()
+            },
+        ];
+        write_csv(&mut csv, &metrics).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "page,render_ms,raster_ms,svg_bytes,png_bytes,ocr_confidence\n\
+             1,1500,2,1024,2048,\n\
+             2,0,0,0,0,91\n"
+        );
+    }
+}
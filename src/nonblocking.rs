@@ -0,0 +1,84 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Converting from async code, such as a web service, without holding up
+//! its worker threads for the minutes a large PDF takes.
+
+use crate::{Docx, DocxOptions, Error, Result};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+impl Docx {
+    /// Converts `pdf` into a new document with `options` like
+    /// [`Docx::convert_pdf`] and returns it like [`Docx::to_bytes`], on
+    /// Tokio's blocking threads. Once `cancel` is cancelled, or the future is
+    /// dropped, the renderer is killed and it returns
+    /// [`Error::Interrupted`]. Must be called within a Tokio runtime.
+    pub async fn convert_pdf_async(
+        options: DocxOptions,
+        pdf: PathBuf,
+        cancel: CancellationToken,
+    ) -> Result<Vec<u8>> {
+        let cancelled: Arc<AtomicBool> = Arc::default();
+        // A child, so that dropping the future does not cancel the caller's
+        // token.
+        let cancel = cancel.child_token();
+        let _guard = cancel.clone().drop_guard();
+        tokio::spawn({
+            let cancelled = Arc::clone(&cancelled);
+            async move {
+                cancel.cancelled().await;
+                cancelled.store(true, Ordering::SeqCst);
+            }
+        });
+        let task = tokio::task::spawn_blocking(move || {
+            let mut docx = Docx::with_options(options)?;
+            docx.cancelled = cancelled;
+            docx.convert_pdf(&pdf)?;
+            docx.to_bytes()
+        });
+        match task.await {
+            Ok(result) => result,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            // The runtime is shutting down.
+            Err(_) => Err(Error::Interrupted),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_pdf_async() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let cancel = CancellationToken::new();
+        let result = runtime.block_on(Docx::convert_pdf_async(
+            DocxOptions::default(),
+            dir.path().join("missing.pdf"),
+            cancel.clone(),
+        ));
+        assert!(matches!(result, Err(Error::IoError { .. })));
+        // Only the conversion's own token is cancelled when it ends.
+        assert!(!cancel.is_cancelled());
+    }
+}
@@ -0,0 +1,178 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Text recognition of pages that have no text of their own, as scanned
+//! PDFs, laid out like `pdftotext -layout` so that the text layer can place
+//! it over the words it was read from.
+
+use crate::ooxml::CHARACTER_WIDTH;
+
+/// Recognize the text of pages for which `pdftotext` finds none with
+/// `tesseract`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Ocr {
+    /// The Tesseract languages the pages are in, such as `eng` or `deu`.
+    pub languages: Vec<String>,
+}
+
+impl Default for Ocr {
+    fn default() -> Ocr {
+        Ocr {
+            languages: vec![String::from("eng")],
+        }
+    }
+}
+
+impl Ocr {
+    /// The `-l` argument of `tesseract`.
+    pub(crate) fn language_arg(&self) -> String {
+        if self.languages.is_empty() {
+            String::from("eng")
+        } else {
+            self.languages.join("+")
+        }
+    }
+}
+
+/// The text of a page as recognized.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Recognized {
+    /// The words at their places on a grid of Courier characters as large
+    /// as the page.
+    pub(crate) text: String,
+    /// The mean confidence of the words, in percent.
+    pub(crate) confidence: Option<u8>,
+}
+
+/// A word of Tesseract's TSV output, in pixels of the page image.
+struct Word<'a> {
+    left: f64,
+    top: f64,
+    width: f64,
+    confidence: f64,
+    text: &'a str,
+}
+
+/// Lays out the words of Tesseract's TSV output for a page image of
+/// `width` by `height` pixels.
+pub(crate) fn layout(tsv: &str, width: u32, height: u32) -> Recognized {
+    let words: Vec<Word> = tsv.lines().filter_map(word).collect();
+    let confidence = (!words.is_empty()).then(|| {
+        let sum: f64 = words.iter().map(|word| word.confidence).sum();
+        (sum / words.len() as f64).round().clamp(0.0, 100.0) as u8
+    });
+    let mut widths: Vec<f64> = words
+        .iter()
+        .map(|word| word.width / word.text.chars().count() as f64)
+        .collect();
+    widths.sort_by(f64::total_cmp);
+    let Some(&column_width) = widths.get(widths.len() / 2).filter(|&&w| w > 0.0) else {
+        return Recognized {
+            text: String::new(),
+            confidence,
+        };
+    };
+    let line_height = column_width / CHARACTER_WIDTH;
+    // As many lines as fill the page, so the text layer scales the grid to it.
+    let mut lines =
+        vec![Vec::<char>::new(); (height as f64 / line_height).ceil().max(1.0) as usize];
+    let columns = (width as f64 / column_width).ceil() as usize;
+    for word in &words {
+        let row = ((word.top / line_height) as usize).min(lines.len() - 1);
+        let line = &mut lines[row];
+        let mut column = ((word.left / column_width).round() as usize).min(columns);
+        // Words of neighbouring lines may land on the same row.
+        if line.len() >= column && !line.is_empty() {
+            column = line.len() + 1;
+        }
+        line.resize(column, ' ');
+        line.extend(word.text.chars());
+    }
+    let text = lines
+        .into_iter()
+        .map(|line| line.into_iter().collect::<String>() + "\n")
+        .collect();
+    Recognized { text, confidence }
+}
+
+/// The word on one line of Tesseract's TSV output, if it is one.
+fn word(line: &str) -> Option<Word<'_>> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    let [level, _, _, _, _, _, left, top, width, _, confidence, text] = fields[..] else {
+        return None;
+    };
+    let confidence: f64 = confidence.parse().ok()?;
+    let text = text.trim();
+    if level != "5" || confidence < 0.0 || text.is_empty() {
+        return None;
+    }
+    Some(Word {
+        left: left.parse().ok()?,
+        top: top.parse().ok()?,
+        width: width.parse().ok()?,
+        confidence,
+        text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "\
+level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t120\t60\t-1\t
+5\t1\t1\t1\t1\t1\t0\t0\t30\t8\t96.5\tHello
+5\t1\t1\t1\t1\t2\t36\t1\t30\t8\t90.1\tworld
+4\t1\t1\t1\t2\t0\t12\t20\t18\t8\t-1\t
+5\t1\t1\t1\t2\t1\t12\t20\t18\t8\t70\tand
+5\t1\t1\t1\t2\t2\t60\t20\t6\t8\t-1\t ";
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(
+            layout(TSV, 120, 60),
+            Recognized {
+                text: String::from("Hello world\n\n  and\n\n\n\n"),
+                confidence: Some(86),
+            }
+        );
+        assert_eq!(
+            layout(TSV.lines().next().unwrap(), 120, 60),
+            Recognized {
+                text: String::new(),
+                confidence: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_language_arg() {
+        let ocr = Ocr {
+            languages: vec![String::from("eng"), String::from("deu")],
+        };
+        assert_eq!(ocr.language_arg(), "eng+deu");
+        assert_eq!(
+            Ocr {
+                languages: Vec::new()
+            }
+            .language_arg(),
+            "eng"
+        );
+    }
+}
@@ -0,0 +1,377 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! OpenDocument Text output, for LibreOffice and other ODF editors.
+
+use crate::ooxml::{escape, media_part, part_target, utc_timestamp, Geometry, PT_PER_PX};
+use crate::package::Package;
+#[cfg(feature = "native")]
+use crate::package::{long_path, write_checksum};
+#[cfg(feature = "native")]
+use crate::Error;
+use crate::{Docx, Margins, Page, PageSize, Result};
+#[cfg(feature = "native")]
+use std::fs::File;
+use std::io::{self, Seek, Write};
+#[cfg(feature = "native")]
+use std::path::Path;
+
+/// The first part of every ODF package, stored, naming its type.
+pub(crate) const MIMETYPE: &str = "mimetype";
+const CONTENT: &str = "content.xml";
+const STYLES: &str = "styles.xml";
+const META: &str = "meta.xml";
+const MANIFEST: &str = "META-INF/manifest.xml";
+const ODT_TYPE: &str = "application/vnd.oasis.opendocument.text";
+
+/// The pages of a [`Docx`] as an OpenDocument Text, each anchored as a
+/// picture in a paragraph of its own and sized as in the DOCX. Pages that
+/// have an SVG are embedded as the SVG only, which ODF editors draw
+/// themselves. Captions and alt text are kept; the text layer, headings
+/// and links are for DOCX. ODF encryption is not supported, so
+/// [`DocxOptions::output_password`](crate::DocxOptions::output_password)
+/// is an error.
+pub struct Odt {
+    docx: Docx,
+}
+
+impl From<Docx> for Odt {
+    fn from(docx: Docx) -> Odt {
+        Odt { docx }
+    }
+}
+
+/// A size in points, as ODF lengths are written.
+fn pt(px: f64) -> String {
+    format!("{:.2}pt", px * PT_PER_PX)
+}
+
+/// The media type of a picture by its file name.
+fn media_type(name: &str) -> &'static str {
+    match name.rsplit_once('.').map_or("", |(_, extension)| extension) {
+        "svg" => "image/svg+xml",
+        "jpeg" | "jpg" => "image/jpeg",
+        "tiff" => "image/tiff",
+        _ => "image/png",
+    }
+}
+
+impl Odt {
+    #[cfg(feature = "native")]
+    pub fn generate_odt(self, p: &Path) -> Result<()> {
+        let p = &long_path(p);
+        let checksum_sidecar = self.docx.options.checksum_sidecar;
+        let file = File::create(p).map_err(|e| Error::from(e).at(p))?;
+        self.generate_odt_to_writer(file).map_err(|e| e.at(p))?;
+        if checksum_sidecar {
+            write_checksum(p)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the zipped ODT to `writer`.
+    pub fn generate_odt_to_writer<W: Write + Seek>(self, writer: W) -> Result<()> {
+        let _span = tracing::info_span!("generate_odt", pages = self.docx.pages.len()).entered();
+        let options = &self.docx.options;
+        if options.output_password.is_some() {
+            let message = "ODT output cannot be encrypted";
+            return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+        }
+        let package = self.package()?;
+        package.write_zip(
+            writer,
+            options.scrub_metadata,
+            options.optimization.deflate_level,
+        )
+    }
+
+    /// Builds the whole package and returns the zipped ODT.
+    pub fn to_bytes(self) -> Result<Vec<u8>> {
+        let mut buf = io::Cursor::new(Vec::new());
+        self.generate_odt_to_writer(&mut buf)?;
+        Ok(buf.into_inner())
+    }
+
+    /// The parts of the document, with the images of the pages copied over
+    /// from the DOCX.
+    fn package(&self) -> Result<Package> {
+        let docx = &self.docx;
+        let options = &docx.options;
+        let mut package = Package::empty(options)?;
+        package.put(MIMETYPE, ODT_TYPE.as_bytes().to_vec())?;
+        let first = docx.pages.first().map_or(docx.size, |page| page.size);
+        let fixed = Geometry::new(options, first);
+        // Each paper size gets a master page, which every page paragraph
+        // names so that it starts a page of that size.
+        let mut papers: Vec<(String, String)> = Vec::new();
+        let mut body = String::new();
+        let mut pictures = Vec::new();
+        for (i, page) in docx.pages.iter().enumerate() {
+            let geometry = match options.page_size {
+                PageSize::Source => Geometry::new(options, page.size),
+                _ => fixed,
+            };
+            let paper = (pt(geometry.paper.width()), pt(geometry.paper.height()));
+            let master = match papers.iter().position(|known| *known == paper) {
+                Some(master) => master,
+                None => {
+                    papers.push(paper);
+                    papers.len() - 1
+                }
+            };
+            let picture = page.svg.as_ref().or(page.png.as_ref());
+            let picture = picture.expect("a page has an SVG or a PNG");
+            package.put(
+                &format!("Pictures/{}", picture),
+                docx.package.get(&media_part(picture))?,
+            )?;
+            body += &write_page(page, i + 1, master + 1, picture, geometry.fit(page.size));
+            pictures.push(picture.clone());
+        }
+        package.put(CONTENT, content(papers.len(), &body).into_bytes())?;
+        let styles = styles(&papers, options.margins);
+        package.put(STYLES, styles.into_bytes())?;
+        let meta = meta(docx, pictures.len());
+        package.put(META, meta.into_bytes())?;
+        package.put(MANIFEST, manifest(&pictures).into_bytes())?;
+        Ok(package)
+    }
+}
+
+/// The paragraph of `page`, at `position` in the document, starting a page
+/// of master page `master`, and its caption.
+fn write_page(
+    page: &Page,
+    position: usize,
+    master: usize,
+    picture: &str,
+    size: usvg::Size,
+) -> String {
+    let style = format!("P{}", master);
+    let name = format!("Page {}", position);
+    let description = page.description();
+    let description = escape(&description);
+    let href = part_target(&format!("Pictures/{}", picture));
+    let mime_type = media_type(picture);
+    let (width, height) = (pt(size.width()), pt(size.height()));
+    let caption = page.caption.as_ref().map_or(String::new(), |caption| {
+        let caption = escape(caption);
+        format_xml::xml! { <text:p text:style-name="Caption">{caption}</text:p> }.to_string()
+    });
+    format_xml::xml! {
+        <text:p text:style-name={style}>
+            <draw:frame draw:style-name="Picture" draw:name={name} text:anchor-type="as-char" svg:width={width} svg:height={height} draw:z-index="0">
+                <draw:image xlink:href={href} xlink:type="simple" xlink:show="embed" xlink:actuate="onLoad" draw:mime-type={mime_type}/>
+                <svg:desc>{description}</svg:desc>
+            </draw:frame>
+        </text:p>
+        {caption}
+    }
+    .to_string()
+}
+
+/// The body holding `body`, with a paragraph style for each of `masters`
+/// master pages.
+fn content(masters: usize, body: &str) -> String {
+    let styles: String = (1..=masters)
+        .map(|master| {
+            let style = format!("P{}", master);
+            let master = format!("Page{}", master);
+            format_xml::xml! {
+                <style:style style:name={style} style:family="paragraph" style:master-page-name={master}>
+                    <style:paragraph-properties fo:margin-top="0pt" fo:margin-bottom="0pt" fo:line-height="100%" fo:text-align="start"/>
+                </style:style>
+            }
+            .to_string()
+        })
+        .collect();
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8"?>
+        <office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:draw="urn:oasis:names:tc:opendocument:xmlns:drawing:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:xlink="http://www.w3.org/1999/xlink" xmlns:svg="urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0" office:version="1.3">
+            <office:automatic-styles>
+                {styles}
+                <style:style style:name="Caption" style:family="paragraph">
+                    <style:paragraph-properties fo:margin-top="0pt" fo:margin-bottom="0pt" fo:text-align="center"/>
+                </style:style>
+                <style:style style:name="Picture" style:family="graphic">
+                    <style:graphic-properties style:vertical-pos="top" style:vertical-rel="baseline" fo:border="none" fo:padding="0pt"/>
+                </style:style>
+            </office:automatic-styles>
+            <office:body>
+                <office:text>{body}</office:text>
+            </office:body>
+        </office:document-content>
+    }
+    .to_string()
+}
+
+/// A page layout and master page `Page{n}` for each of `papers`, widths
+/// and heights, all with `margins`.
+fn styles(papers: &[(String, String)], margins: Margins) -> String {
+    let margin = |points: f64| format!("{:.2}pt", points);
+    let (top, right, bottom, left) = (
+        margin(margins.top),
+        margin(margins.right),
+        margin(margins.bottom),
+        margin(margins.left),
+    );
+    let mut layouts = String::new();
+    let mut masters = String::new();
+    for (i, (width, height)) in papers.iter().enumerate() {
+        let layout = format!("Layout{}", i + 1);
+        let master = format!("Page{}", i + 1);
+        layouts += &format_xml::xml! {
+            <style:page-layout style:name={layout}>
+                <style:page-layout-properties fo:page-width={width} fo:page-height={height} fo:margin-top={top} fo:margin-right={right} fo:margin-bottom={bottom} fo:margin-left={left}/>
+            </style:page-layout>
+        }
+        .to_string();
+        masters += &format_xml::xml! {
+            <style:master-page style:name={master} style:page-layout-name={layout}/>
+        }
+        .to_string();
+    }
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8"?>
+        <office:document-styles xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.3">
+            <office:automatic-styles>{layouts}</office:automatic-styles>
+            <office:master-styles>{masters}</office:master-styles>
+        </office:document-styles>
+    }
+    .to_string()
+}
+
+/// The metadata of the [`Docx`], with its page and image counts.
+fn meta(docx: &Docx, images: usize) -> String {
+    let metadata = docx.metadata();
+    let mut properties = String::new();
+    if !docx.options.scrub_metadata {
+        properties += "<meta:generator>docx-you-want</meta:generator>";
+    }
+    let text = [
+        ("dc:title", &metadata.title),
+        ("dc:subject", &metadata.subject),
+        ("meta:initial-creator", &metadata.author),
+        ("dc:creator", &metadata.author),
+        ("meta:keyword", &metadata.keywords),
+    ];
+    for (element, value) in text {
+        if let Some(value) = value {
+            let value = escape(value);
+            properties += &format!("<{}>{}</{}>", element, value, element);
+        }
+    }
+    let dates = [
+        ("meta:creation-date", metadata.created),
+        ("dc:date", metadata.modified),
+    ];
+    for (element, secs) in dates {
+        if let Some(secs) = secs {
+            properties += &format!("<{}>{}</{}>", element, utc_timestamp(secs), element);
+        }
+    }
+    let pages = docx.pages.len();
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8"?>
+        <office:document-meta xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:meta="urn:oasis:names:tc:opendocument:xmlns:meta:1.0" xmlns:dc="http://purl.org/dc/elements/1.1/" office:version="1.3">
+            <office:meta>
+                {properties}
+                <meta:document-statistic meta:page-count={pages} meta:image-count={images}/>
+            </office:meta>
+        </office:document-meta>
+    }
+    .to_string()
+}
+
+/// The manifest of the package with `pictures`.
+fn manifest(pictures: &[String]) -> String {
+    let pictures: String = pictures
+        .iter()
+        .map(|picture| {
+            // A path in the ZIP, not a URI.
+            let path = escape(&format!("Pictures/{}", picture));
+            let media_type = media_type(picture);
+            format_xml::xml! {
+                <manifest:file-entry manifest:full-path={path} manifest:media-type={media_type}/>
+            }
+            .to_string()
+        })
+        .collect();
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8"?>
+        <manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.3">
+            <manifest:file-entry manifest:full-path="/" manifest:version="1.3" manifest:media-type={ODT_TYPE}/>
+            <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+            <manifest:file-entry manifest:full-path="styles.xml" manifest:media-type="text/xml"/>
+            <manifest:file-entry manifest:full-path="meta.xml" manifest:media-type="text/xml"/>
+            {pictures}
+        </manifest:manifest>
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+    use std::io::Read;
+
+    #[test]
+    fn test_odt() {
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            optimization: crate::Optimization::smaller(),
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        let svg = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg");
+        docx.add_svg_bytes(&std::fs::read(svg).unwrap()).unwrap();
+        let landscape = br#"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="300"/>"#;
+        docx.add_svg_data("2.svg", landscape.to_vec()).unwrap();
+        docx.set_page_caption(2, "Figure <2>").unwrap();
+        let bytes = Odt::from(docx).to_bytes().unwrap();
+        let mut zip = zip::ZipArchive::new(io::Cursor::new(bytes)).unwrap();
+        let mimetype = zip.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), MIMETYPE);
+        assert_eq!(mimetype.compression(), zip::CompressionMethod::Stored);
+        drop(mimetype);
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            let mut file = zip.by_name(name).unwrap();
+            file.read_to_string(&mut text).unwrap();
+            text
+        };
+        let content = read(CONTENT);
+        assert!(content.contains(r#"<text:p text:style-name="P2">"#));
+        assert!(content.contains(r#"xlink:href="Pictures/2.svg""#));
+        assert!(content.contains("Figure &lt;2&gt;"));
+        assert!(read(STYLES).contains(r#"fo:page-width="300.00pt" fo:page-height="225.00pt""#));
+        assert!(read(MANIFEST).contains(r#"manifest:full-path="Pictures/1.svg""#));
+        assert!(read(META).contains(r#"meta:page-count="2""#));
+        assert!(zip.by_name("Pictures/1.png").is_err());
+    }
+
+    #[test]
+    fn test_odt_password() {
+        let docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            output_password: Some(String::from("secret")),
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        assert!(Odt::from(docx).to_bytes().is_err());
+    }
+}
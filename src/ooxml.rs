@@ -0,0 +1,1579 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The WordprocessingML written into the package: page paragraphs,
+//! relationships and custom properties.
+
+use crate::package::Package;
+use crate::parts::{self, ContentTypes, Relationship, Relationships};
+use crate::{DocxOptions, Error, Result};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub(crate) fn px_to_emu(px: f64) -> i32 {
+    let dpi = 96.0;
+    let emus_per_inch = 914400.0;
+    (px / dpi * emus_per_inch) as i32
+}
+
+pub(crate) fn px_to_twenties_of_pt(px: f64) -> i32 {
+    let dpi = 96.0;
+    let pt_per_inch = 72.0;
+    (px / dpi * pt_per_inch * 20.0) as i32
+}
+
+pub(crate) fn pt_to_twenties_of_pt(pt: f64) -> i32 {
+    (pt * 20.0).round() as i32
+}
+
+/// `YYYY-MM-DDThh:mm:ssZ` for a Unix timestamp.
+pub fn utc_timestamp(secs: u64) -> String {
+    let (days, secs) = (secs / 86400, secs % 86400);
+    // Civil date from days since the epoch, after Howard Hinnant.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// `SystemTime` panics in browsers; the time is JavaScript's there.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn now() -> u64 {
+    (js_sys::Date::now() / 1000.0) as u64
+}
+
+pub(crate) const CONTENT_TYPES: &str = "[Content_Types].xml";
+pub(crate) const DOCUMENT: &str = "word/document.xml";
+pub(crate) const DOCUMENT_RELS: &str = "word/_rels/document.xml.rels";
+pub(crate) const PACKAGE_RELS: &str = "_rels/.rels";
+pub(crate) const CUSTOM_PROPERTIES: &str = "docProps/custom.xml";
+pub(crate) const CORE_PROPERTIES: &str = "docProps/core.xml";
+pub(crate) const APP_PROPERTIES: &str = "docProps/app.xml";
+/// The document of the built-in template, with the placeholders of
+/// [`crate::template`].
+const DOCUMENT_TEMPLATE: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:wpc="http://schemas.microsoft.com/office/word/2010/wordprocessingCanvas"
+    xmlns:cx="http://schemas.microsoft.com/office/drawing/2014/chartex"
+    xmlns:cx1="http://schemas.microsoft.com/office/drawing/2015/9/8/chartex"
+    xmlns:cx2="http://schemas.microsoft.com/office/drawing/2015/10/21/chartex"
+    xmlns:cx3="http://schemas.microsoft.com/office/drawing/2016/5/9/chartex"
+    xmlns:cx4="http://schemas.microsoft.com/office/drawing/2016/5/10/chartex"
+    xmlns:cx5="http://schemas.microsoft.com/office/drawing/2016/5/11/chartex"
+    xmlns:cx6="http://schemas.microsoft.com/office/drawing/2016/5/12/chartex"
+    xmlns:cx7="http://schemas.microsoft.com/office/drawing/2016/5/13/chartex"
+    xmlns:cx8="http://schemas.microsoft.com/office/drawing/2016/5/14/chartex"
+    xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006"
+    xmlns:aink="http://schemas.microsoft.com/office/drawing/2016/ink"
+    xmlns:am3d="http://schemas.microsoft.com/office/drawing/2017/model3d"
+    xmlns:o="urn:schemas-microsoft-com:office:office"
+    xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"
+    xmlns:m="http://schemas.openxmlformats.org/officeDocument/2006/math"
+    xmlns:v="urn:schemas-microsoft-com:vml"
+    xmlns:wp14="http://schemas.microsoft.com/office/word/2010/wordprocessingDrawing"
+    xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing"
+    xmlns:w10="urn:schemas-microsoft-com:office:word"
+    xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"
+    xmlns:w14="http://schemas.microsoft.com/office/word/2010/wordml"
+    xmlns:w15="http://schemas.microsoft.com/office/word/2012/wordml"
+    xmlns:w16cex="http://schemas.microsoft.com/office/word/2018/wordml/cex"
+    xmlns:w16cid="http://schemas.microsoft.com/office/word/2016/wordml/cid"
+    xmlns:w16="http://schemas.microsoft.com/office/word/2018/wordml"
+    xmlns:w16sdtdh="http://schemas.microsoft.com/office/word/2020/wordml/sdtdatahash"
+    xmlns:w16se="http://schemas.microsoft.com/office/word/2015/wordml/symex"
+    xmlns:wpg="http://schemas.microsoft.com/office/word/2010/wordprocessingGroup"
+    xmlns:wpi="http://schemas.microsoft.com/office/word/2010/wordprocessingInk"
+    xmlns:wne="http://schemas.microsoft.com/office/word/2006/wordml"
+    xmlns:wps="http://schemas.microsoft.com/office/word/2010/wordprocessingShape" mc:Ignorable="w14 w15 w16se w16cid w16 w16cex w16sdtdh wp14">
+    <w:body>
+        {{pages}}
+        <w:sectPr>
+            <w:pgSz w:w="{{page_width}}" w:h="{{page_height}}" w:orient="{{page_orient}}"/>
+            <w:pgMar w:top="{{margin_top}}" w:right="{{margin_right}}" w:bottom="{{margin_bottom}}" w:left="{{margin_left}}" w:header="0" w:footer="0" w:gutter="0"/>
+        </w:sectPr>
+    </w:body>
+</w:document>"#;
+
+/// The parts of the built-in template: a document with its content types
+/// and relationships, which the pages are added to.
+pub(crate) fn skeleton() -> [(&'static str, String); 4] {
+    let mut types = ContentTypes::new();
+    types.add_default("png", "image/png");
+    types.add_default("svg", "image/svg+xml");
+    types.add_override(
+        DOCUMENT,
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml",
+    );
+    let mut package_rels = Relationships::new();
+    package_rels.push(Relationship::part("rId1", parts::OFFICE_DOCUMENT, DOCUMENT));
+    [
+        (CONTENT_TYPES, types.to_xml()),
+        (PACKAGE_RELS, package_rels.to_xml()),
+        (DOCUMENT, String::from(DOCUMENT_TEMPLATE)),
+        (DOCUMENT_RELS, Relationships::new().to_xml()),
+    ]
+}
+
+const FMTID_USER_DEFINED: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+
+/// The value of attribute `name` in a start tag, without unescaping it.
+pub(crate) fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let end = start + tag[start..].find('"')?;
+    Some(&tag[start..end])
+}
+
+pub(crate) fn media_part(name: &str) -> String {
+    format!("word/media/{}", name)
+}
+
+/// Whether XML 1.0 allows `c` at all.
+fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' '..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+/// `text` escaped for element content and attribute values. Characters XML
+/// does not allow even escaped, like the form feeds of pdftotext or the
+/// control characters of PDF metadata, are dropped.
+pub(crate) fn escape(text: &str) -> String {
+    let text: String = text.chars().filter(|&c| is_xml_char(c)).collect();
+    format_xml::escape(text).to_string()
+}
+
+/// `path` with what part names cannot hold percent-encoded.
+pub(crate) fn percent_encode(path: &str) -> String {
+    let mut target = String::with_capacity(path.len());
+    for c in path.chars() {
+        if c.is_ascii_alphanumeric() || "-._~/!$&'()*+,;=:@".contains(c) {
+            target.push(c);
+        } else {
+            for byte in c.to_string().bytes() {
+                target += &format!("%{:02X}", byte);
+            }
+        }
+    }
+    target
+}
+
+/// `path` as the target of a relationship or link inside the package,
+/// percent-encoding what part names cannot hold, then escaped.
+pub(crate) fn part_target(path: &str) -> String {
+    escape(&percent_encode(path))
+}
+
+/// A Microsoft Information Protection label, stamped into the custom
+/// document properties the way Office records an applied label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SensitivityLabel {
+    pub name: String,
+    /// The label GUID, without braces.
+    pub id: String,
+    /// The Azure AD tenant the label belongs to, if known.
+    pub site_id: Option<String>,
+}
+
+impl SensitivityLabel {
+    pub(crate) fn properties(&self, set_date: Option<&str>) -> Vec<(String, String)> {
+        let prefix = format!("MSIP_Label_{}", self.id);
+        let mut properties = vec![
+            ("Enabled", "true"),
+            ("Method", "Standard"),
+            ("Name", self.name.as_str()),
+        ];
+        if let Some(set_date) = set_date {
+            properties.push(("SetDate", set_date));
+        }
+        if let Some(site_id) = &self.site_id {
+            properties.push(("SiteId", site_id.as_str()));
+        }
+        properties.push(("ContentBits", "0"));
+        properties
+            .into_iter()
+            .map(|(key, value)| (format!("{}_{}", prefix, key), value.to_owned()))
+            .collect()
+    }
+}
+
+/// The custom properties part holding `properties` as strings.
+pub(crate) fn custom_properties(properties: &[(String, String)]) -> String {
+    let properties: String = properties
+        .iter()
+        .enumerate()
+        .map(|(i, (name, value))| {
+            let name = escape(name);
+            let value = escape(value);
+            // Property ids start at 2; 0 and 1 are reserved.
+            let pid = i + 2;
+            format_xml::xml! {
+                <property fmtid={FMTID_USER_DEFINED} pid={pid} name={name}><vt:lpwstr>{value}</vt:lpwstr></property>
+            }
+            .to_string()
+        })
+        .collect();
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes">{properties}</Properties>
+    }
+    .to_string()
+}
+
+/// The title, author and the like, which Word shows among the properties
+/// of the document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    /// Unix timestamp; the time of writing if unset, unless metadata is
+    /// scrubbed.
+    pub created: Option<u64>,
+    /// Unix timestamp, like `created`.
+    pub modified: Option<u64>,
+}
+
+impl Metadata {
+    /// These fields, with the unset ones taken from `other`.
+    pub fn or(self, other: Metadata) -> Metadata {
+        Metadata {
+            title: self.title.or(other.title),
+            author: self.author.or(other.author),
+            subject: self.subject.or(other.subject),
+            keywords: self.keywords.or(other.keywords),
+            created: self.created.or(other.created),
+            modified: self.modified.or(other.modified),
+        }
+    }
+}
+
+/// The core properties part for `metadata`.
+pub(crate) fn core_properties(metadata: &Metadata) -> String {
+    let mut properties = String::new();
+    let text = [
+        ("dc:title", &metadata.title),
+        ("dc:subject", &metadata.subject),
+        ("dc:creator", &metadata.author),
+        ("cp:keywords", &metadata.keywords),
+    ];
+    for (element, value) in text {
+        if let Some(value) = value {
+            let value = escape(value);
+            properties += &format!("<{}>{}</{}>", element, value, element);
+        }
+    }
+    let dates = [
+        ("dcterms:created", metadata.created),
+        ("dcterms:modified", metadata.modified),
+    ];
+    for (element, secs) in dates {
+        if let Some(secs) = secs {
+            properties += &format!(
+                "<{} xsi:type=\"dcterms:W3CDTF\">{}</{}>",
+                element,
+                utc_timestamp(secs),
+                element
+            );
+        }
+    }
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <cp:coreProperties xmlns:cp="http://schemas.openxmlformats.org/package/2006/metadata/core-properties" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:dcterms="http://purl.org/dc/terms/" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">{properties}</cp:coreProperties>
+    }
+    .to_string()
+}
+
+/// The extended properties part of a document of `count` pages or slides,
+/// as `statistic` says, naming this program as its application if
+/// `application`.
+pub(crate) fn app_properties(statistic: &str, count: usize, application: bool) -> String {
+    let application = if application {
+        "<Application>docx-you-want</Application>"
+    } else {
+        ""
+    };
+    let count = format!("<{}>{}</{}>", statistic, count, statistic);
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties">{application}{count}</Properties>
+    }
+    .to_string()
+}
+
+/// A page that has been converted and will be placed in the document.
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub(crate) number: usize,
+    pub(crate) svg: Option<String>,
+    /// The raster fallback, left out of pages embedded as just their SVG.
+    pub(crate) png: Option<String>,
+    pub(crate) size: usvg::Size,
+    pub(crate) caption: Option<String>,
+    pub(crate) alt_text: Option<String>,
+    /// The layout text of the page, for [`TextLayer`].
+    pub(crate) text: Option<String>,
+    /// Level, from 1, and title of the headings before the page.
+    pub(crate) headings: Vec<(usize, String)>,
+    /// Clickable areas of the page, from the links of the PDF.
+    pub(crate) links: Vec<PageLink>,
+    /// File name of the PDF the page came from, if it is to be shown.
+    pub(crate) source: Option<String>,
+    pub(crate) break_after: bool,
+    /// A blank page follows, as between the pages of two PDFs.
+    pub(crate) blank_after: bool,
+    /// The next page is of another size, so a new section starts after this
+    /// one. Set when the document is written.
+    pub(crate) ends_section: bool,
+}
+
+impl Page {
+    /// 1-based position the page was added at in the document, counting
+    /// skipped pages. It stays when pages are removed or reordered, and is
+    /// the page number in the PDF only for a single PDF converted whole.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// The alt text, or else where the page came from.
+    pub(crate) fn description(&self) -> String {
+        match (&self.alt_text, &self.source) {
+            (Some(alt_text), _) => alt_text.clone(),
+            (None, Some(source)) => format!("Page {} of {}", self.number, source),
+            (None, None) => format!("Page {}", self.number),
+        }
+    }
+
+    pub fn size(&self) -> usvg::Size {
+        self.size
+    }
+}
+
+/// Where a link on a page leads.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LinkTarget {
+    Uri(String),
+    /// A page by its number, in the PDF until the page is added and in the
+    /// document after.
+    Page(usize),
+}
+
+/// A clickable area of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PageLink {
+    /// Left, top, right and bottom, as fractions of the page.
+    pub(crate) area: [f64; 4],
+    pub(crate) target: LinkTarget,
+}
+
+/// The converted pages of a [`Docx`], in document order. Indices are 0-based.
+pub struct Pages<'a> {
+    pub(crate) pages: &'a mut Vec<Page>,
+    pub(crate) package: &'a mut Package,
+}
+
+impl Pages<'_> {
+    pub fn len(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Page> {
+        self.pages.iter()
+    }
+
+    /// Drops the page and its images from the package. The images of a page
+    /// streamed to the output already (see [`Docx::stream_to`]) cannot be
+    /// taken out of it, so removing that page fails and keeps it.
+    ///
+    /// [`Docx::stream_to`]: crate::Docx::stream_to
+    pub fn remove(&mut self, idx: usize) -> Result<()> {
+        let page = self.pages.get(idx).ok_or(Error::PageOutOfRange)?;
+        // The images of a page are streamed together, so the first one
+        // tells.
+        for name in page.svg.iter().chain(&page.png) {
+            self.package.remove(&media_part(name))?;
+        }
+        self.pages.remove(idx);
+        Ok(())
+    }
+
+    /// Rearranges the pages so that the page at `order[i]` comes `i`th.
+    /// `order` must be a permutation of `0..len()`.
+    pub fn reorder(&mut self, order: &[usize]) -> Result<()> {
+        let mut seen = vec![false; self.pages.len()];
+        if order.len() != seen.len() {
+            return Err(Error::PageOutOfRange);
+        }
+        for &idx in order {
+            if idx >= seen.len() || std::mem::replace(&mut seen[idx], true) {
+                return Err(Error::PageOutOfRange);
+            }
+        }
+        *self.pages = order.iter().map(|&idx| self.pages[idx].clone()).collect();
+        Ok(())
+    }
+
+    pub fn insert_break_after(&mut self, idx: usize) -> Result<()> {
+        self.pages
+            .get_mut(idx)
+            .ok_or(Error::PageOutOfRange)?
+            .break_after = true;
+        Ok(())
+    }
+}
+
+/// Lines drawn around every page image, so that white pages stand out from
+/// the white background of the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PageFrame {
+    /// A thin grey outline.
+    pub border: bool,
+    /// A soft shadow below and to the right.
+    pub shadow: bool,
+}
+
+/// How consecutive pages are kept on separate pages of the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PageBreaks {
+    /// Page-sized images push the next page over by themselves; breaks
+    /// requested with [`Pages::insert_break_after`] are break paragraphs.
+    #[default]
+    Flow,
+    /// Every page after the first starts a new page through its paragraph
+    /// properties, with no extra paragraphs. Suits images smaller than the
+    /// page.
+    BeforeEachPage,
+}
+
+/// What separates the pages of one PDF from those of the next when several
+/// are put into one document with [`Docx::append_pdf`](crate::Docx::append_pdf).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SourceSeparator {
+    /// Nothing more than between the pages of one PDF.
+    #[default]
+    None,
+    /// A page break after the last page of each PDF, for images smaller
+    /// than the page.
+    PageBreak,
+    /// A blank page between the PDFs.
+    BlankPage,
+}
+
+/// Points per CSS pixel, the unit of SVG and page sizes.
+pub(crate) const PT_PER_PX: f64 = 0.75;
+
+/// The paper the document is laid out on. Fixed sizes are in points and
+/// portrait; see [`DocxOptions::landscape`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum PageSize {
+    /// The size of the first page of the PDF, plus the margins.
+    #[default]
+    Source,
+    A4,
+    Letter,
+    Custom {
+        width: f64,
+        height: f64,
+    },
+}
+
+impl PageSize {
+    /// Width and height in points, unless they come from the source.
+    fn points(self) -> Option<(f64, f64)> {
+        match self {
+            PageSize::Source => None,
+            PageSize::A4 => Some((595.28, 841.89)),
+            PageSize::Letter => Some((612.0, 792.0)),
+            PageSize::Custom { width, height } => Some((width, height)),
+        }
+    }
+}
+
+/// Blank space around the pages, in points.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Margins {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Margins {
+    pub fn uniform(points: f64) -> Margins {
+        Margins {
+            top: points,
+            right: points,
+            bottom: points,
+            left: points,
+        }
+    }
+}
+
+/// How pages are sized on paper of a fixed [`PageSize`], less its margins.
+/// With [`PageSize::Source`] the paper is as large as each page, so pages
+/// keep their size whatever the mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FitMode {
+    /// As large as the page of the PDF, and shrunk only if it would not fit.
+    #[default]
+    ActualSize,
+    /// As large as fits, keeping the aspect ratio, so that every page fills
+    /// the paper however it was sized in the PDF.
+    Contain,
+    /// As wide as the paper less its margins. Pages taller than that in
+    /// proportion run past the bottom margin.
+    FitWidth,
+}
+
+/// The paper and the part of it the pages go on, in px.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Geometry {
+    pub(crate) paper: usvg::Size,
+    pub(crate) text: usvg::Size,
+    fit: FitMode,
+}
+
+impl Geometry {
+    /// The geometry for `options`, with `first` the size of the first page.
+    pub(crate) fn new(options: &DocxOptions, first: usvg::Size) -> Geometry {
+        let margins = options.margins;
+        let (dx, dy) = (
+            (margins.left + margins.right) / PT_PER_PX,
+            (margins.top + margins.bottom) / PT_PER_PX,
+        );
+        let paper = match options.page_size.points() {
+            Some((width, height)) => {
+                let (short, long) = (width.min(height), width.max(height));
+                let (width, height) = if options.landscape {
+                    (long, short)
+                } else {
+                    (short, long)
+                };
+                usvg::Size::new(width / PT_PER_PX, height / PT_PER_PX)
+            }
+            None => usvg::Size::new(first.width() + dx, first.height() + dy),
+        };
+        let paper = paper.unwrap_or(first);
+        let text = usvg::Size::new(paper.width() - dx, paper.height() - dy).unwrap_or(paper);
+        Geometry {
+            paper,
+            text,
+            fit: options.fit_mode,
+        }
+    }
+
+    /// `size` scaled to the text area as the [`FitMode`] says, keeping its
+    /// aspect ratio.
+    pub(crate) fn fit(&self, size: usvg::Size) -> usvg::Size {
+        let width = self.text.width() / size.width();
+        let contain = width.min(self.text.height() / size.height());
+        let scale = match self.fit {
+            FitMode::ActualSize => contain.min(1.0),
+            FitMode::Contain => contain,
+            FitMode::FitWidth => width,
+        };
+        usvg::Size::new(size.width() * scale, size.height() * scale).unwrap_or(size)
+    }
+}
+
+/// Where the text of the pages goes, so that it can be searched, selected
+/// and read aloud.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum TextLayer {
+    /// The pages are images only.
+    #[default]
+    None,
+    /// In a text box behind each page image, laid out like the page.
+    Hidden,
+    /// After the last page, under a heading for each page.
+    Appendix,
+}
+
+/// Protection of the page images against accidental edits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PageLocks {
+    pub no_move: bool,
+    pub no_resize: bool,
+    pub no_select: bool,
+    /// Wrap each page in a content control whose contents cannot be edited.
+    pub content_locked: bool,
+}
+
+impl PageLocks {
+    fn locks_picture(self) -> bool {
+        self.no_move || self.no_resize || self.no_select
+    }
+}
+
+/// The `a:blip` of a picture: its PNG, with its SVG in an extension for
+/// clients that draw SVG. Without the PNG, clients that do not draw SVG show
+/// an empty frame.
+pub(crate) fn write_blip(svg_rid: Option<&str>, png_rid: Option<&str>) -> String {
+    let svg = svg_rid.map(|svg_rid| {
+        format_xml::xml! {
+            <a:extLst>
+                <a:ext uri="{{96DAC541-7B7A-43D3-8B79-37D633B846F1}}">
+                    <asvg:svgBlip xmlns:asvg="http://schemas.microsoft.com/office/drawing/2016/SVG/main" r:embed={svg_rid}/>
+                </a:ext>
+            </a:extLst>
+        }
+        .to_string()
+    });
+    let blip = format_xml::xml! {
+        match ((png_rid, &svg)) {
+            (Some(png_rid), Some(svg)) => {
+                <a:blip r:embed={png_rid}>{svg}</a:blip>
+            }
+            (Some(png_rid), None) => {
+                <a:blip r:embed={png_rid}/>
+            }
+            (None, Some(svg)) => {
+                <a:blip>{svg}</a:blip>
+            }
+            (None, None) => {
+                <a:blip/>
+            }
+        }
+    }
+    .to_string();
+    blip
+}
+
+/// Writes the paragraphs of `page`, at `position` in the document. The drawing
+/// of the page takes `id`, its [`TextLayer::Hidden`] text box `id + 1` and
+/// its links `id + 2` on.
+/// Its headings go in a frame in the corner of the page, so that they take
+/// no room from the image.
+pub(crate) fn write_page(
+    doc: &mut String,
+    page: &Page,
+    options: &DocxOptions,
+    id: i32,
+    position: usize,
+    svg_rid: Option<&str>,
+    png_rid: Option<&str>,
+) {
+    let name = format!("Page {}", position);
+    let description = page.description();
+    let description = escape(&description);
+    let blip = write_blip(svg_rid, png_rid);
+    let caption = page.caption.as_deref().map(escape);
+    let width = px_to_emu(page.size.width());
+    let height = px_to_emu(page.size.height());
+    let break_before = options.page_breaks == PageBreaks::BeforeEachPage && position > 1;
+    let page_break =
+        page.break_after && options.page_breaks == PageBreaks::Flow && !page.ends_section;
+    // The page after a blank one breaks before itself, or else the blank
+    // page breaks after itself.
+    let blank_break = options.page_breaks == PageBreaks::Flow;
+    let section = page
+        .ends_section
+        .then(|| write_section(Geometry::new(options, page.size).paper, options.margins));
+    // The section properties go in the last paragraph of the section.
+    let (page_section, caption_section) = match caption {
+        Some(_) => (None, section),
+        None => (section, None),
+    };
+    let (border, shadow) = (options.page_frame.border, options.page_frame.shadow);
+    let locks = options.page_locks;
+    let locked = locks.locks_picture();
+    let (no_move, no_resize, no_select) = (
+        u8::from(locks.no_move),
+        u8::from(locks.no_resize),
+        u8::from(locks.no_select),
+    );
+    let text_box = match (options.text_layer, &page.text) {
+        (TextLayer::Hidden, Some(text)) => Some(write_text_box(page, text, id + 1)),
+        _ => None,
+    };
+    let links: String = page
+        .links
+        .iter()
+        .enumerate()
+        .map(|(i, link)| write_link(page, link, id + 2 + i as i32, &link_rid(position, i)))
+        .collect();
+    for (level, title) in &page.headings {
+        *doc += &write_heading(*level, title);
+    }
+    if locks.content_locked {
+        *doc += "<w:sdt><w:sdtPr><w:lock w:val=\"sdtContentLocked\"/></w:sdtPr><w:sdtContent>";
+    }
+    *doc += &format_xml::xml! {
+      <w:p>
+        <w:pPr>
+            if (break_before) {
+                <w:pageBreakBefore/>
+            }
+            <w:widowControl/>
+            <w:spacing w:before="0" w:after="0" w:line="240" w:lineRule="auto"/>
+            <w:jc w:val="left"/>
+            if let Some(section) = (&page_section) {
+                {section}
+            }
+        </w:pPr>
+        <w:r>
+            <w:rPr>
+                <w:noProof/>
+            </w:rPr>
+            <w:drawing>
+                <wp:inline distT="0" distB="0" distL="0" distR="0">
+                    <wp:extent cx={width} cy={height}/>
+                    <wp:effectExtent l="0" t="0" r="0" b="0"/>
+                    <wp:docPr id={id} name={name} descr={description}/>
+                    <wp:cNvGraphicFramePr>
+                                    if (locked) {
+                        <a:graphicFrameLocks xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" noChangeAspect="1" noMove={no_move} noResize={no_resize} noSelect={no_select}/>
+                                    } else {
+                        <a:graphicFrameLocks xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" noChangeAspect="1"/>
+                                    }
+                    </wp:cNvGraphicFramePr>
+                    <a:graphic xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
+                        <a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture">
+                            <pic:pic xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture">
+                                <pic:nvPicPr>
+                                    <pic:cNvPr id="1" name=""/>
+                                    if (locked) {
+                                        <pic:cNvPicPr>
+                                            <a:picLocks noChangeAspect="1" noMove={no_move} noResize={no_resize} noSelect={no_select}/>
+                                        </pic:cNvPicPr>
+                                    } else {
+                                        <pic:cNvPicPr/>
+                                    }
+                                </pic:nvPicPr>
+                                <pic:blipFill>
+                                    {blip}
+                                    <a:stretch>
+                                        <a:fillRect/>
+                                    </a:stretch>
+                                </pic:blipFill>
+                                <pic:spPr>
+                                    <a:xfrm>
+                                        <a:off x="0" y="0"/>
+                                        <a:ext cx={width} cy={height}/>
+                                    </a:xfrm>
+                                    <a:prstGeom prst="rect">
+                                        <a:avLst/>
+                                    </a:prstGeom>
+                                    if (border) {
+                                        <a:ln w="9525">
+                                            <a:solidFill>
+                                                <a:srgbClr val="808080"/>
+                                            </a:solidFill>
+                                        </a:ln>
+                                    }
+                                    if (shadow) {
+                                        <a:effectLst>
+                                            <a:outerShdw blurRad="63500" dist="38100" dir="2700000" algn="tl" rotWithShape="0">
+                                                <a:prstClr val="black">
+                                                    <a:alpha val="40000"/>
+                                                </a:prstClr>
+                                            </a:outerShdw>
+                                        </a:effectLst>
+                                    }
+                                </pic:spPr>
+                            </pic:pic>
+                        </a:graphicData>
+                    </a:graphic>
+                </wp:inline>
+            </w:drawing>
+        </w:r>
+        if let Some(text_box) = (&text_box) {
+            {text_box}
+        }
+        {links}
+      </w:p>
+      if let Some(caption) = (&caption) {
+        <w:p>
+            <w:pPr>
+                <w:spacing w:before="0" w:after="0"/>
+                <w:jc w:val="center"/>
+                if let Some(section) = (&caption_section) {
+                    {section}
+                }
+            </w:pPr>
+            <w:r>
+                <w:t xml:space="preserve">{caption}</w:t>
+            </w:r>
+        </w:p>
+      }
+      if (page_break) {
+        <w:p>
+            <w:pPr>
+                <w:spacing w:before="0" w:after="0" w:line="240" w:lineRule="auto"/>
+            </w:pPr>
+            <w:r>
+                <w:br w:type="page"/>
+            </w:r>
+        </w:p>
+      }
+      if (page.blank_after) {
+        <w:p>
+            <w:pPr>
+                <w:pageBreakBefore/>
+                <w:spacing w:before="0" w:after="0" w:line="240" w:lineRule="auto"/>
+            </w:pPr>
+            if (blank_break) {
+                <w:r>
+                    <w:br w:type="page"/>
+                </w:r>
+            }
+        </w:p>
+      }
+    }
+    .to_string();
+    if locks.content_locked {
+        *doc += "</w:sdtContent></w:sdt>";
+    }
+}
+
+/// A paragraph of `Heading{level}` style, out of the way and not seen.
+fn write_heading(level: usize, title: &str) -> String {
+    let level = level.clamp(1, 9);
+    let style = format!("Heading{}", level);
+    let outline_level = level - 1;
+    let title = escape(title);
+    format_xml::xml! {
+        <w:p>
+            <w:pPr>
+                <w:pStyle w:val={style}/>
+                <w:framePr w:w="20" w:h="20" w:hRule="exact" w:wrap="through" w:vAnchor="page" w:hAnchor="page" w:x="0" w:y="0"/>
+                <w:spacing w:before="0" w:after="0" w:line="20" w:lineRule="exact"/>
+                <w:outlineLvl w:val={outline_level}/>
+            </w:pPr>
+            <w:r>
+                <w:rPr>
+                    <w:color w:val="FFFFFF"/>
+                    <w:sz w:val="2"/>
+                    <w:szCs w:val="2"/>
+                </w:rPr>
+                <w:t xml:space="preserve">{title}</w:t>
+            </w:r>
+        </w:p>
+    }
+    .to_string()
+}
+
+/// Id of the relationship of the `i`th link on the page at `position`, if it
+/// leads out of the document.
+pub(crate) fn link_rid(position: usize, i: usize) -> String {
+    format!("rIdLink{}_{}", position, i)
+}
+
+/// Name of the bookmark that links to page `number` lead to.
+pub(crate) fn link_bookmark(number: usize) -> String {
+    format!("_Link_page_{}", number)
+}
+
+/// A hyperlink around a transparent shape over `link`'s area of `page`.
+fn write_link(page: &Page, link: &PageLink, id: i32, rid: &str) -> String {
+    let [left, top, right, bottom] = link.area;
+    let (width, height) = (page.size.width(), page.size.height());
+    let x = px_to_emu(width * left);
+    let y = px_to_emu(height * top);
+    let cx = px_to_emu(width * (right - left));
+    let cy = px_to_emu(height * (bottom - top));
+    let name = format!("Link {}", id);
+    let description = match &link.target {
+        LinkTarget::Uri(uri) => escape(uri),
+        LinkTarget::Page(number) => format!("Go to page {}", number),
+    };
+    let shape = format_xml::xml! {
+        <w:r>
+            <w:drawing>
+                <wp:anchor distT="0" distB="0" distL="0" distR="0" simplePos="0" relativeHeight="1" behindDoc="0" locked="1" layoutInCell="1" allowOverlap="1">
+                    <wp:simplePos x="0" y="0"/>
+                    <wp:positionH relativeFrom="column">
+                        <wp:posOffset>{x}</wp:posOffset>
+                    </wp:positionH>
+                    <wp:positionV relativeFrom="paragraph">
+                        <wp:posOffset>{y}</wp:posOffset>
+                    </wp:positionV>
+                    <wp:extent cx={cx} cy={cy}/>
+                    <wp:effectExtent l="0" t="0" r="0" b="0"/>
+                    <wp:wrapNone/>
+                    <wp:docPr id={id} name={name} descr={description}/>
+                    <wp:cNvGraphicFramePr/>
+                    <a:graphic xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
+                        <a:graphicData uri="http://schemas.microsoft.com/office/word/2010/wordprocessingShape">
+                            <wps:wsp>
+                                <wps:cNvSpPr/>
+                                <wps:spPr>
+                                    <a:xfrm>
+                                        <a:off x="0" y="0"/>
+                                        <a:ext cx={cx} cy={cy}/>
+                                    </a:xfrm>
+                                    <a:prstGeom prst="rect">
+                                        <a:avLst/>
+                                    </a:prstGeom>
+                                    <a:noFill/>
+                                    <a:ln>
+                                        <a:noFill/>
+                                    </a:ln>
+                                </wps:spPr>
+                                <wps:bodyPr/>
+                            </wps:wsp>
+                        </a:graphicData>
+                    </a:graphic>
+                </wp:anchor>
+            </w:drawing>
+        </w:r>
+    }
+    .to_string();
+    match &link.target {
+        LinkTarget::Uri(_) => format_xml::xml! {
+            <w:hyperlink r:id={rid} w:tooltip={description} w:history="1">{shape}</w:hyperlink>
+        }
+        .to_string(),
+        LinkTarget::Page(number) => {
+            let bookmark = link_bookmark(*number);
+            format_xml::xml! {
+                <w:hyperlink w:anchor={bookmark} w:tooltip={description} w:history="1">{shape}</w:hyperlink>
+            }
+            .to_string()
+        }
+    }
+}
+
+/// The properties of a section on paper of `paper` px, in a new page.
+pub(crate) fn write_section(paper: usvg::Size, margins: Margins) -> String {
+    let width = px_to_twenties_of_pt(paper.width());
+    let height = px_to_twenties_of_pt(paper.height());
+    let orient = if width > height {
+        "landscape"
+    } else {
+        "portrait"
+    };
+    let [top, right, bottom, left] =
+        [margins.top, margins.right, margins.bottom, margins.left].map(pt_to_twenties_of_pt);
+    format_xml::xml! {
+        <w:sectPr>
+            <w:type w:val="nextPage"/>
+            <w:pgSz w:w={width} w:h={height} w:orient={orient}/>
+            <w:pgMar w:top={top} w:right={right} w:bottom={bottom} w:left={left} w:header="0" w:footer="0" w:gutter="0"/>
+        </w:sectPr>
+    }
+    .to_string()
+}
+
+/// The widest a layout text character is, in ems, as in Courier.
+pub(crate) const CHARACTER_WIDTH: f64 = 0.6;
+
+/// A run holding `text`, the layout text of `page`, in a text box the size of
+/// the page behind its image: found by searches and readers, but not seen.
+/// The font size makes the lines and columns of the text fill the page.
+fn write_text_box(page: &Page, text: &str, id: i32) -> String {
+    let width = px_to_emu(page.size.width());
+    let height = px_to_emu(page.size.height());
+    let lines: Vec<_> = text.lines().map(str::trim_end).collect();
+    let columns = lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let line_height = page.size.height() * 0.75 / lines.len().max(1) as f64;
+    let column_width = page.size.width() * 0.75 / CHARACTER_WIDTH / columns.max(1) as f64;
+    let pt = line_height.min(column_width).clamp(1.0, 12.0);
+    let size = (pt * 2.0).round() as i32;
+    let line = (pt * 20.0).round() as i32;
+    let name = format!("Text of page {}", page.number);
+    let paragraphs: String = lines
+        .iter()
+        .map(|text| {
+            let text = escape(text);
+            format_xml::xml! {
+                <w:p>
+                    <w:pPr>
+                        <w:spacing w:before="0" w:after="0" w:line={line} w:lineRule="exact"/>
+                    </w:pPr>
+                    <w:r>
+                        <w:rPr>
+                            <w:rFonts w:ascii="Courier New" w:hAnsi="Courier New" w:cs="Courier New"/>
+                            <w:color w:val="FFFFFF"/>
+                            <w:sz w:val={size}/>
+                            <w:szCs w:val={size}/>
+                        </w:rPr>
+                        <w:t xml:space="preserve">{text}</w:t>
+                    </w:r>
+                </w:p>
+            }
+            .to_string()
+        })
+        .collect();
+    format_xml::xml! {
+        <w:r>
+            <w:drawing>
+                <wp:anchor distT="0" distB="0" distL="0" distR="0" simplePos="0" relativeHeight="0" behindDoc="1" locked="1" layoutInCell="1" allowOverlap="1">
+                    <wp:simplePos x="0" y="0"/>
+                    <wp:positionH relativeFrom="column">
+                        <wp:posOffset>0</wp:posOffset>
+                    </wp:positionH>
+                    <wp:positionV relativeFrom="paragraph">
+                        <wp:posOffset>0</wp:posOffset>
+                    </wp:positionV>
+                    <wp:extent cx={width} cy={height}/>
+                    <wp:effectExtent l="0" t="0" r="0" b="0"/>
+                    <wp:wrapNone/>
+                    <wp:docPr id={id} name={name} descr={name}/>
+                    <wp:cNvGraphicFramePr/>
+                    <a:graphic xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
+                        <a:graphicData uri="http://schemas.microsoft.com/office/word/2010/wordprocessingShape">
+                            <wps:wsp>
+                                <wps:cNvSpPr txBox="1"/>
+                                <wps:spPr>
+                                    <a:xfrm>
+                                        <a:off x="0" y="0"/>
+                                        <a:ext cx={width} cy={height}/>
+                                    </a:xfrm>
+                                    <a:prstGeom prst="rect">
+                                        <a:avLst/>
+                                    </a:prstGeom>
+                                    <a:noFill/>
+                                    <a:ln>
+                                        <a:noFill/>
+                                    </a:ln>
+                                </wps:spPr>
+                                <wps:txbx>
+                                    <w:txbxContent>{paragraphs}</w:txbxContent>
+                                </wps:txbx>
+                                <wps:bodyPr rot="0" wrap="none" lIns="0" tIns="0" rIns="0" bIns="0" anchor="t">
+                                    <a:noAutofit/>
+                                </wps:bodyPr>
+                            </wps:wsp>
+                        </a:graphicData>
+                    </a:graphic>
+                </wp:anchor>
+            </w:drawing>
+        </w:r>
+    }
+    .to_string()
+}
+
+/// The text of every page that has some, after a page break, each under a
+/// heading naming the page.
+pub(crate) fn write_text_appendix(doc: &mut String, pages: &[Page]) {
+    let mut break_before = true;
+    for page in pages {
+        let Some(text) = &page.text else {
+            continue;
+        };
+        let heading = format!("Page {}", page.number);
+        *doc += &format_xml::xml! {
+            <w:p>
+                <w:pPr>
+                    if (break_before) {
+                        <w:pageBreakBefore/>
+                    }
+                    <w:keepNext/>
+                    <w:spacing w:before="240" w:after="120"/>
+                    <w:ind w:left="720" w:right="720"/>
+                </w:pPr>
+                <w:r>
+                    <w:rPr>
+                        <w:b/>
+                    </w:rPr>
+                    <w:t>{heading}</w:t>
+                </w:r>
+            </w:p>
+        }
+        .to_string();
+        break_before = false;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let line = escape(line);
+            *doc += &format_xml::xml! {
+                <w:p>
+                    <w:pPr>
+                        <w:spacing w:before="0" w:after="0"/>
+                        <w:ind w:left="720" w:right="720"/>
+                    </w:pPr>
+                    <w:r>
+                        <w:t xml:space="preserve">{line}</w:t>
+                    </w:r>
+                </w:p>
+            }
+            .to_string();
+        }
+    }
+}
+
+/// Thumbnails per row on the index page.
+const INDEX_COLUMNS: f64 = 4.0;
+
+/// A small copy of a page on the index page, linking to the page itself.
+pub(crate) struct Thumbnail<'a> {
+    pub(crate) id: i32,
+    /// 1-based position of the page in the document.
+    pub(crate) position: usize,
+    /// The PNG of the page, or else its SVG.
+    pub(crate) svg_rid: Option<&'a str>,
+    pub(crate) png_rid: Option<&'a str>,
+    pub(crate) bookmark: String,
+    pub(crate) size: usvg::Size,
+}
+
+/// Name of the bookmark on the `n`th page of the document, 1-based.
+pub(crate) fn page_bookmark(n: usize) -> String {
+    format!("page_{}", n)
+}
+
+pub(crate) fn write_bookmark(doc: &mut String, id: usize, name: &str) {
+    *doc += &format_xml::xml! {
+        <w:bookmarkStart w:id={id} w:name={name}/>
+        <w:bookmarkEnd w:id={id}/>
+    }
+    .to_string();
+}
+
+/// A page of hyperlinked thumbnails, as wide as a quarter of the text area
+/// each, followed by a page break.
+pub(crate) fn write_thumbnail_index(doc: &mut String, thumbnails: &[Thumbnail], text_width: f64) {
+    let mut links = String::new();
+    for thumbnail in thumbnails {
+        let scale = text_width / (INDEX_COLUMNS + 1.0) / thumbnail.size.width();
+        let width = px_to_emu(thumbnail.size.width() * scale);
+        let height = px_to_emu(thumbnail.size.height() * scale);
+        let (id, bookmark) = (thumbnail.id, &thumbnail.bookmark);
+        let blip = match thumbnail.png_rid {
+            Some(_) => write_blip(None, thumbnail.png_rid),
+            None => write_blip(thumbnail.svg_rid, None),
+        };
+        let name = format!("Thumbnail {}", thumbnail.position);
+        let description = format!("Go to page {}", thumbnail.position);
+        links += &format_xml::xml! {
+            <w:hyperlink w:anchor={bookmark} w:history="1">
+                <w:r>
+                    <w:drawing>
+                        <wp:inline distT="0" distB="0" distL="0" distR="0">
+                            <wp:extent cx={width} cy={height}/>
+                            <wp:docPr id={id} name={name} descr={description}/>
+                            <a:graphic xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main">
+                                <a:graphicData uri="http://schemas.openxmlformats.org/drawingml/2006/picture">
+                                    <pic:pic xmlns:pic="http://schemas.openxmlformats.org/drawingml/2006/picture">
+                                        <pic:nvPicPr>
+                                            <pic:cNvPr id="1" name=""/>
+                                            <pic:cNvPicPr/>
+                                        </pic:nvPicPr>
+                                        <pic:blipFill>
+                                            {blip}
+                                            <a:stretch>
+                                                <a:fillRect/>
+                                            </a:stretch>
+                                        </pic:blipFill>
+                                        <pic:spPr>
+                                            <a:xfrm>
+                                                <a:off x="0" y="0"/>
+                                                <a:ext cx={width} cy={height}/>
+                                            </a:xfrm>
+                                            <a:prstGeom prst="rect">
+                                                <a:avLst/>
+                                            </a:prstGeom>
+                                            <a:ln w="9525">
+                                                <a:solidFill>
+                                                    <a:srgbClr val="808080"/>
+                                                </a:solidFill>
+                                            </a:ln>
+                                        </pic:spPr>
+                                    </pic:pic>
+                                </a:graphicData>
+                            </a:graphic>
+                        </wp:inline>
+                    </w:drawing>
+                </w:r>
+            </w:hyperlink>
+            <w:r>
+                <w:t xml:space="preserve">{" "}</w:t>
+            </w:r>
+        }
+        .to_string();
+    }
+    *doc += &format_xml::xml! {
+        <w:p>
+            <w:pPr>
+                <w:jc w:val="center"/>
+            </w:pPr>
+            {links}
+        </w:p>
+        <w:p>
+            <w:pPr>
+                <w:spacing w:before="0" w:after="0" w:line="240" w:lineRule="auto"/>
+            </w:pPr>
+            <w:r>
+                <w:br w:type="page"/>
+            </w:r>
+        </w:p>
+    }
+    .to_string();
+}
+
+pub(crate) fn hyperlink_relationship(rid: &str, uri: &str) -> Relationship {
+    Relationship::external(rid, parts::HYPERLINK, uri)
+}
+
+/// The relationship from the document to the media file `filename`.
+pub(crate) fn relationship(rid: &str, filename: &str) -> Relationship {
+    Relationship::part(rid, parts::IMAGE, &format!("media/{}", filename))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size() {
+        assert_eq!(px_to_twenties_of_pt(793.707), 11905)
+    }
+
+    #[test]
+    fn test_geometry() {
+        let dims = |size: usvg::Size| (size.width(), size.height());
+        let a4 = usvg::Size::new(793.707, 1122.52).unwrap();
+        let options = DocxOptions {
+            margins: Margins::uniform(72.0),
+            ..DocxOptions::default()
+        };
+        let geometry = Geometry::new(&options, a4);
+        assert_eq!(geometry.paper.width().round(), 986.0);
+        assert_eq!(geometry.text.height(), a4.height());
+        assert_eq!(dims(geometry.fit(a4)), dims(a4));
+
+        let options = DocxOptions {
+            page_size: PageSize::Custom {
+                width: 300.0,
+                height: 150.0,
+            },
+            landscape: false,
+            ..DocxOptions::default()
+        };
+        let geometry = Geometry::new(&options, a4);
+        assert_eq!(dims(geometry.paper), (200.0, 400.0));
+        let fitted = geometry.fit(usvg::Size::new(400.0, 400.0).unwrap());
+        assert_eq!(dims(fitted), (200.0, 200.0));
+        let small = usvg::Size::new(100.0, 50.0).unwrap();
+        assert_eq!(dims(geometry.fit(small)), dims(small));
+
+        let options = DocxOptions {
+            fit_mode: FitMode::Contain,
+            ..options
+        };
+        let geometry = Geometry::new(&options, a4);
+        assert_eq!(dims(geometry.fit(small)), (200.0, 100.0));
+        let tall = usvg::Size::new(100.0, 800.0).unwrap();
+        assert_eq!(dims(geometry.fit(tall)), (50.0, 400.0));
+        let options = DocxOptions {
+            fit_mode: FitMode::FitWidth,
+            ..options
+        };
+        let geometry = Geometry::new(&options, a4);
+        assert_eq!(dims(geometry.fit(small)), (200.0, 100.0));
+        assert_eq!(dims(geometry.fit(tall)), (200.0, 1600.0));
+    }
+
+    #[test]
+    fn test_utc_timestamp() {
+        assert_eq!(utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(315532800), "1980-01-01T00:00:00Z");
+        assert_eq!(utc_timestamp(1709210096), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("Fish & \"chips\" ]]>"),
+            "Fish &amp; &quot;chips&quot; ]]&gt;"
+        );
+        assert_eq!(escape("page\u{c}\u{1}\tone\u{FFFE}"), "page\tone");
+        assert_eq!(escape("中文 🐟"), "中文 🐟");
+        assert_eq!(part_target("media/1.svg"), "media/1.svg");
+        assert_eq!(
+            part_target("media/a b#%&\"<>.svg"),
+            "media/a%20b%23%25&amp;%22%3C%3E.svg"
+        );
+        assert_eq!(part_target("media/é.png"), "media/%C3%A9.png");
+        let relationship = relationship("rId1", "a&b.svg").to_xml();
+        assert!(relationship.contains(r#"Target="media/a&amp;b.svg""#));
+    }
+
+    #[test]
+    fn test_page_frame() {
+        let page = Page {
+            number: 1,
+            svg: None,
+            png: Some(String::from("1.png")),
+            size: usvg::Size::new(10.0, 10.0).unwrap(),
+            caption: None,
+            alt_text: None,
+            text: None,
+            headings: Vec::new(),
+            links: Vec::new(),
+            source: None,
+            break_after: false,
+            blank_after: false,
+            ends_section: false,
+        };
+        let mut options = DocxOptions::default();
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert!(!doc.contains("<a:ln") && !doc.contains("<a:effectLst>"));
+        options.page_frame = PageFrame {
+            border: true,
+            shadow: true,
+        };
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert!(doc.contains(r#"<a:ln w="9525">"#));
+        assert!(doc.contains("<a:outerShdw"));
+    }
+
+    #[test]
+    fn test_description() {
+        let mut page = Page {
+            number: 7,
+            svg: None,
+            png: Some(String::from("7.png")),
+            size: usvg::Size::new(10.0, 10.0).unwrap(),
+            caption: None,
+            alt_text: None,
+            text: None,
+            headings: Vec::new(),
+            links: Vec::new(),
+            source: None,
+            break_after: false,
+            blank_after: false,
+            ends_section: false,
+        };
+        assert_eq!(page.description(), "Page 7");
+        page.source = Some(String::from("report.pdf"));
+        assert_eq!(page.description(), "Page 7 of report.pdf");
+        let mut doc = String::new();
+        write_page(
+            &mut doc,
+            &page,
+            &DocxOptions::default(),
+            3,
+            2,
+            None,
+            Some("rId3"),
+        );
+        assert!(doc.contains(r#"<wp:docPr id="3" name="Page 2" descr="Page 7 of report.pdf" />"#));
+        page.alt_text = Some(String::from("A <scan>"));
+        assert_eq!(page.description(), "A <scan>");
+    }
+
+    #[test]
+    fn test_page_breaks() {
+        let mut page = Page {
+            number: 1,
+            svg: None,
+            png: Some(String::from("1.png")),
+            size: usvg::Size::new(10.0, 10.0).unwrap(),
+            caption: None,
+            alt_text: None,
+            text: None,
+            headings: Vec::new(),
+            links: Vec::new(),
+            source: None,
+            break_after: true,
+            blank_after: false,
+            ends_section: false,
+        };
+        let mut options = DocxOptions::default();
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 2, None, Some("rId0"));
+        assert!(doc.contains(r#"<w:br w:type="page" />"#));
+        assert!(!doc.contains("<w:pageBreakBefore />"));
+        options.page_breaks = PageBreaks::BeforeEachPage;
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert!(!doc.contains("<w:pageBreakBefore />"));
+        page.break_after = false;
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 2, None, Some("rId0"));
+        assert!(doc.contains("<w:pageBreakBefore />"));
+        assert!(!doc.contains("<w:br"));
+        page.blank_after = true;
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert_eq!(doc.matches("<w:pageBreakBefore />").count(), 1);
+        assert!(!doc.contains("<w:br"));
+        options.page_breaks = PageBreaks::Flow;
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert_eq!(doc.matches("<w:pageBreakBefore />").count(), 1);
+        assert_eq!(doc.matches(r#"<w:br w:type="page" />"#).count(), 1);
+        page.blank_after = false;
+
+        // A section break replaces the page break, in the caption if any.
+        options.page_breaks = PageBreaks::Flow;
+        page.break_after = true;
+        page.ends_section = true;
+        page.caption = Some(String::from("Figure 1"));
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 2, None, Some("rId0"));
+        assert!(!doc.contains("<w:br"));
+        let section = doc.find("<w:sectPr>").unwrap();
+        assert!(section > doc.find("<w:drawing>").unwrap());
+        assert!(doc[section..].contains(r#"<w:pgSz w:w="150" w:h="150" w:orient="portrait" />"#));
+    }
+
+    #[test]
+    fn test_page_locks() {
+        let page = Page {
+            number: 1,
+            svg: None,
+            png: Some(String::from("1.png")),
+            size: usvg::Size::new(10.0, 10.0).unwrap(),
+            caption: None,
+            alt_text: None,
+            text: None,
+            headings: Vec::new(),
+            links: Vec::new(),
+            source: None,
+            break_after: false,
+            blank_after: false,
+            ends_section: false,
+        };
+        let mut options = DocxOptions::default();
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert!(!doc.contains("noMove") && !doc.contains("<w:sdt>"));
+        options.page_locks = PageLocks {
+            no_move: true,
+            no_resize: true,
+            no_select: false,
+            content_locked: true,
+        };
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 1, None, Some("rId0"));
+        assert!(doc.contains(r#"<a:picLocks noChangeAspect="1" noMove="1" noResize="1" noSelect="0" />"#));
+        assert!(doc.starts_with("<w:sdt>") && doc.ends_with("</w:sdt>"));
+    }
+
+    #[test]
+    fn test_headings() {
+        let page = Page {
+            number: 2,
+            svg: None,
+            png: Some(String::from("2.png")),
+            size: usvg::Size::new(10.0, 10.0).unwrap(),
+            caption: None,
+            alt_text: None,
+            text: None,
+            headings: vec![(1, String::from("Q&A")), (12, String::from("Deep"))],
+            links: Vec::new(),
+            source: None,
+            break_after: false,
+            blank_after: false,
+            ends_section: false,
+        };
+        let options = DocxOptions {
+            page_breaks: PageBreaks::BeforeEachPage,
+            ..DocxOptions::default()
+        };
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 0, 2, None, Some("rId0"));
+        let (headings, image) = doc.split_at(doc.find("<wp:inline").unwrap());
+        assert_eq!(headings.matches("<w:framePr ").count(), 2);
+        assert!(headings.contains(r#"<w:pStyle w:val="Heading1" />"#));
+        assert!(headings.contains(r#"<w:outlineLvl w:val="8" />"#));
+        assert!(headings.contains(">Q&amp;A</w:t>"));
+        // The frames go with the image paragraph, onto its new page.
+        assert_eq!(headings.matches("<w:pageBreakBefore />").count(), 1);
+        assert!(!image.contains("<w:pStyle"));
+    }
+
+    #[test]
+    fn test_text_layer() {
+        let mut page = Page {
+            number: 4,
+            svg: None,
+            png: Some(String::from("4.png")),
+            size: usvg::Size::new(96.0, 48.0).unwrap(),
+            caption: None,
+            alt_text: None,
+            text: Some(String::from("Fish & chips\n\n   £4.50   ")),
+            headings: Vec::new(),
+            links: Vec::new(),
+            source: None,
+            break_after: false,
+            blank_after: false,
+            ends_section: false,
+        };
+        let mut options = DocxOptions::default();
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 3, 1, None, Some("rId3"));
+        assert!(!doc.contains("<wp:anchor"));
+        options.text_layer = TextLayer::Hidden;
+        let mut doc = String::new();
+        write_page(&mut doc, &page, &options, 3, 1, None, Some("rId3"));
+        assert!(doc.contains(r#"behindDoc="1""#));
+        assert!(doc.contains(r#"<wp:docPr id="4" name="Text of page 4" descr="Text of page 4" />"#));
+        assert!(doc.contains("Fish &amp; chips</w:t>"));
+        // Three lines fit 36pt in 12pt, but twelve columns fit 72pt in 10pt.
+        assert!(doc.contains(r#"<w:sz w:val="20" />"#));
+        assert_eq!(doc.matches("<w:p>").count(), 4);
+
+        let mut doc = String::new();
+        write_text_appendix(&mut doc, std::slice::from_ref(&page));
+        assert!(doc.contains("<w:pageBreakBefore />"));
+        assert!(doc.contains("<w:t>Page 4</w:t>"));
+        assert!(doc.contains(r#"<w:t xml:space="preserve">£4.50</w:t>"#));
+        assert_eq!(doc.matches("<w:p>").count(), 3);
+        page.text = None;
+        let mut doc = String::new();
+        write_text_appendix(&mut doc, &[page]);
+        assert!(doc.is_empty());
+    }
+}
@@ -0,0 +1,692 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The parts of the DOCX before they are zipped, and the files around it.
+
+use crate::odt::MIMETYPE;
+use crate::ooxml::{skeleton, CONTENT_TYPES};
+use crate::{DocxOptions, Error, Result};
+#[cfg(feature = "native")]
+use sha2::Digest;
+use std::collections::BTreeMap;
+#[cfg(feature = "native")]
+use std::collections::BTreeSet;
+#[cfg(feature = "native")]
+use std::fs::{read_dir, remove_file, write, File, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "native")]
+use tempfile::TempDir;
+
+/// Resolution of the PNG fallback with [`Optimization::smaller`].
+pub const OPTIMIZED_DPI: f64 = 72.0;
+
+/// How hard to work at making the DOCX small.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Optimization {
+    /// Deflate the parts at this level, 0 to 9, instead of storing them.
+    /// PNG and JPEG images, which deflate no further, are stored either way.
+    pub deflate_level: Option<u32>,
+    /// Leave out the PNG of pages that have an SVG, for clients that draw
+    /// the SVG themselves, and skip rasterizing them for it. Clients that do
+    /// not draw SVG show those pages as empty frames.
+    pub svg_only: bool,
+    /// Strip comments, metadata and blank space between tags from the SVGs.
+    pub minify_svg: bool,
+}
+
+impl Optimization {
+    /// Everything short of leaving out the PNGs, which older clients need.
+    /// Rendering the PNGs at [`OPTIMIZED_DPI`] goes with it.
+    pub fn smaller() -> Optimization {
+        Optimization {
+            deflate_level: Some(9),
+            svg_only: false,
+            minify_svg: true,
+        }
+    }
+}
+
+/// Whether the part is an image that is compressed already.
+fn is_compressed(name: &str) -> bool {
+    [".png", ".jpeg", ".jpg"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+/// The zip options of each part: stored, or deflated at `deflate_level` if
+/// given, except images and the ODF mimetype, which readers look for
+/// stored. When scrubbing metadata, every entry gets the zip epoch instead
+/// of the current time.
+#[derive(Clone, Copy)]
+struct PartOptions {
+    stored: zip::write::FileOptions,
+    deflated: Option<zip::write::FileOptions>,
+}
+
+impl PartOptions {
+    fn new(scrub_metadata: bool, deflate_level: Option<u32>) -> PartOptions {
+        let mut stored =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        if scrub_metadata {
+            stored = stored.last_modified_time(zip::DateTime::default());
+        }
+        let deflated = deflate_level.map(|level| {
+            stored
+                .compression_method(zip::CompressionMethod::Deflated)
+                .compression_level(Some(level as i32))
+        });
+        PartOptions { stored, deflated }
+    }
+
+    fn of(self, name: &str) -> zip::write::FileOptions {
+        match self.deflated {
+            Some(deflated) if !is_compressed(name) && name != MIMETYPE => deflated,
+            _ => self.stored,
+        }
+    }
+}
+
+/// The error for a part that is not in the package.
+fn missing(name: &str) -> Error {
+    let message = format!("no part {} in the package", name);
+    io::Error::new(io::ErrorKind::NotFound, message).into()
+}
+
+#[cfg(feature = "native")]
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "native")]
+/// Overwrites a file with zeros and syncs it before unlinking it, so the
+/// plain-text content doesn't linger in free blocks of the temp filesystem.
+pub(crate) fn shred_file(path: &Path) -> Result<()> {
+    let len = std::fs::metadata(path)?.len();
+    let mut file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let zeros = [0u8; 64 * 1024];
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()?;
+    drop(file);
+    remove_file(path)?;
+    Ok(())
+}
+
+#[cfg(feature = "native")]
+fn shred_dir(dir: &Path) -> Result<()> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.iter().try_for_each(|f| shred_file(f))
+}
+
+/// `path` in the `\\?\` form on Windows, which lifts the MAX_PATH limit;
+/// UNC shares become `\\?\UNC\server\share`. Unchanged elsewhere.
+pub(crate) fn long_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        if let Some(long) = std::path::absolute(path)
+            .ok()
+            .and_then(|path| path.to_str().and_then(verbatim))
+        {
+            return PathBuf::from(long);
+        }
+    }
+    path.to_owned()
+}
+
+fn verbatim(path: &str) -> Option<String> {
+    let path = path.replace('/', "\\");
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        None
+    } else if let Some(share) = path.strip_prefix(r"\\") {
+        Some(format!(r"\\?\UNC\{}", share))
+    } else if path.get(1..3) == Some(r":\") {
+        Some(format!(r"\\?\{}", path))
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "native")]
+/// A temporary directory, shredded on drop if secure deletion is on.
+pub(crate) struct WorkDir {
+    dir: TempDir,
+    shred: bool,
+}
+
+#[cfg(feature = "native")]
+impl WorkDir {
+    pub(crate) fn new(shred: bool) -> Result<WorkDir> {
+        Ok(WorkDir {
+            dir: TempDir::new()?,
+            shred,
+        })
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+#[cfg(feature = "native")]
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        if self.shred {
+            let _ = shred_dir(self.dir.path());
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+/// Creates the missing parent directories of `path` and checks that it can be
+/// written, so that a bad destination fails before any page is rendered.
+pub fn prepare_output(path: &Path) -> Result<()> {
+    let path = long_path(path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|_| Error::OutputNotWritable)?;
+    }
+    let existed = path.exists();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&path)
+        .map_err(|_| Error::OutputNotWritable)?;
+    if !existed {
+        remove_file(&path)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "native")]
+/// An advisory lock on an output file, held through `<output>.lock` until
+/// dropped, so that two conversions never write the same file at once.
+#[derive(Debug)]
+pub struct OutputLock {
+    path: PathBuf,
+    _file: File,
+}
+
+#[cfg(feature = "native")]
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        // Removed while still locked; see the inode check in `lock_output`.
+        let _ = remove_file(&self.path);
+    }
+}
+
+#[cfg(feature = "native")]
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (file.metadata(), std::fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "native")]
+#[cfg(not(unix))]
+fn is_same_file(_: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+#[cfg(feature = "native")]
+/// Locks `output` against other conversions. Fails with
+/// [`Error::OutputLocked`] if another one holds it, unless `wait` is set.
+pub fn lock_output(output: &Path, wait: bool) -> Result<OutputLock> {
+    let mut path = long_path(output).into_os_string();
+    path.push(".lock");
+    let path = PathBuf::from(path);
+    loop {
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|_| Error::OutputNotWritable)?;
+        if wait {
+            file.lock()?;
+        } else {
+            file.try_lock().map_err(|e| match e {
+                TryLockError::WouldBlock => Error::OutputLocked,
+                TryLockError::Error(e) => e.into(),
+            })?;
+        }
+        // The previous holder may have removed the file between our open and
+        // lock; a lock on the unlinked file would exclude nobody.
+        if is_same_file(&file, &path) {
+            return Ok(OutputLock { path, _file: file });
+        }
+    }
+}
+
+#[cfg(feature = "native")]
+pub(crate) fn write_checksum(path: &Path) -> Result<PathBuf> {
+    let digest = sha2::Sha256::digest(std::fs::read(path)?);
+    let name = path
+        .file_name()
+        .ok_or_else(|| Error::invalid_data("the output has no file name"))?
+        .to_string_lossy();
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".sha256");
+    let sidecar = PathBuf::from(sidecar);
+    write(&sidecar, format!("{:x}  {}\n", digest, name))?;
+    Ok(sidecar)
+}
+
+/// Where the parts of the package live until they are zipped: files in a
+/// temporary directory, buffers that never touch the disk, or for media, the
+/// zip itself.
+pub(crate) enum Package {
+    #[cfg(feature = "native")]
+    Disk(WorkDir),
+    Memory(BTreeMap<String, Vec<u8>>),
+    /// Media in the output zip already, and the other parts still kept.
+    #[cfg(feature = "native")]
+    Streaming(Box<Stream>),
+}
+
+impl Package {
+    /// A package with no parts, kept where `options` say.
+    pub(crate) fn empty(options: &DocxOptions) -> Result<Package> {
+        #[cfg(feature = "native")]
+        if !options.in_memory {
+            return Ok(Package::Disk(WorkDir::new(options.secure_delete)?));
+        }
+        #[cfg(not(feature = "native"))]
+        let _ = options;
+        Ok(Package::Memory(BTreeMap::new()))
+    }
+
+    /// The parts of the template, or of the built-in one.
+    pub(crate) fn new(options: &DocxOptions) -> Result<Package> {
+        let mut package = Package::empty(options)?;
+        let Some(template) = &options.template else {
+            for (name, data) in skeleton() {
+                package.put(name, data.into_bytes())?;
+            }
+            return Ok(package);
+        };
+        let template = std::fs::read(long_path(template)).map_err(|_| Error::TemplateInvalid)?;
+        let mut template =
+            zip::ZipArchive::new(io::Cursor::new(template)).map_err(|_| Error::TemplateInvalid)?;
+        for i in 0..template.len() {
+            let mut file = template.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let mut data = Vec::new();
+            io::Read::read_to_end(&mut file, &mut data)?;
+            package.put(file.name(), data)?;
+        }
+        Ok(package)
+    }
+
+    /// Part names always use `/`, which verbatim Windows paths do not accept.
+    #[cfg(feature = "native")]
+    fn part_path(dir: &WorkDir, name: &str) -> PathBuf {
+        let mut path = long_path(dir.path());
+        path.extend(name.split('/'));
+        path
+    }
+
+    pub(crate) fn put(&mut self, name: &str, data: Vec<u8>) -> Result<()> {
+        match self {
+            #[cfg(feature = "native")]
+            Package::Disk(dir) => {
+                let path = Package::part_path(dir, name);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                write(path, data)?;
+            }
+            Package::Memory(parts) => {
+                parts.insert(name.to_owned(), data);
+            }
+            #[cfg(feature = "native")]
+            Package::Streaming(stream) => stream.put(name, data)?,
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "native")]
+            Package::Disk(dir) => Ok(std::fs::read(Package::part_path(dir, name))?),
+            Package::Memory(parts) => parts.get(name).cloned().ok_or_else(|| missing(name)),
+            #[cfg(feature = "native")]
+            Package::Streaming(stream) if stream.streamed.contains(name) => {
+                let message = format!("part {} is in the output already", name);
+                Err(io::Error::new(io::ErrorKind::Unsupported, message).into())
+            }
+            #[cfg(feature = "native")]
+            Package::Streaming(stream) => stream.rest.get(name),
+        }
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> Result<()> {
+        match self {
+            #[cfg(feature = "native")]
+            Package::Disk(dir) => remove_file(Package::part_path(dir, name))?,
+            Package::Memory(parts) => {
+                parts.remove(name).ok_or_else(|| missing(name))?;
+            }
+            #[cfg(feature = "native")]
+            Package::Streaming(stream) if stream.streamed.contains(name) => {
+                let message = format!("part {} is in the output already", name);
+                return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+            }
+            #[cfg(feature = "native")]
+            Package::Streaming(stream) => stream.rest.remove(name)?,
+        }
+        Ok(())
+    }
+
+    /// Part names in zip order: content types, or the ODF mimetype, first,
+    /// then alphabetically. Those of a streaming package are the parts not
+    /// in the zip yet.
+    pub(crate) fn names(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = match self {
+            #[cfg(feature = "native")]
+            Package::Disk(dir) => {
+                let mut files = Vec::new();
+                collect_files(dir.path(), &mut files)?;
+                files
+                    .iter()
+                    .map(|file| {
+                        Ok(file
+                            .strip_prefix(dir.path())
+                            .unwrap()
+                            .iter()
+                            .map(|c| {
+                                c.to_str()
+                                    .ok_or_else(|| Error::invalid_data("part name is not UTF-8"))
+                            })
+                            .collect::<Result<Vec<_>>>()?
+                            .join("/"))
+                    })
+                    .collect::<Result<Vec<_>>>()?
+            }
+            Package::Memory(parts) => parts.keys().cloned().collect(),
+            #[cfg(feature = "native")]
+            Package::Streaming(stream) => return stream.rest.names(),
+        };
+        let rest = |name: &str| name != CONTENT_TYPES && name != MIMETYPE;
+        names.sort_by(|a, b| (rest(a), a).cmp(&(rest(b), b)));
+        Ok(names)
+    }
+
+    /// Zips the parts into `writer`, as [`PartOptions`] says. A streaming
+    /// package cannot be zipped again; see [`Package::finish`].
+    pub(crate) fn write_zip<W: Write + io::Seek>(
+        &self,
+        writer: W,
+        scrub_metadata: bool,
+        deflate_level: Option<u32>,
+    ) -> Result<()> {
+        #[cfg(feature = "native")]
+        if let Package::Streaming(_) = self {
+            let message = "the package is being streamed to a file";
+            return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+        }
+        let options = PartOptions::new(scrub_metadata, deflate_level);
+        let mut zip = zip::ZipWriter::new(writer);
+        for name in self.names()? {
+            zip.start_file(name.as_str(), options.of(&name))?;
+            zip.write_all(&self.get(&name)?)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// Starts writing the media of the package into a zip at `path` as
+    /// they are put, the ones already in it first. The other parts wait for
+    /// [`Package::finish`]. Media in the zip cannot be removed any more.
+    #[cfg(feature = "native")]
+    pub(crate) fn stream_to(
+        &mut self,
+        path: &Path,
+        scrub_metadata: bool,
+        deflate_level: Option<u32>,
+    ) -> Result<()> {
+        if let Package::Streaming(_) = self {
+            return Ok(());
+        }
+        let file = File::create(path).map_err(|e| Error::from(e).at(path))?;
+        let rest = std::mem::replace(self, Package::Memory(BTreeMap::new()));
+        let mut stream = Stream {
+            path: path.to_owned(),
+            zip: Some(zip::ZipWriter::new(file)),
+            options: PartOptions::new(scrub_metadata, deflate_level),
+            streamed: BTreeSet::new(),
+            rest,
+        };
+        for name in stream.rest.names()? {
+            if is_media(&name) {
+                let data = stream.rest.get(&name)?;
+                stream.rest.remove(&name)?;
+                stream.put(&name, data)?;
+            }
+        }
+        *self = Package::Streaming(Box::new(stream));
+        Ok(())
+    }
+
+    /// Writes the parts that waited into the zip of a streaming package
+    /// and finishes it, returning its path.
+    #[cfg(feature = "native")]
+    pub(crate) fn finish(self) -> Result<PathBuf> {
+        let Package::Streaming(mut stream) = self else {
+            let message = "the package is not being streamed";
+            return Err(io::Error::new(io::ErrorKind::Unsupported, message).into());
+        };
+        let mut zip = stream.zip.take().expect("a stream is finished once");
+        for name in stream.rest.names()? {
+            zip.start_file(name.as_str(), stream.options.of(&name))?;
+            zip.write_all(&stream.rest.get(&name)?)?;
+        }
+        zip.finish()?;
+        Ok(std::mem::take(&mut stream.path))
+    }
+}
+
+/// Whether the part is an image of a page, which a streaming package writes
+/// as soon as it is put.
+#[cfg(feature = "native")]
+fn is_media(name: &str) -> bool {
+    name.starts_with("word/media/")
+}
+
+/// A zip written as the pages are converted, so that their images are on
+/// disk once, in the output, instead of in a temporary directory and then
+/// again in the output.
+#[cfg(feature = "native")]
+pub(crate) struct Stream {
+    path: PathBuf,
+    /// Taken when finished.
+    zip: Option<zip::ZipWriter<File>>,
+    options: PartOptions,
+    /// Media already in the zip, which cannot be read back or taken out.
+    streamed: BTreeSet<String>,
+    /// The parts that are not media, written when the zip is finished.
+    rest: Package,
+}
+
+#[cfg(feature = "native")]
+impl Stream {
+    /// Writes media to the zip right away, and keeps other parts for later.
+    /// Media in the zip already cannot be replaced: a second entry of the
+    /// same name would make the package invalid.
+    fn put(&mut self, name: &str, data: Vec<u8>) -> Result<()> {
+        if self.streamed.contains(name) {
+            let message = format!("part {} is in the output already", name);
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, message).into());
+        }
+        if !is_media(name) {
+            return self.rest.put(name, data);
+        }
+        let zip = self.zip.as_mut().expect("a finished stream takes no parts");
+        zip.start_file(name, self.options.of(name))?;
+        zip.write_all(&data)?;
+        self.streamed.insert(name.to_owned());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "native")]
+impl Drop for Stream {
+    /// An unfinished zip is no DOCX, so it goes.
+    fn drop(&mut self) {
+        if self.zip.take().is_some() {
+            let _ = remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_shred_file() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("secret.svg");
+        write(&file, "secret").unwrap();
+        shred_file(&file).unwrap();
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn test_deflate() {
+        let mut package = Package::Memory(BTreeMap::new());
+        package.put("word/document.xml", vec![b'a'; 4096]).unwrap();
+        package.put("word/media/1.png", vec![b'a'; 4096]).unwrap();
+        let zip = |deflate_level| {
+            let mut buf = io::Cursor::new(Vec::new());
+            package.write_zip(&mut buf, false, deflate_level).unwrap();
+            zip::ZipArchive::new(buf).unwrap()
+        };
+        let mut stored = zip(None);
+        assert_eq!(
+            stored
+                .by_name("word/document.xml")
+                .unwrap()
+                .compressed_size(),
+            4096
+        );
+        let mut deflated = zip(Some(9));
+        assert!(deflated.by_name("word/document.xml").unwrap().compressed_size() < 100);
+        assert_eq!(deflated.by_name("word/media/1.png").unwrap().compressed_size(), 4096);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_stream_duplicate() {
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("a.docx");
+        let mut package = Package::Memory(BTreeMap::new());
+        package.put("word/media/1.png", vec![1]).unwrap();
+        package.stream_to(&output, false, None).unwrap();
+        package.put("word/media/2.png", vec![2]).unwrap();
+        for name in ["word/media/1.png", "word/media/2.png"] {
+            assert!(matches!(
+                package.put(name, vec![3]),
+                Err(Error::IoError { source, .. }) if source.kind() == io::ErrorKind::AlreadyExists
+            ));
+        }
+        package.put(CONTENT_TYPES, vec![4]).unwrap();
+        let output = package.finish().unwrap();
+        let mut zip = zip::ZipArchive::new(File::open(output).unwrap()).unwrap();
+        assert_eq!(zip.len(), 3);
+        assert_eq!(zip.by_name("word/media/1.png").unwrap().size(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_checksum_sidecar() {
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("a.docx");
+        write(&output, "abc").unwrap();
+        let sidecar = write_checksum(&output).unwrap();
+        assert_eq!(sidecar, dir.path().join("a.docx.sha256"));
+        assert_eq!(
+            std::fs::read_to_string(sidecar).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  a.docx\n"
+        );
+    }
+
+    #[test]
+    fn test_verbatim() {
+        assert_eq!(
+            verbatim(r"C:\Users\me/out.docx").unwrap(),
+            r"\\?\C:\Users\me\out.docx"
+        );
+        assert_eq!(
+            verbatim(r"\\server\share\out.docx").unwrap(),
+            r"\\?\UNC\server\share\out.docx"
+        );
+        assert_eq!(verbatim(r"\\?\C:\out.docx"), None);
+        assert_eq!(verbatim("out.docx"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_prepare_output() {
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("a/b/c.docx");
+        prepare_output(&output).unwrap();
+        assert!(output.parent().unwrap().is_dir());
+        assert!(!output.exists());
+        write(&output, "abc").unwrap();
+        prepare_output(&output).unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"abc");
+        assert!(matches!(
+            prepare_output(&output.join("d.docx")),
+            Err(Error::OutputNotWritable)
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_lock_output() {
+        let dir = TempDir::new().unwrap();
+        let output = dir.path().join("a.docx");
+        let lock = lock_output(&output, false).unwrap();
+        assert!(dir.path().join("a.docx.lock").exists());
+        assert!(matches!(
+            lock_output(&output, false),
+            Err(Error::OutputLocked)
+        ));
+        drop(lock);
+        assert!(!dir.path().join("a.docx.lock").exists());
+        lock_output(&output, true).unwrap();
+    }
+}
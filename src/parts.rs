@@ -0,0 +1,327 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! `[Content_Types].xml` and the relationship parts, kept as lists that the
+//! parts of the package are registered in and written out once complete.
+
+use crate::ooxml::{attribute, escape, percent_encode};
+
+const CONTENT_TYPES_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/package/2006/content-types";
+pub(crate) const RELATIONSHIPS_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships";
+
+pub(crate) const OFFICE_DOCUMENT: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+pub(crate) const IMAGE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+pub(crate) const HYPERLINK: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+pub(crate) const HEADER: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/header";
+pub(crate) const FOOTER: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/footer";
+pub(crate) const CORE_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+pub(crate) const EXTENDED_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties";
+pub(crate) const CUSTOM_PROPERTIES: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
+pub(crate) const SIGNATURE_ORIGIN: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/origin";
+pub(crate) const SIGNATURE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/signature";
+
+/// The content types of the parts: by extension, and by name for the parts
+/// whose extension does not tell.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ContentTypes {
+    defaults: Vec<(String, String)>,
+    overrides: Vec<(String, String)>,
+}
+
+impl ContentTypes {
+    /// Relationship parts and XML, which every package has.
+    pub(crate) fn new() -> ContentTypes {
+        let mut types = ContentTypes::default();
+        types.add_default(
+            "rels",
+            "application/vnd.openxmlformats-package.relationships+xml",
+        );
+        types.add_default("xml", "application/xml");
+        types
+    }
+
+    /// The declarations in `xml`, as of a template, leaving out the rest.
+    pub(crate) fn parse(xml: &str) -> ContentTypes {
+        let mut types = ContentTypes::default();
+        for tag in xml.split('<') {
+            let content_type = attribute(tag, "ContentType").map(unescape);
+            if tag.starts_with("Default ") {
+                if let (Some(extension), Some(content_type)) =
+                    (attribute(tag, "Extension"), content_type)
+                {
+                    types.defaults.push((unescape(extension), content_type));
+                }
+            } else if tag.starts_with("Override ") {
+                if let (Some(name), Some(content_type)) = (attribute(tag, "PartName"), content_type)
+                {
+                    types.overrides.push((unescape(name), content_type));
+                }
+            }
+        }
+        types
+    }
+
+    /// Declares the parts ending in `.extension` of `content_type`, unless
+    /// some content type is declared for them already.
+    pub(crate) fn add_default(&mut self, extension: &str, content_type: &str) {
+        if !self
+            .defaults
+            .iter()
+            .any(|(known, _)| known.eq_ignore_ascii_case(extension))
+        {
+            self.defaults
+                .push((extension.to_owned(), content_type.to_owned()));
+        }
+    }
+
+    /// Declares part `name` of `content_type`, unless it has a declared
+    /// content type of its own already.
+    pub(crate) fn add_override(&mut self, name: &str, content_type: &str) {
+        let part_name = format!("/{}", name);
+        if !self
+            .overrides
+            .iter()
+            .any(|(known, _)| known.eq_ignore_ascii_case(&part_name))
+        {
+            self.overrides.push((part_name, content_type.to_owned()));
+        }
+    }
+
+    /// The content type of part `name`, from its override or else its
+    /// extension.
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        let part_name = format!("/{}", name);
+        let extension = name.rsplit('.').next()?;
+        self.overrides
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(&part_name))
+            .or_else(|| {
+                self.defaults
+                    .iter()
+                    .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+            })
+            .map(|(_, content_type)| content_type.as_str())
+    }
+
+    pub(crate) fn to_xml(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?><Types xmlns=\"{}\">",
+            CONTENT_TYPES_NAMESPACE
+        );
+        for (extension, content_type) in &self.defaults {
+            xml += &format!(
+                "<Default Extension=\"{}\" ContentType=\"{}\"/>",
+                escape(extension),
+                escape(content_type)
+            );
+        }
+        for (name, content_type) in &self.overrides {
+            xml += &format!(
+                "<Override PartName=\"{}\" ContentType=\"{}\"/>",
+                escape(name),
+                escape(content_type)
+            );
+        }
+        xml + "</Types>"
+    }
+}
+
+/// A relationship from a part, or the package, to another part or to a
+/// resource outside the package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Relationship {
+    pub(crate) id: String,
+    pub(crate) kind: String,
+    /// Percent-encoded, but not XML-escaped.
+    pub(crate) target: String,
+    pub(crate) external: bool,
+}
+
+impl Relationship {
+    /// To the part at `path`, relative to the folder of the source part.
+    pub(crate) fn part(id: impl Into<String>, kind: &str, path: &str) -> Relationship {
+        Relationship {
+            id: id.into(),
+            kind: kind.to_owned(),
+            target: percent_encode(path),
+            external: false,
+        }
+    }
+
+    /// To `uri`, outside the package.
+    pub(crate) fn external(id: impl Into<String>, kind: &str, uri: &str) -> Relationship {
+        Relationship {
+            id: id.into(),
+            kind: kind.to_owned(),
+            target: uri.to_owned(),
+            external: true,
+        }
+    }
+
+    pub(crate) fn to_xml(&self) -> String {
+        let (id, kind, target) = (escape(&self.id), escape(&self.kind), escape(&self.target));
+        if self.external {
+            format_xml::xml! {
+                <Relationship Id={id} Type={kind} Target={target} TargetMode="External"/>
+            }
+            .to_string()
+        } else {
+            format_xml::xml! {
+                <Relationship Id={id} Type={kind} Target={target}/>
+            }
+            .to_string()
+        }
+    }
+}
+
+/// The relationships of one part, or of the package.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Relationships(Vec<Relationship>);
+
+impl Relationships {
+    pub(crate) fn new() -> Relationships {
+        Relationships::default()
+    }
+
+    /// The relationships in `xml`, as of a template.
+    pub(crate) fn parse(xml: &str) -> Relationships {
+        let relationships = xml
+            .split('<')
+            .filter(|tag| tag.starts_with("Relationship "))
+            .filter_map(|tag| {
+                Some(Relationship {
+                    id: unescape(attribute(tag, "Id")?),
+                    kind: unescape(attribute(tag, "Type")?),
+                    target: unescape(attribute(tag, "Target")?),
+                    external: attribute(tag, "TargetMode") == Some("External"),
+                })
+            })
+            .collect();
+        Relationships(relationships)
+    }
+
+    pub(crate) fn push(&mut self, relationship: Relationship) {
+        self.0.push(relationship);
+    }
+
+    /// Adds `relationship` unless there is one of its kind to its target.
+    pub(crate) fn add(&mut self, relationship: Relationship) {
+        if !self
+            .0
+            .iter()
+            .any(|known| known.kind == relationship.kind && known.target == relationship.target)
+        {
+            self.0.push(relationship);
+        }
+    }
+
+    /// The `Relationship` elements, for a part that has them in a
+    /// placeholder.
+    pub(crate) fn elements(&self) -> String {
+        self.0.iter().map(Relationship::to_xml).collect()
+    }
+
+    pub(crate) fn to_xml(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?><Relationships xmlns=\"{}\">{}</Relationships>",
+            RELATIONSHIPS_NAMESPACE,
+            self.elements()
+        )
+    }
+}
+
+impl Extend<Relationship> for Relationships {
+    fn extend<T: IntoIterator<Item = Relationship>>(&mut self, iter: T) {
+        self.0.extend(iter);
+    }
+}
+
+impl IntoIterator for Relationships {
+    type Item = Relationship;
+    type IntoIter = std::vec::IntoIter<Relationship>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+/// An attribute value with the predefined entities of XML replaced.
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_types() {
+        let mut types = ContentTypes::new();
+        types.add_default("png", "image/png");
+        types.add_default("PNG", "image/x-png");
+        types.add_override(
+            "word/document.xml",
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml",
+        );
+        assert_eq!(ContentTypes::parse(&types.to_xml()), types);
+        assert_eq!(
+            types.get("word/document.xml").unwrap(),
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"
+        );
+        assert_eq!(types.get("word/media/1.PNG"), Some("image/png"));
+        assert_eq!(types.get("word/styles.xml"), Some("application/xml"));
+        assert_eq!(types.get("word/media/1.gif"), None);
+    }
+
+    #[test]
+    fn test_relationships() {
+        let mut rels = Relationships::new();
+        rels.add(Relationship::part("rId1", IMAGE, "media/a b&c.png"));
+        rels.add(Relationship::part("rId2", IMAGE, "media/a b&c.png"));
+        rels.add(Relationship::external(
+            "rId3",
+            HYPERLINK,
+            "https://a.example/?a=1&b=2",
+        ));
+        assert_eq!(
+            rels.elements(),
+            format!(
+                "<Relationship Id=\"rId1\" Type=\"{}\" Target=\"media/a%20b&amp;c.png\" />\
+                 <Relationship Id=\"rId3\" Type=\"{}\" Target=\"https://a.example/?a=1&amp;b=2\" \
+                 TargetMode=\"External\" />",
+                IMAGE, HYPERLINK
+            )
+        );
+        assert_eq!(Relationships::parse(&rels.to_xml()), rels);
+    }
+}
@@ -0,0 +1,780 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Heuristics over the raw bytes of a PDF, for what is worth knowing before
+//! the renderer runs. Objects inside compressed object streams are not seen.
+
+use crate::ooxml::{LinkTarget, PageLink};
+use crate::Metadata;
+use std::collections::{HashMap, HashSet};
+
+pub(crate) fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn rfind(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+/// Whether the trailer names an `/Encrypt` dictionary. PDFs with only an
+/// owner password are encrypted too, but open without one.
+pub(crate) fn is_encrypted(data: &[u8]) -> bool {
+    trailer(data).is_some_and(|trailer| find(trailer, b"/Encrypt").is_some())
+}
+
+/// The last trailer: the cross-reference section the last `startxref`
+/// points to, a table with its `trailer` dictionary or a stream object
+/// with its own, up to the stream data. Without a usable `startxref`, the
+/// last `trailer` dictionary.
+fn trailer(data: &[u8]) -> Option<&[u8]> {
+    let end = rfind(data, b"startxref");
+    let start = end
+        .and_then(|end| Some((end, count(&data[end + 9..])?)))
+        .filter(|&(end, offset)| offset < end)
+        .map(|(_, offset)| offset);
+    let start = match start {
+        Some(start) => start,
+        None => rfind(&data[..end.unwrap_or(data.len())], b"trailer")?,
+    };
+    let section = &data[start..end.unwrap_or(data.len())];
+    Some(&section[..find(section, b"stream").unwrap_or(section.len())])
+}
+
+/// The `/Count` of the page tree root the last trailer leads to. When the
+/// catalog cannot be found, as in a compressed object stream, it is the
+/// largest of the `/Pages` nodes, which counts too many pages if an
+/// incremental update left an old page tree behind.
+pub(crate) fn page_count(data: &[u8]) -> Option<usize> {
+    root_page_count(data).or_else(|| largest_page_count(data))
+}
+
+fn root_page_count(data: &[u8]) -> Option<usize> {
+    let at = rfind(data, b"/Root")?;
+    let (number, generation) = reference(&data[at + 5..])?;
+    let catalog = object(data, number, generation)?;
+    let at = find(catalog, b"/Pages")?;
+    let (number, generation) = reference(&catalog[at + 6..])?;
+    let root = object(data, number, generation)?;
+    let at = find(root, b"/Count")?;
+    count(&root[at + 6..])
+}
+
+fn largest_page_count(data: &[u8]) -> Option<usize> {
+    let mut largest = None;
+    let mut offset = 0;
+    while let Some(found) = find(&data[offset..], b"/Pages") {
+        let at = offset + found;
+        offset = at + 6;
+        // `/Type /Pages`, not the `/Pages` entry of the catalog.
+        let before = &data[at.saturating_sub(16)..at];
+        let Some(type_at) = rfind(before, b"/Type") else {
+            continue;
+        };
+        if !before[type_at + 5..].iter().all(u8::is_ascii_whitespace) {
+            continue;
+        }
+        let start = rfind(&data[..at], b"<<").unwrap_or(0);
+        let end = find(&data[at..], b">>").map_or(data.len(), |end| at + end);
+        let Some(count_at) = find(&data[start..end], b"/Count") else {
+            continue;
+        };
+        largest = largest.max(count(&data[start + count_at + 6..end]));
+    }
+    largest
+}
+
+/// The number at the start of `data`, after any whitespace.
+fn count(data: &[u8]) -> Option<usize> {
+    let digits: String = data
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take_while(|b| b.is_ascii_digit())
+        .map(|&b| b as char)
+        .collect();
+    digits.parse().ok()
+}
+
+/// The body of object `number generation`, up to `endobj`. The last
+/// definition wins, as an incremental update replaces the ones before it.
+fn object(data: &[u8], number: u32, generation: u32) -> Option<&[u8]> {
+    let header = format!("{} {} obj", number, generation);
+    let mut before = data.len();
+    loop {
+        let at = rfind(&data[..before], header.as_bytes())?;
+        before = at;
+        // Not the end of `15 0 obj` when looking for `5 0 obj`.
+        if at == 0 || !data[at - 1].is_ascii_digit() {
+            let offset = at + header.len();
+            let end = find(&data[offset..], b"endobj").map_or(data.len(), |end| offset + end);
+            return Some(&data[offset..end]);
+        }
+    }
+}
+
+/// The `N G R` reference at the start of `data`, after any whitespace.
+fn reference(data: &[u8]) -> Option<Reference> {
+    let data = &data[..data.len().min(40)];
+    let data = String::from_utf8_lossy(data);
+    let mut words = data.split_whitespace();
+    let number = words.next()?.parse().ok()?;
+    let generation = words.next()?.parse().ok()?;
+    words
+        .next()?
+        .starts_with('R')
+        .then_some((number, generation))
+}
+
+/// The document information dictionary the last trailer points to.
+fn info_dictionary(data: &[u8]) -> Option<&[u8]> {
+    let at = rfind(data, b"/Info")?;
+    let (number, generation) = reference(&data[at + 5..])?;
+    object(data, number, generation)
+}
+
+/// The bytes of the string at the start of `data`, literal or hex.
+fn string(data: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match data.first()? {
+        b'(' => {
+            let mut depth = 0;
+            let mut chars = data.iter().copied();
+            while let Some(c) = chars.next() {
+                match c {
+                    b'(' if depth == 0 => depth = 1,
+                    b'(' => {
+                        depth += 1;
+                        bytes.push(c);
+                    }
+                    b')' if depth == 1 => return Some(bytes),
+                    b')' => {
+                        depth -= 1;
+                        bytes.push(c);
+                    }
+                    b'\\' => match chars.next()? {
+                        b'n' => bytes.push(b'\n'),
+                        b'r' => bytes.push(b'\r'),
+                        b't' => bytes.push(b'\t'),
+                        b'b' => bytes.push(8),
+                        b'f' => bytes.push(12),
+                        b'\n' => {}
+                        digit @ b'0'..=b'7' => {
+                            // Up to three octal digits.
+                            let mut value = u32::from(digit - b'0');
+                            let rest = chars.clone().take(2).take_while(u8::is_ascii_digit);
+                            for digit in rest {
+                                value = value * 8 + u32::from(digit - b'0');
+                                chars.next();
+                            }
+                            bytes.push(value as u8);
+                        }
+                        other => bytes.push(other),
+                    },
+                    _ => bytes.push(c),
+                }
+            }
+            None
+        }
+        b'<' => {
+            let end = find(data, b">")?;
+            let digits: Vec<u8> = data[1..end]
+                .iter()
+                .filter(|b| !b.is_ascii_whitespace())
+                .copied()
+                .collect();
+            for pair in digits.chunks(2) {
+                let hex = format!("{:0<2}", String::from_utf8_lossy(pair));
+                bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+            }
+            Some(bytes)
+        }
+        _ => None,
+    }
+}
+
+/// Text in UTF-16 with a byte order mark, or else in PDFDocEncoding, taken
+/// as Latin-1.
+fn text(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(b"\xfe\xff") {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// What follows `key` in `dictionary`, from its first non-blank byte.
+fn value<'a>(dictionary: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut offset = 0;
+    loop {
+        let at = offset + find(&dictionary[offset..], key)?;
+        offset = at + key.len();
+        // Not `/Titles` when looking for `/Title`.
+        let rest = &dictionary[offset..];
+        if rest.first().is_some_and(u8::is_ascii_alphanumeric) {
+            continue;
+        }
+        let start = rest.iter().position(|b| !b.is_ascii_whitespace())?;
+        return Some(&rest[start..]);
+    }
+}
+
+/// The string under `key` in `dictionary`, passing over `key` where it is a
+/// value, as the first `/URI` of `/S /URI /URI (…)`.
+fn entry(dictionary: &[u8], key: &[u8]) -> Option<Vec<u8>> {
+    let mut rest = dictionary;
+    loop {
+        rest = value(rest, key)?;
+        if let Some(string) = string(rest) {
+            return Some(string);
+        }
+    }
+}
+
+/// Days from 1970-01-01 to a date of the proleptic Gregorian calendar, after
+/// Howard Hinnant.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// A PDF date, `D:YYYYMMDDHHmmSSOHH'mm'` with everything after the year
+/// optional, as a Unix timestamp.
+fn date(text: &str) -> Option<u64> {
+    let text = text.strip_prefix("D:").unwrap_or(text);
+    let digits: String = text.chars().take_while(char::is_ascii_digit).collect();
+    let field = |range: std::ops::Range<usize>, default: i64| {
+        digits.get(range).map_or(Some(default), |s| s.parse().ok())
+    };
+    let year = digits.get(0..4)?.parse().ok()?;
+    let (month, day) = (field(4..6, 1)?, field(6..8, 1)?);
+    let (hour, minute, second) = (field(8..10, 0)?, field(10..12, 0)?, field(12..14, 0)?);
+    let mut secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    let zone = &text[digits.len()..];
+    if let Some(sign @ ('+' | '-')) = zone.chars().next() {
+        let offset: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+        let hours: i64 = offset.get(0..2)?.parse().ok()?;
+        let minutes: i64 = offset.get(2..4).map_or(Some(0), |s| s.parse().ok())?;
+        let offset = hours * 3600 + minutes * 60;
+        secs -= if sign == '+' { offset } else { -offset };
+    }
+    u64::try_from(secs).ok()
+}
+
+/// The document information dictionary, as [`Metadata`].
+pub(crate) fn metadata(data: &[u8]) -> Metadata {
+    let Some(info) = info_dictionary(data) else {
+        return Metadata::default();
+    };
+    let text = |key: &[u8]| {
+        entry(info, key)
+            .map(|bytes| text(&bytes))
+            .filter(|text| !text.trim().is_empty())
+    };
+    Metadata {
+        title: text(b"/Title"),
+        author: text(b"/Author"),
+        subject: text(b"/Subject"),
+        keywords: text(b"/Keywords"),
+        created: text(b"/CreationDate").as_deref().and_then(date),
+        modified: text(b"/ModDate").as_deref().and_then(date),
+    }
+}
+
+/// The number and generation of an object.
+type Reference = (u32, u32);
+
+/// An outline item that leads to a page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OutlineItem {
+    /// From 1 for the top level.
+    pub(crate) level: usize,
+    pub(crate) title: String,
+    /// From 1.
+    pub(crate) page: usize,
+}
+
+/// Outlines nested deeper than this are cut off, and page trees taller than
+/// this are not followed.
+const MAX_DEPTH: usize = 32;
+
+/// Where each object starts in a PDF, so that following references does not
+/// search the whole file every time. Later definitions win, as they do for
+/// incremental updates.
+struct Objects<'a> {
+    data: &'a [u8],
+    offsets: HashMap<Reference, usize>,
+}
+
+impl<'a> Objects<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        let mut offsets = HashMap::new();
+        let mut offset = 0;
+        while let Some(found) = find(&data[offset..], b" obj") {
+            let at = offset + found;
+            offset = at + 4;
+            let before = String::from_utf8_lossy(&data[at.saturating_sub(24)..at]);
+            let mut words = before.split_whitespace().rev();
+            let (Some(generation), Some(number)) = (words.next(), words.next()) else {
+                continue;
+            };
+            if let (Ok(number), Ok(generation)) = (number.parse(), generation.parse()) {
+                offsets.insert((number, generation), offset);
+            }
+        }
+        Objects { data, offsets }
+    }
+
+    /// The body of the object `reference` points to, up to `endobj`.
+    fn get(&self, reference: Reference) -> Option<&'a [u8]> {
+        let start = *self.offsets.get(&reference)?;
+        let end = find(&self.data[start..], b"endobj").map_or(self.data.len(), |end| start + end);
+        Some(&self.data[start..end])
+    }
+
+    /// The object `data` refers to if it starts with a reference, else
+    /// `data` itself.
+    fn resolve<'b>(&self, data: &'b [u8]) -> Option<&'b [u8]>
+    where
+        'a: 'b,
+    {
+        match reference(data) {
+            Some(reference) => self.get(reference).map(<[u8]>::trim_ascii_start),
+            None => Some(data),
+        }
+    }
+}
+
+/// The references in the array at the start of `data`.
+fn references(data: &[u8]) -> Vec<Reference> {
+    let Some(array) = data.strip_prefix(b"[") else {
+        return Vec::new();
+    };
+    let end = find(array, b"]").unwrap_or(array.len());
+    let array = String::from_utf8_lossy(&array[..end]);
+    let words: Vec<&str> = array.split_whitespace().collect();
+    words
+        .windows(3)
+        .filter(|words| words[2] == "R")
+        .filter_map(|words| Some((words[0].parse().ok()?, words[1].parse().ok()?)))
+        .collect()
+}
+
+/// Appends the leaves of the page tree under `node` to `pages`, in order.
+fn pages(objects: &Objects, node: Reference, pages: &mut Vec<Reference>, depth: usize) {
+    let Some(body) = objects.get(node) else {
+        return;
+    };
+    match value(body, b"/Kids") {
+        Some(kids) if depth < MAX_DEPTH => {
+            for kid in references(kids) {
+                self::pages(objects, kid, pages, depth + 1);
+            }
+        }
+        Some(_) => {}
+        None => pages.push(node),
+    }
+}
+
+/// The page an outline item's `/Dest` or `/GoTo` action leads to. Named
+/// destinations are not looked up.
+fn destination(objects: &Objects, item: &[u8]) -> Option<Reference> {
+    let destination = match value(item, b"/Dest") {
+        Some(destination) => destination,
+        None => value(objects.resolve(value(item, b"/A")?)?, b"/D")?,
+    };
+    references(objects.resolve(destination)?).first().copied()
+}
+
+/// Appends the outline items from `first` on and their children to `items`.
+fn outline_items(
+    objects: &Objects,
+    first: Option<Reference>,
+    level: usize,
+    page_numbers: &HashMap<Reference, usize>,
+    visited: &mut HashSet<Reference>,
+    items: &mut Vec<OutlineItem>,
+) {
+    let mut next = first;
+    while let Some(current) = next {
+        if level > MAX_DEPTH || !visited.insert(current) {
+            return;
+        }
+        let Some(item) = objects.get(current) else {
+            return;
+        };
+        let title = entry(item, b"/Title").map(|bytes| text(&bytes));
+        let page = destination(objects, item).and_then(|page| page_numbers.get(&page));
+        if let (Some(title), Some(&page)) = (title, page) {
+            let title = title.trim().to_string();
+            if !title.is_empty() {
+                items.push(OutlineItem { level, title, page });
+            }
+        }
+        let first_child = value(item, b"/First").and_then(reference);
+        outline_items(
+            objects,
+            first_child,
+            level + 1,
+            page_numbers,
+            visited,
+            items,
+        );
+        next = value(item, b"/Next").and_then(reference);
+    }
+}
+
+/// The catalog the last trailer points to, and the pages of its page tree
+/// in order.
+fn page_tree<'a>(objects: &Objects<'a>, data: &[u8]) -> Option<(&'a [u8], Vec<Reference>)> {
+    let catalog = rfind(data, b"/Root")
+        .and_then(|at| reference(&data[at + 5..]))
+        .and_then(|root| objects.get(root))?;
+    let mut page_refs = Vec::new();
+    if let Some(root) = value(catalog, b"/Pages").and_then(reference) {
+        pages(objects, root, &mut page_refs, 0);
+    }
+    Some((catalog, page_refs))
+}
+
+/// The number of each page, from 1.
+fn page_numbers(page_refs: &[Reference]) -> HashMap<Reference, usize> {
+    page_refs
+        .iter()
+        .enumerate()
+        .map(|(i, &page)| (page, i + 1))
+        .collect()
+}
+
+/// The document outline, or bookmarks, in order. Items that lead nowhere
+/// are left out, but their children are not.
+pub(crate) fn outline(data: &[u8]) -> Vec<OutlineItem> {
+    let objects = Objects::new(data);
+    let mut items = Vec::new();
+    let Some((catalog, page_refs)) = page_tree(&objects, data) else {
+        return items;
+    };
+    let Some(outlines) = value(catalog, b"/Outlines")
+        .and_then(reference)
+        .and_then(|outlines| objects.get(outlines))
+    else {
+        return items;
+    };
+    let first = value(outlines, b"/First").and_then(reference);
+    outline_items(
+        &objects,
+        first,
+        1,
+        &page_numbers(&page_refs),
+        &mut HashSet::new(),
+        &mut items,
+    );
+    items
+}
+
+/// The numbers in the array at the start of `data`.
+fn numbers(data: &[u8]) -> Vec<f64> {
+    let Some(array) = data.strip_prefix(b"[") else {
+        return Vec::new();
+    };
+    let end = find(array, b"]").unwrap_or(array.len());
+    String::from_utf8_lossy(&array[..end])
+        .split_whitespace()
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+/// `key` of `page`, or of the nearest of its ancestors in the page tree that
+/// has it.
+fn inherited<'a>(objects: &Objects<'a>, page: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    let mut node = page;
+    for _ in 0..MAX_DEPTH {
+        if let Some(value) = value(node, key) {
+            return Some(value);
+        }
+        node = objects.get(value(node, b"/Parent").and_then(reference)?)?;
+    }
+    None
+}
+
+/// The crop box of `page`, or else its media box.
+fn visible_area(objects: &Objects, page: &[u8]) -> Vec<f64> {
+    inherited(objects, page, b"/CropBox")
+        .or_else(|| inherited(objects, page, b"/MediaBox"))
+        .map(numbers)
+        .unwrap_or_default()
+}
+
+/// The `/Rotate` of `page`, in degrees clockwise.
+fn rotation(objects: &Objects, page: &[u8]) -> i64 {
+    inherited(objects, page, b"/Rotate")
+        .and_then(|rotate| {
+            let rotate = String::from_utf8_lossy(&rotate[..rotate.len().min(8)]);
+            rotate.split_whitespace().next()?.parse().ok()
+        })
+        .unwrap_or(0)
+}
+
+/// The width and height of each page as shown, in points: its visible area
+/// turned by its `/Rotate`. Pages without a box are left out.
+pub(crate) fn page_sizes(data: &[u8]) -> Vec<(f64, f64)> {
+    let objects = Objects::new(data);
+    let Some((_, page_refs)) = page_tree(&objects, data) else {
+        return Vec::new();
+    };
+    page_refs
+        .into_iter()
+        .filter_map(|page_ref| {
+            let page = objects.get(page_ref)?;
+            let [left, bottom, right, top] = visible_area(&objects, page)[..] else {
+                return None;
+            };
+            let (width, height) = ((right - left).abs(), (top - bottom).abs());
+            Some(match rotation(&objects, page).rem_euclid(180) {
+                90 => (height, width),
+                _ => (width, height),
+            })
+        })
+        .collect()
+}
+
+/// Whether the PDF looks scanned: it has images but no fonts, so no text.
+/// `None` if it has compressed object streams, where fonts could hide.
+pub(crate) fn is_image_only(data: &[u8]) -> Option<bool> {
+    if find(data, b"/ObjStm").is_some() {
+        return None;
+    }
+    Some(find(data, b"/Font").is_none() && find(data, b"/Image").is_some())
+}
+
+/// `rect` in the default user space of a page with the visible `area`,
+/// turned clockwise by `rotate` degrees, as left, top, right and bottom
+/// fractions of the page as shown.
+fn fractions(rect: &[f64], area: &[f64], rotate: i64) -> Option<[f64; 4]> {
+    let ([x0, y0, x1, y1], [left, bottom, right, top]) = (rect, area) else {
+        return None;
+    };
+    let (width, height) = ((right - left).abs(), (top - bottom).abs());
+    if width == 0.0 || height == 0.0 {
+        return None;
+    }
+    let (left, top) = (left.min(*right), top.max(*bottom));
+    let x = |x: f64| ((x - left) / width).clamp(0.0, 1.0);
+    let y = |y: f64| ((top - y) / height).clamp(0.0, 1.0);
+    let corners = [(x(*x0), y(*y0)), (x(*x1), y(*y1))];
+    let [(x0, y0), (x1, y1)] = corners.map(|(x, y)| match rotate.rem_euclid(360) {
+        90 => (1.0 - y, x),
+        180 => (1.0 - x, 1.0 - y),
+        270 => (y, 1.0 - x),
+        _ => (x, y),
+    });
+    let fractions = [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)];
+    (fractions[0] < fractions[2] && fractions[1] < fractions[3]).then_some(fractions)
+}
+
+/// The link annotations of every page, as the page number and the link.
+/// Links to other pages lead to pages of the PDF.
+pub(crate) fn links(data: &[u8]) -> Vec<(usize, PageLink)> {
+    let objects = Objects::new(data);
+    let mut links = Vec::new();
+    let Some((_, page_refs)) = page_tree(&objects, data) else {
+        return links;
+    };
+    let page_numbers = page_numbers(&page_refs);
+    for (i, &page_ref) in page_refs.iter().enumerate() {
+        let Some(page) = objects.get(page_ref) else {
+            continue;
+        };
+        let Some(annotations) = value(page, b"/Annots").and_then(|data| objects.resolve(data))
+        else {
+            continue;
+        };
+        let area = visible_area(&objects, page);
+        let rotate = rotation(&objects, page);
+        for annotation in references(annotations) {
+            let Some(annotation) = objects.get(annotation) else {
+                continue;
+            };
+            let subtype = value(annotation, b"/Subtype").unwrap_or_default();
+            if !subtype.starts_with(b"/Link") {
+                continue;
+            }
+            let Some(area) = value(annotation, b"/Rect")
+                .and_then(|rect| fractions(&numbers(rect), &area, rotate))
+            else {
+                continue;
+            };
+            let uri = value(annotation, b"/A")
+                .and_then(|action| objects.resolve(action))
+                .and_then(|action| entry(action, b"/URI"))
+                .map(|uri| String::from_utf8_lossy(&uri).trim().to_string())
+                .filter(|uri| !uri.is_empty());
+            let target = match uri {
+                Some(uri) => LinkTarget::Uri(uri),
+                None => match destination(&objects, annotation)
+                    .and_then(|page| page_numbers.get(&page))
+                {
+                    Some(&page) => LinkTarget::Page(page),
+                    None => continue,
+                },
+            };
+            links.push((i + 1, PageLink { area, target }));
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_count() {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+                    2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R] /Count 12 >> endobj\n\
+                    3 0 obj <</Type/Pages/Parent 2 0 R/Count 5/Kids[]>> endobj\n\
+                    5 0 obj << /Type /Outlines /Count 40 >> endobj";
+        assert_eq!(page_count(pdf), Some(12));
+        assert_eq!(page_count(b"%PDF-1.5\n<< /Type /ObjStm >>"), None);
+        // An incremental update that drops pages leaves the old tree behind.
+        let updated = [
+            &pdf[..],
+            b"\n6 0 obj << /Type /Pages /Kids [3 0 R] /Count 5 >> endobj\n\
+              1 0 obj << /Type /Catalog /Pages 6 0 R >> endobj\n\
+              trailer << /Size 7 /Root 1 0 R >>",
+        ]
+        .concat();
+        assert_eq!(page_count(&updated), Some(5));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let pdf = b"%PDF-1.4\n15 0 obj << /Title (Wrong) >> endobj\n\
+                    5 0 obj << /Title (Annual \\(draft\\) report\\041) /Author <FEFF00C5 0073>\n\
+                    /Keywords () /CreationDate (D:20210813090000+08'00') /ModDate (D:2021) >> endobj\n\
+                    trailer << /Root 1 0 R /Info 5 0 R >>";
+        let metadata = metadata(pdf);
+        assert_eq!(metadata.title.as_deref(), Some("Annual (draft) report!"));
+        assert_eq!(metadata.author.as_deref(), Some("Ås"));
+        assert_eq!(metadata.subject, None);
+        assert_eq!(metadata.keywords, None);
+        assert_eq!(metadata.created, Some(1628816400));
+        assert_eq!(metadata.modified, Some(1609459200));
+        assert_eq!(super::metadata(b"%PDF-1.4"), Metadata::default());
+    }
+
+    #[test]
+    fn test_outline() {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 2 0 R /Outlines 10 0 R >> endobj\n\
+                    2 0 obj << /Type /Pages /Kids [3 0 R 6 0 R] /Count 3 >> endobj\n\
+                    3 0 obj << /Type /Pages /Kids [4 0 R 5 0 R] /Count 2 >> endobj\n\
+                    4 0 obj << /Type /Page >> endobj\n5 0 obj << /Type /Page >> endobj\n\
+                    6 0 obj << /Type /Page >> endobj\n\
+                    10 0 obj << /Type /Outlines /First 11 0 R >> endobj\n\
+                    11 0 obj << /Title (Intro) /Dest [4 0 R /Fit] /Next 12 0 R >> endobj\n\
+                    12 0 obj << /Title <FEFF00C5> /First 13 0 R /Next 11 0 R\n\
+                    /A << /S /GoTo /D [6 0 R /XYZ 0 0 0] >> >> endobj\n\
+                    13 0 obj << /Title (Named) /Dest (chapter) /Next 14 0 R >> endobj\n\
+                    14 0 obj << /Title (Detail) /A 15 0 R >> endobj\n\
+                    15 0 obj << /S /GoTo /D 16 0 R >> endobj\n16 0 obj [5 0 R /Fit] endobj\n\
+                    trailer << /Root 1 0 R >>";
+        let item = |level, title: &str, page| OutlineItem {
+            level,
+            title: title.to_string(),
+            page,
+        };
+        assert_eq!(
+            outline(pdf),
+            [item(1, "Intro", 1), item(1, "Å", 3), item(2, "Detail", 2)]
+        );
+        assert_eq!(outline(b"%PDF-1.4\ntrailer << /Root 1 0 R >>"), []);
+    }
+
+    #[test]
+    fn test_links() {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+                    2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R] /MediaBox [0 0 200 100] >> endobj\n\
+                    3 0 obj << /Type /Page /Parent 2 0 R /Annots [5 0 R 6 0 R 7 0 R] >> endobj\n\
+                    4 0 obj << /Type /Page /Parent 2 0 R /Rotate 90 /Annots 9 0 R >> endobj\n\
+                    5 0 obj << /Subtype /Link /Rect [20 90 60 70]\n\
+                    /A << /S /URI /URI (https://example.com/?a=1&b=2) >> >> endobj\n\
+                    6 0 obj << /Subtype /Link /Rect [0 0 10 10] /Dest [4 0 R /Fit] >> endobj\n\
+                    7 0 obj << /Subtype /Text /Rect [0 0 10 10] >> endobj\n\
+                    8 0 obj << /Subtype /Link /Rect [0 0 100 50] /A << /S /GoTo /D [3 0 R] >> >> endobj\n\
+                    9 0 obj [8 0 R] endobj\n\
+                    trailer << /Root 1 0 R >>";
+        let links = links(pdf);
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].0, 1);
+        assert_eq!(
+            links[0].1.target,
+            LinkTarget::Uri(String::from("https://example.com/?a=1&b=2"))
+        );
+        assert_eq!(links[0].1.area, [0.1, 0.1, 0.3, 0.3]);
+        assert_eq!(links[1].1.target, LinkTarget::Page(2));
+        assert_eq!(links[1].1.area, [0.0, 0.9, 0.05, 1.0]);
+        // The lower left quarter, turned a quarter clockwise.
+        assert_eq!(links[2].0, 2);
+        assert_eq!(links[2].1.target, LinkTarget::Page(1));
+        assert_eq!(links[2].1.area, [0.0, 0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_page_sizes() {
+        let pdf = b"%PDF-1.4\n1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+                    2 0 obj << /Type /Pages /Kids [3 0 R 4 0 R 5 0 R] /MediaBox [0 0 612 792] >> endobj\n\
+                    3 0 obj << /Type /Page /Parent 2 0 R >> endobj\n\
+                    4 0 obj << /Type /Page /Parent 2 0 R /Rotate 270 >> endobj\n\
+                    5 0 obj << /Type /Page /Parent 2 0 R /CropBox [10 10 310 110] >> endobj\n\
+                    trailer << /Root 1 0 R >>";
+        assert_eq!(
+            page_sizes(pdf),
+            [(612.0, 792.0), (792.0, 612.0), (300.0, 100.0)]
+        );
+        assert_eq!(page_sizes(b"%PDF-1.4"), []);
+    }
+
+    #[test]
+    fn test_is_image_only() {
+        let scan = b"3 0 obj << /Resources << /XObject << /Im0 6 0 R >> >> >> endobj\n\
+                     6 0 obj << /Type /XObject /Subtype /Image >> stream";
+        assert_eq!(is_image_only(scan), Some(true));
+        assert_eq!(is_image_only(b"<< /Font << /F1 7 0 R >> >>"), Some(false));
+        assert_eq!(is_image_only(b"<< /Type /ObjStm /N 4 >>"), None);
+    }
+
+    #[test]
+    fn test_is_encrypted() {
+        assert!(is_encrypted(b"trailer << /Root 1 0 R /Encrypt 9 0 R >>"));
+        assert!(!is_encrypted(b"trailer << /Root 1 0 R >>"));
+        // A page that talks about encryption is not.
+        let plain = b"%PDF-1.4\n4 0 obj << /Length 20 >> stream\n(/Encrypt) Tj\nendstream endobj\n\
+                      xref\n0 1\n0000000000 65535 f \ntrailer << /Root 1 0 R >>\nstartxref\n72\n%%EOF";
+        assert!(!is_encrypted(plain));
+        let stream = b"%PDF-1.5\n7 0 obj << /Type /XRef /Root 1 0 R /Encrypt 9 0 R >> stream\n\
+                       /Encrypt\nendstream endobj\nstartxref\n9\n%%EOF";
+        assert!(is_encrypted(stream));
+    }
+}
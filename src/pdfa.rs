@@ -0,0 +1,135 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! A quick look at whether a PDF is PDF/A: the conformance level it claims
+//! in its XMP metadata, and a few things PDF/A forbids. This is a heuristic
+//! over the raw bytes, not a validator.
+
+use crate::pdf::find;
+use crate::{Error, Result};
+use std::fmt;
+use std::path::Path;
+
+/// What [`check_pdfa`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PdfaReport {
+    /// The claimed part and conformance level, like `2B`.
+    pub claimed: Option<String>,
+    /// Features PDF/A does not allow, in words meant for the user.
+    pub problems: Vec<String>,
+}
+
+impl PdfaReport {
+    pub fn is_compliant(&self) -> bool {
+        self.claimed.is_some() && self.problems.is_empty()
+    }
+
+    /// The report of PDFs converted into one document: PDF/A of a level
+    /// only if each of them claims that level, with the problems of all.
+    pub(crate) fn and(mut self, other: PdfaReport) -> PdfaReport {
+        if self.claimed != other.claimed {
+            self.claimed = None;
+        }
+        for problem in other.problems {
+            if !self.problems.contains(&problem) {
+                self.problems.push(problem);
+            }
+        }
+        self
+    }
+}
+
+impl fmt::Display for PdfaReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.claimed, self.problems.is_empty()) {
+            (Some(claimed), true) => write!(f, "PDF/A-{}", claimed),
+            (Some(claimed), false) => write!(
+                f,
+                "claims PDF/A-{}, but {}",
+                claimed,
+                self.problems.join("; ")
+            ),
+            (None, _) => write!(f, "not PDF/A"),
+        }
+    }
+}
+
+/// The value of an XMP property written either as an attribute or as an
+/// element.
+fn xmp_value(data: &[u8], name: &str) -> Option<String> {
+    let start = find(data, name.as_bytes())? + name.len();
+    let rest = &data[start..];
+    let (open, close) = match rest.first()? {
+        b'=' => (b'"', b'"'),
+        b'>' => (b'>', b'<'),
+        _ => return None,
+    };
+    let rest = &rest[rest.iter().position(|&b| b == open || b == b'\'')? + 1..];
+    let end = rest.iter().position(|&b| b == close || b == b'\'')?;
+    let value = std::str::from_utf8(&rest[..end]).ok()?.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+pub(crate) fn inspect(data: &[u8]) -> PdfaReport {
+    let claimed = xmp_value(data, "pdfaid:part").map(|part| {
+        part + &xmp_value(data, "pdfaid:conformance").unwrap_or_default()
+    });
+    let mut problems = Vec::new();
+    if find(data, b"/Encrypt").is_some() {
+        problems.push(String::from("it is encrypted"));
+    }
+    if find(data, b"/JavaScript").is_some() || find(data, b"/JS ").is_some() {
+        problems.push(String::from("it contains JavaScript"));
+    }
+    if claimed.is_some() && find(data, b"/OutputIntents").is_none() {
+        problems.push(String::from("it has no output intent"));
+    }
+    PdfaReport { claimed, problems }
+}
+
+/// Reads `pdf` and reports whether it looks like PDF/A.
+pub fn check_pdfa(pdf: &Path) -> Result<PdfaReport> {
+    Ok(inspect(&std::fs::read(pdf).map_err(|e| Error::from(e).at(pdf))?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect() {
+        let report = inspect(
+            b"%PDF-1.7\n<< /OutputIntents [] >>\n<rdf:Description pdfaid:part=\"2\" pdfaid:conformance=\"B\"/>",
+        );
+        assert_eq!(report.claimed.as_deref(), Some("2B"));
+        assert!(report.is_compliant());
+        assert_eq!(report.to_string(), "PDF/A-2B");
+
+        let report = inspect(
+            b"%PDF-1.4\n<< /Encrypt 5 0 R >>\n<pdfaid:part>1</pdfaid:part><pdfaid:conformance>A</pdfaid:conformance>",
+        );
+        assert_eq!(report.claimed.as_deref(), Some("1A"));
+        assert_eq!(
+            report.to_string(),
+            "claims PDF/A-1A, but it is encrypted; it has no output intent"
+        );
+
+        let report = inspect(b"%PDF-1.4\n<< /S /JavaScript >>");
+        assert!(!report.is_compliant());
+        assert_eq!(report.to_string(), "not PDF/A");
+    }
+}
@@ -0,0 +1,121 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Rendering pages in-process with the PDFium library, for systems without
+//! Inkscape, poppler or MuPDF.
+
+use crate::renderer::{PdfRenderer, RenderContext, SvgPage};
+use crate::{Error, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use pdfium_render::prelude::{PdfDocument, PdfRenderConfig, Pdfium as Library};
+use std::sync::OnceLock;
+
+/// PDFium, loaded from beside the executable or else from the system's
+/// library path. Pages come out as raster images at
+/// [`DocxOptions::raster_dpi`](crate::DocxOptions::raster_dpi) wrapped in an
+/// SVG, so text is not kept as vectors. It runs in this process, outside
+/// the [`Sandbox`](crate::Sandbox) and the resource limits.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pdfium;
+
+/// The library, bound once: PDFium cannot be initialized twice.
+fn library() -> Result<&'static Library> {
+    static LIBRARY: OnceLock<Option<Library>> = OnceLock::new();
+    LIBRARY
+        .get_or_init(|| {
+            let beside_exe = std::env::current_exe()
+                .ok()
+                .and_then(|exe| Some(Library::pdfium_platform_library_name_at_path(exe.parent()?)));
+            beside_exe
+                .and_then(|path| Library::bind_to_library(path).ok())
+                .or_else(|| Library::bind_to_system_library().ok())
+                .map(Library::new)
+        })
+        .as_ref()
+        .ok_or(Error::RendererNotFound)
+}
+
+/// The PDF of `context`, or `None` if PDFium cannot open it, as when the
+/// password is missing or wrong.
+fn open<'a>(library: &'a Library, context: &RenderContext) -> Option<PdfDocument<'a>> {
+    library
+        .load_pdf_from_file(context.pdf(), context.password())
+        .ok()
+}
+
+impl PdfRenderer for Pdfium {
+    fn page_count(&self, context: &RenderContext) -> Result<Option<usize>> {
+        let library = library()?;
+        Ok(open(library, context).map(|pdf| pdf.pages().len() as usize))
+    }
+
+    fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+        let library = library()?;
+        let Some(pdf) = open(library, context) else {
+            return Ok(None);
+        };
+        let Ok(page) = pdf.pages().get(page as i32 - 1) else {
+            return Ok(None);
+        };
+        let (width, height) = (page.width().value as f64, page.height().value as f64);
+        let zoom = context.options.raster_dpi / 72.0;
+        let config = PdfRenderConfig::new().set_target_size(
+            (width * zoom).round().max(1.0) as i32,
+            (height * zoom).round().max(1.0) as i32,
+        );
+        let bitmap = page
+            .render_with_config(&config)
+            .map_err(|e| Error::invalid_data(e.to_string()))?;
+        let mut pixmap = tiny_skia::Pixmap::new(bitmap.width() as u32, bitmap.height() as u32)
+            .ok_or_else(Error::image)?;
+        // Rendered over white, so the pixels are opaque and need no
+        // premultiplying.
+        pixmap.data_mut().copy_from_slice(&bitmap.as_rgba_bytes());
+        let png = BASE64.encode(pixmap.encode_png()?);
+        Ok(Some(SvgPage {
+            data: wrap_png(&png, width * 96.0 / 72.0, height * 96.0 / 72.0).into_bytes(),
+        }))
+    }
+}
+
+/// An SVG of `width` × `height` px showing the base64 PNG `png`.
+fn wrap_png(png: &str, width: f64, height: f64) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <image width=\"{w}\" height=\"{h}\" xlink:href=\"data:image/png;base64,{png}\"/></svg>",
+        w = width,
+        h = height,
+        png = png,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+
+    #[test]
+    fn test_wrap_png() {
+        let pixmap = tiny_skia::Pixmap::new(4, 2).unwrap();
+        let png = BASE64.encode(pixmap.encode_png().unwrap());
+        let svg = wrap_png(&png, 8.0, 4.0);
+        let tree = crate::image::parse_svg(svg.as_bytes(), &DocxOptions::default()).unwrap();
+        let size = tree.svg_node().size;
+        assert_eq!((size.width(), size.height()), (8.0, 4.0));
+    }
+}
@@ -0,0 +1,281 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The size and resolution of raster images added as pages, read from their
+//! headers without decoding them.
+
+use crate::{Error, Result};
+
+const INCH_PER_METER: f64 = 0.0254;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PictureFormat {
+    Png,
+    Jpeg,
+    Tiff,
+}
+
+impl PictureFormat {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            PictureFormat::Png => "png",
+            PictureFormat::Jpeg => "jpeg",
+            PictureFormat::Tiff => "tiff",
+        }
+    }
+
+    pub(crate) fn content_type(self) -> &'static str {
+        match self {
+            PictureFormat::Png => "image/png",
+            PictureFormat::Jpeg => "image/jpeg",
+            PictureFormat::Tiff => "image/tiff",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Picture {
+    pub(crate) format: PictureFormat,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// Horizontal and vertical dots per inch, if the image says.
+    pub(crate) dpi: Option<(f64, f64)>,
+}
+
+impl Picture {
+    pub(crate) fn read(data: &[u8]) -> Result<Picture> {
+        let picture = if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            png(data)
+        } else if data.starts_with(b"\xff\xd8") {
+            jpeg(data)
+        } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+            tiff(data)
+        } else {
+            return Err(Error::invalid_data("not a PNG, JPEG or TIFF image"));
+        };
+        picture
+            .filter(|picture| picture.width > 0 && picture.height > 0)
+            .ok_or_else(|| Error::invalid_data("truncated or invalid image header"))
+    }
+
+    /// The size in px at 96 per inch, as printed at the image's resolution,
+    /// or one pixel per px if it has none.
+    pub(crate) fn size(&self) -> Option<usvg::Size> {
+        let (x_dpi, y_dpi) = self
+            .dpi
+            .filter(|&(x, y)| x.is_normal() && y.is_normal() && x > 0.0 && y > 0.0)
+            .unwrap_or((96.0, 96.0));
+        usvg::Size::new(
+            self.width as f64 * 96.0 / x_dpi,
+            self.height as f64 * 96.0 / y_dpi,
+        )
+    }
+}
+
+fn u16_be(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_be(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The size from `IHDR`, the resolution from `pHYs` if it is in meters.
+fn png(data: &[u8]) -> Option<Picture> {
+    let mut picture = Picture {
+        format: PictureFormat::Png,
+        width: u32_be(data, 16)?,
+        height: u32_be(data, 20)?,
+        dpi: None,
+    };
+    let mut at = 8;
+    while let (Some(len), Some(kind)) = (u32_be(data, at), data.get(at + 4..at + 8)) {
+        let chunk = data.get(at + 8..at + 8 + len as usize)?;
+        match kind {
+            b"pHYs" if chunk.get(8) == Some(&1) => {
+                let per_meter = |at| u32_be(chunk, at).map(|n| n as f64 * INCH_PER_METER);
+                picture.dpi = Some((per_meter(0)?, per_meter(4)?));
+            }
+            b"IDAT" | b"IEND" => break,
+            _ => {}
+        }
+        at += 12 + len as usize;
+    }
+    Some(picture)
+}
+
+/// The size from the first `SOFn` segment, the resolution from `JFIF`.
+fn jpeg(data: &[u8]) -> Option<Picture> {
+    let mut dpi = None;
+    let mut at = 2;
+    loop {
+        if *data.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *data.get(at + 1)?;
+        let len = u16_be(data, at + 2)? as usize;
+        let segment = data.get(at + 4..at + 2 + len)?;
+        match marker {
+            0xe0 if segment.starts_with(b"JFIF\0") => {
+                let density = |at| u16_be(segment, at).map(f64::from);
+                let (x, y) = (density(8)?, density(10)?);
+                dpi = match segment.get(7) {
+                    Some(1) => Some((x, y)),
+                    Some(2) => Some((x * 2.54, y * 2.54)),
+                    _ => None,
+                };
+            }
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                return Some(Picture {
+                    format: PictureFormat::Jpeg,
+                    width: u16_be(segment, 3)?.into(),
+                    height: u16_be(segment, 1)?.into(),
+                    dpi,
+                });
+            }
+            _ => {}
+        }
+        at += 2 + len;
+    }
+}
+
+/// The size and resolution from the tags of the first image file directory.
+fn tiff(data: &[u8]) -> Option<Picture> {
+    let big_endian = data.starts_with(b"MM");
+    let u16_at = |at: usize| {
+        let bytes = data.get(at..at + 2)?.try_into().ok()?;
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let u32_at = |at: usize| {
+        let bytes = data.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+    let ifd = u32_at(4)? as usize;
+    let (mut width, mut height, mut x_res, mut y_res) = (None, None, None, None);
+    let mut unit = 2;
+    for i in 0..u16_at(ifd)? as usize {
+        let entry = ifd + 2 + 12 * i;
+        // SHORT or LONG values fit in the entry; RATIONALs are elsewhere.
+        let value = match u16_at(entry + 2)? {
+            3 => u16_at(entry + 8).map(u32::from),
+            _ => u32_at(entry + 8),
+        };
+        let rational = || {
+            let at = u32_at(entry + 8)? as usize;
+            Some(u32_at(at)? as f64 / u32_at(at + 4)? as f64)
+        };
+        match u16_at(entry)? {
+            256 => width = value,
+            257 => height = value,
+            282 => x_res = rational(),
+            283 => y_res = rational(),
+            296 => unit = value?,
+            _ => {}
+        }
+    }
+    let dpi = x_res.zip(y_res).and_then(|(x, y)| match unit {
+        2 => Some((x, y)),
+        3 => Some((x * 2.54, y * 2.54)),
+        _ => None,
+    });
+    Some(Picture {
+        format: PictureFormat::Tiff,
+        width: width?,
+        height: height?,
+        dpi,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_file(phys: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut encoder = png::Encoder::new(&mut data, 30, 20);
+        encoder.set_color(png::ColorType::Grayscale);
+        let mut writer = encoder.write_header().unwrap();
+        if phys {
+            // 7874 per meter is 200 per inch.
+            let mut chunk = [7874u32.to_be_bytes(), 7874u32.to_be_bytes()].concat();
+            chunk.push(1);
+            writer.write_chunk(png::chunk::pHYs, &chunk).unwrap();
+        }
+        writer.write_image_data(&[0; 600]).unwrap();
+        drop(writer);
+        data
+    }
+
+    #[test]
+    fn test_png() {
+        let picture = Picture::read(&png_file(true)).unwrap();
+        assert_eq!((picture.width, picture.height), (30, 20));
+        let (x, y) = picture.dpi.unwrap();
+        assert!((x - 200.0).abs() < 0.01 && (y - 200.0).abs() < 0.01);
+        let size = picture.size().unwrap();
+        assert!((size.width() - 14.4).abs() < 0.01);
+        let picture = Picture::read(&png_file(false)).unwrap();
+        assert_eq!(picture.dpi, None);
+        let size = picture.size().unwrap();
+        assert_eq!((size.width(), size.height()), (30.0, 20.0));
+    }
+
+    #[test]
+    fn test_jpeg() {
+        let mut data = b"\xff\xd8\xff\xe0\x00\x10JFIF\0\x01\x01\x01\x01\x2c\x00\x96\0\0".to_vec();
+        data.extend(b"\xff\xdb\x00\x04\0\0");
+        data.extend(b"\xff\xc0\x00\x0b\x08\x00\x20\x00\x40\x01\x01\x11\x00");
+        let picture = Picture::read(&data).unwrap();
+        assert_eq!(picture.format, PictureFormat::Jpeg);
+        assert_eq!((picture.width, picture.height), (64, 32));
+        assert_eq!(picture.dpi, Some((300.0, 150.0)));
+        assert!(Picture::read(&data[..24]).is_err());
+    }
+
+    #[test]
+    fn test_tiff() {
+        let mut data = b"II*\0\x08\0\0\0\x05\0".to_vec();
+        let entry = |tag: u16, kind: u16, value: u32| {
+            let mut entry = Vec::new();
+            entry.extend(tag.to_le_bytes());
+            entry.extend(kind.to_le_bytes());
+            entry.extend(1u32.to_le_bytes());
+            entry.extend(value.to_le_bytes());
+            entry
+        };
+        data.extend(entry(256, 3, 100));
+        data.extend(entry(257, 4, 50));
+        data.extend(entry(282, 5, 74));
+        data.extend(entry(283, 5, 74));
+        data.extend(entry(296, 3, 3));
+        data.extend(0u32.to_le_bytes());
+        data.extend(100u32.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        let picture = Picture::read(&data).unwrap();
+        assert_eq!((picture.width, picture.height), (100, 50));
+        // 100 per centimeter.
+        assert_eq!(picture.dpi, Some((254.0, 254.0)));
+        assert!(Picture::read(b"GIF89a").is_err());
+    }
+}
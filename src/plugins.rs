@@ -0,0 +1,200 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Renderers and rasterizers loaded from shared libraries in a plugin
+//! directory. A plugin exports `dyw_plugin_abi_version` returning
+//! [`ABI_VERSION`] and any of:
+//!
+//! ```c
+//! /* 0 on success, 1 past the last page, anything else on failure. The SVG
+//!    may be passed to `write` in several pieces. */
+//! int dyw_render_page(const char *pdf, size_t page,
+//!                     void (*write)(void *ctx, const uint8_t *data, size_t len),
+//!                     void *ctx);
+//! /* Fills `rgba`, width * height premultiplied RGBA pixels. 0 on success. */
+//! int dyw_rasterize(const uint8_t *svg, size_t len, uint32_t width,
+//!                   uint32_t height, uint8_t *rgba);
+//! ```
+//!
+//! The first library in file name order that exports a function is used for
+//! it. Plugins run in-process, outside the sandbox and resource limits.
+//!
+//! Both functions must be thread-safe: with [`DocxOptions::jobs`] above 1,
+//! pages are rendered and rasterized from several threads at once, and the
+//! functions are called concurrently, for different pages of the same PDF.
+
+use crate::image::blank_pixmap;
+use crate::{DocxOptions, Error, PdfRenderer, RenderContext, Result, SvgPage};
+use libloading::Library;
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::path::Path;
+
+/// The plugin interface version this crate implements.
+pub const ABI_VERSION: u32 = 1;
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type WriteFn = unsafe extern "C" fn(*mut c_void, *const u8, usize);
+type RenderPageFn = unsafe extern "C" fn(*const c_char, usize, WriteFn, *mut c_void) -> c_int;
+type RasterizeFn = unsafe extern "C" fn(*const u8, usize, u32, u32, *mut u8) -> c_int;
+
+#[derive(Default)]
+pub(crate) struct Plugins {
+    render_page: Option<RenderPageFn>,
+    rasterize: Option<RasterizeFn>,
+    /// Keeps the functions above loaded.
+    _libraries: Vec<Library>,
+}
+
+fn is_library(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("so" | "dylib" | "dll")
+    )
+}
+
+unsafe extern "C" fn append(ctx: *mut c_void, data: *const u8, len: usize) {
+    if len > 0 {
+        (*(ctx as *mut Vec<u8>)).extend_from_slice(std::slice::from_raw_parts(data, len));
+    }
+}
+
+impl Plugins {
+    /// Loads every shared library in `dir`. Libraries exporting neither
+    /// function are kept loaded but unused.
+    pub(crate) fn load(dir: &Path) -> Result<Plugins> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.retain(|path| is_library(path));
+        paths.sort();
+        let mut plugins = Plugins::default();
+        for path in paths {
+            // Loading runs the library's initializers; the plugin directory
+            // is trusted like the executable itself.
+            unsafe {
+                let library = Library::new(&path).map_err(|_| Error::PluginInvalid)?;
+                let version = library
+                    .get::<AbiVersionFn>(b"dyw_plugin_abi_version")
+                    .map_err(|_| Error::PluginInvalid)?;
+                if version() != ABI_VERSION {
+                    return Err(Error::PluginInvalid);
+                }
+                if plugins.render_page.is_none() {
+                    plugins.render_page = library
+                        .get::<RenderPageFn>(b"dyw_render_page")
+                        .ok()
+                        .map(|f| *f);
+                }
+                if plugins.rasterize.is_none() {
+                    plugins.rasterize = library
+                        .get::<RasterizeFn>(b"dyw_rasterize")
+                        .ok()
+                        .map(|f| *f);
+                }
+                plugins._libraries.push(library);
+            }
+        }
+        Ok(plugins)
+    }
+
+    pub(crate) fn renders_pages(&self) -> bool {
+        self.render_page.is_some()
+    }
+
+    /// The PNG fallback of a page, or `None` without a rasterizer plugin.
+    pub(crate) fn rasterize(
+        &self,
+        svg_data: &[u8],
+        rtree: &usvg::Tree,
+        options: &DocxOptions,
+    ) -> Option<Result<tiny_skia::Pixmap>> {
+        let rasterize = self.rasterize?;
+        let mut pixmap = match blank_pixmap(rtree, options) {
+            Ok(pixmap) => pixmap,
+            Err(e) => return Some(Err(e)),
+        };
+        let status = unsafe {
+            rasterize(
+                svg_data.as_ptr(),
+                svg_data.len(),
+                pixmap.width(),
+                pixmap.height(),
+                pixmap.data_mut().as_mut_ptr(),
+            )
+        };
+        if status != 0 {
+            return Some(Err(Error::PluginFailed));
+        }
+        options.scan_cleanup.apply(&mut pixmap);
+        Some(Ok(pixmap))
+    }
+}
+
+impl PdfRenderer for Plugins {
+    fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+        let render_page = self.render_page.ok_or(Error::PluginFailed)?;
+        let pdf = match context.pdf().to_str().map(CString::new) {
+            Some(Ok(pdf)) => pdf,
+            _ => {
+                return Err(Error::invalid_data("the PDF path is not UTF-8").at(context.pdf()))
+            }
+        };
+        let mut svg = Vec::new();
+        let status = unsafe {
+            render_page(
+                pdf.as_ptr(),
+                page,
+                append,
+                &mut svg as *mut Vec<u8> as *mut c_void,
+            )
+        };
+        match status {
+            0 => Ok(Some(SvgPage { data: svg })),
+            1 => Ok(None),
+            _ => Err(Error::PluginFailed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append() {
+        let mut buf = Vec::new();
+        let ctx = &mut buf as *mut Vec<u8> as *mut c_void;
+        unsafe {
+            append(ctx, b"<svg".as_ptr(), 4);
+            append(ctx, std::ptr::null(), 0);
+            append(ctx, b"/>".as_ptr(), 2);
+        }
+        assert_eq!(buf, b"<svg/>");
+    }
+
+    #[test]
+    fn test_load() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("README"), "not a plugin").unwrap();
+        let plugins = Plugins::load(dir.path()).unwrap();
+        assert!(!plugins.renders_pages());
+        std::fs::write(dir.path().join("broken.so"), "not a library").unwrap();
+        assert!(matches!(
+            Plugins::load(dir.path()),
+            Err(Error::PluginInvalid)
+        ));
+    }
+}
@@ -0,0 +1,414 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! PowerPoint output: every page a picture filling a slide of its own.
+
+use crate::ooxml::{
+    app_properties, core_properties, escape, media_part, part_target, px_to_emu, write_blip,
+    APP_PROPERTIES, CONTENT_TYPES, CORE_PROPERTIES, PACKAGE_RELS,
+};
+use crate::package::Package;
+#[cfg(feature = "native")]
+use crate::package::{long_path, write_checksum};
+use crate::{encryption, Docx, Page, Result};
+#[cfg(feature = "native")]
+use std::fs::File;
+use std::io::{self, Seek, Write};
+#[cfg(feature = "native")]
+use std::path::Path;
+
+const PRESENTATION: &str = "ppt/presentation.xml";
+const PRESENTATION_RELS: &str = "ppt/_rels/presentation.xml.rels";
+const SLIDE_MASTER: &str = "ppt/slideMasters/slideMaster1.xml";
+const SLIDE_MASTER_RELS: &str = "ppt/slideMasters/_rels/slideMaster1.xml.rels";
+const SLIDE_LAYOUT: &str = "ppt/slideLayouts/slideLayout1.xml";
+const SLIDE_LAYOUT_RELS: &str = "ppt/slideLayouts/_rels/slideLayout1.xml.rels";
+const THEME: &str = "ppt/theme/theme1.xml";
+
+/// The smallest and largest sides of a slide PowerPoint accepts, 1 and 56
+/// inches, in px.
+const MIN_SLIDE_PX: f64 = 96.0;
+const MAX_SLIDE_PX: f64 = 5376.0;
+
+/// The pages of a [`Docx`] as a presentation, one slide per page. The
+/// slides are as large as the first page; other pages are scaled to fit
+/// theirs and centered. Only the images and alt text of the pages are
+/// kept: captions, the text layer, headings and links are for DOCX.
+pub struct Pptx {
+    docx: Docx,
+}
+
+impl From<Docx> for Pptx {
+    fn from(docx: Docx) -> Pptx {
+        Pptx { docx }
+    }
+}
+
+impl Pptx {
+    #[cfg(feature = "native")]
+    pub fn generate_pptx(self, p: &Path) -> Result<()> {
+        let p = &long_path(p);
+        let checksum_sidecar = self.docx.options.checksum_sidecar;
+        let file = File::create(p).map_err(|e| crate::Error::from(e).at(p))?;
+        self.generate_pptx_to_writer(file).map_err(|e| e.at(p))?;
+        if checksum_sidecar {
+            write_checksum(p)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the zipped PPTX to `writer`, encrypted with
+    /// [`DocxOptions::output_password`](crate::DocxOptions::output_password)
+    /// if set.
+    pub fn generate_pptx_to_writer<W: Write + Seek>(self, mut writer: W) -> Result<()> {
+        let _span = tracing::info_span!("generate_pptx", pages = self.docx.pages.len()).entered();
+        let package = self.package()?;
+        let options = &self.docx.options;
+        let scrub_metadata = options.scrub_metadata;
+        let deflate_level = options.optimization.deflate_level;
+        match &options.output_password {
+            Some(password) => {
+                let mut buf = io::Cursor::new(Vec::new());
+                package.write_zip(&mut buf, scrub_metadata, deflate_level)?;
+                writer.write_all(&encryption::encrypt(&buf.into_inner(), password)?)?;
+                Ok(())
+            }
+            None => package.write_zip(writer, scrub_metadata, deflate_level),
+        }
+    }
+
+    /// Builds the whole package and returns the zipped PPTX.
+    pub fn to_bytes(self) -> Result<Vec<u8>> {
+        let mut buf = io::Cursor::new(Vec::new());
+        self.generate_pptx_to_writer(&mut buf)?;
+        Ok(buf.into_inner())
+    }
+
+    /// The parts of the presentation, with the images of the pages copied
+    /// over from the DOCX.
+    fn package(&self) -> Result<Package> {
+        let docx = &self.docx;
+        let pages = &docx.pages;
+        let mut package = Package::empty(&docx.options)?;
+        let slide = slide_size(pages.first().map_or(docx.size, |page| page.size));
+        package.put(CONTENT_TYPES, content_types(pages.len()).into_bytes())?;
+        package.put(PACKAGE_RELS, PACKAGE_RELATIONSHIPS.as_bytes().to_vec())?;
+        package.put(PRESENTATION, presentation(pages.len(), slide).into_bytes())?;
+        let rels = presentation_relationships(pages.len());
+        package.put(PRESENTATION_RELS, rels.into_bytes())?;
+        package.put(SLIDE_MASTER, SLIDE_MASTER_XML.as_bytes().to_vec())?;
+        package.put(
+            SLIDE_MASTER_RELS,
+            SLIDE_MASTER_RELATIONSHIPS.as_bytes().to_vec(),
+        )?;
+        package.put(SLIDE_LAYOUT, SLIDE_LAYOUT_XML.as_bytes().to_vec())?;
+        package.put(
+            SLIDE_LAYOUT_RELS,
+            SLIDE_LAYOUT_RELATIONSHIPS.as_bytes().to_vec(),
+        )?;
+        package.put(THEME, THEME_XML.as_bytes().to_vec())?;
+        for (i, page) in pages.iter().enumerate() {
+            let position = i + 1;
+            for name in page.svg.iter().chain(&page.png) {
+                let data = docx.package.get(&media_part(name))?;
+                package.put(&format!("ppt/media/{}", name), data)?;
+            }
+            let xml = write_slide(page, position, slide);
+            package.put(
+                &format!("ppt/slides/slide{}.xml", position),
+                xml.into_bytes(),
+            )?;
+            let rels = slide_relationships(page);
+            let name = format!("ppt/slides/_rels/slide{}.xml.rels", position);
+            package.put(&name, rels.into_bytes())?;
+        }
+        package.put(
+            CORE_PROPERTIES,
+            core_properties(&docx.metadata()).into_bytes(),
+        )?;
+        let app = app_properties("Slides", pages.len(), !docx.options.scrub_metadata);
+        package.put(APP_PROPERTIES, app.into_bytes())?;
+        Ok(package)
+    }
+}
+
+/// `first`, scaled as little as needed to be a slide size PowerPoint
+/// accepts. Pages longer than 56 times their width get a wider slide.
+fn slide_size(first: usvg::Size) -> usvg::Size {
+    let (short, long) = (
+        first.width().min(first.height()),
+        first.width().max(first.height()),
+    );
+    let scale = (MIN_SLIDE_PX / short).max(1.0).min(MAX_SLIDE_PX / long);
+    let side = |px: f64| (px * scale).clamp(MIN_SLIDE_PX, MAX_SLIDE_PX);
+    usvg::Size::new(side(first.width()), side(first.height())).unwrap_or(first)
+}
+
+/// Offset and size of `page` scaled to fit `slide` and centered on it, in
+/// EMU.
+fn place(page: usvg::Size, slide: usvg::Size) -> (i32, i32, i32, i32) {
+    let scale = (slide.width() / page.width()).min(slide.height() / page.height());
+    let (width, height) = (page.width() * scale, page.height() * scale);
+    (
+        px_to_emu((slide.width() - width) / 2.0),
+        px_to_emu((slide.height() - height) / 2.0),
+        px_to_emu(width),
+        px_to_emu(height),
+    )
+}
+
+fn content_types(slides: usize) -> String {
+    let slides: String = (1..=slides)
+        .map(|position| {
+            let part_name = format!("/ppt/slides/slide{}.xml", position);
+            format_xml::xml! {
+                <Override PartName={part_name} ContentType="application/vnd.openxmlformats-officedocument.presentationml.slide+xml"/>
+            }
+            .to_string()
+        })
+        .collect();
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+            <Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+            <Default Extension="xml" ContentType="application/xml"/>
+            <Default Extension="png" ContentType="image/png"/>
+            <Default Extension="svg" ContentType="image/svg+xml"/>
+            <Default Extension="jpeg" ContentType="image/jpeg"/>
+            <Default Extension="tiff" ContentType="image/tiff"/>
+            <Override PartName="/ppt/presentation.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.presentation.main+xml"/>
+            <Override PartName="/ppt/slideMasters/slideMaster1.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.slideMaster+xml"/>
+            <Override PartName="/ppt/slideLayouts/slideLayout1.xml" ContentType="application/vnd.openxmlformats-officedocument.presentationml.slideLayout+xml"/>
+            <Override PartName="/ppt/theme/theme1.xml" ContentType="application/vnd.openxmlformats-officedocument.theme+xml"/>
+            <Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/>
+            <Override PartName="/docProps/app.xml" ContentType="application/vnd.openxmlformats-officedocument.extended-properties+xml"/>
+            {slides}
+        </Types>
+    }
+    .to_string()
+}
+
+const PACKAGE_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="ppt/presentation.xml"/><Relationship Id="rIdCoreProperties" Type="http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties" Target="docProps/core.xml"/><Relationship Id="rIdAppProperties" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties" Target="docProps/app.xml"/></Relationships>"#;
+
+/// The presentation of `slides` slides of size `slide`. Its relationships
+/// are the slide master `rId1`, the theme `rId2` and the slides from `rId3`.
+fn presentation(slides: usize, slide: usvg::Size) -> String {
+    let slide_ids: String = (0..slides)
+        .map(|i| {
+            // Slide ids start at 256.
+            let id = 256 + i;
+            let rid = format!("rId{}", 3 + i);
+            format_xml::xml! { <p:sldId id={id} r:id={rid}/> }.to_string()
+        })
+        .collect();
+    // A list of no slides is not allowed.
+    let slide_ids = if slides > 0 {
+        format!("<p:sldIdLst>{}</p:sldIdLst>", slide_ids)
+    } else {
+        String::new()
+    };
+    let (width, height) = (px_to_emu(slide.width()), px_to_emu(slide.height()));
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <p:presentation xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
+            <p:sldMasterIdLst>
+                <p:sldMasterId id="2147483648" r:id="rId1"/>
+            </p:sldMasterIdLst>
+            {slide_ids}
+            <p:sldSz cx={width} cy={height}/>
+            <p:notesSz cx="6858000" cy="9144000"/>
+        </p:presentation>
+    }
+    .to_string()
+}
+
+fn presentation_relationships(slides: usize) -> String {
+    let slides: String = (0..slides)
+        .map(|i| {
+            let rid = format!("rId{}", 3 + i);
+            let target = format!("slides/slide{}.xml", i + 1);
+            format_xml::xml! {
+                <Relationship Id={rid} Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slide" Target={target}/>
+            }
+            .to_string()
+        })
+        .collect();
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+            <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideMaster" Target="slideMasters/slideMaster1.xml"/>
+            <Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/theme" Target="theme/theme1.xml"/>
+            {slides}
+        </Relationships>
+    }
+    .to_string()
+}
+
+/// The relationships of the slide of `page`: its layout `rId1`, its PNG
+/// `rId2` and its SVG `rId3`, whichever it has.
+fn slide_relationships(page: &Page) -> String {
+    let image = |rid: &str, name: &str| {
+        let target = part_target(&format!("../media/{}", name));
+        format_xml::xml! {
+            <Relationship Id={rid} Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target={target}/>
+        }
+        .to_string()
+    };
+    let png = page
+        .png
+        .as_ref()
+        .map_or(String::new(), |png| image("rId2", png));
+    let svg = page
+        .svg
+        .as_ref()
+        .map_or(String::new(), |svg| image("rId3", svg));
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+            <Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideLayout" Target="../slideLayouts/slideLayout1.xml"/>
+            {png}
+            {svg}
+        </Relationships>
+    }
+    .to_string()
+}
+
+/// The slide of `page`, at `position` in the presentation.
+fn write_slide(page: &Page, position: usize, slide: usvg::Size) -> String {
+    let name = format!("Page {}", position);
+    let description = page.description();
+    let description = escape(&description);
+    let (x, y, width, height) = place(page.size, slide);
+    let blip = write_blip(
+        page.svg.as_ref().map(|_| "rId3"),
+        page.png.as_ref().map(|_| "rId2"),
+    );
+    format_xml::xml! {
+        <?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+        <p:sld xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main">
+            <p:cSld>
+                <p:spTree>
+                    <p:nvGrpSpPr>
+                        <p:cNvPr id="1" name=""/>
+                        <p:cNvGrpSpPr/>
+                        <p:nvPr/>
+                    </p:nvGrpSpPr>
+                    <p:grpSpPr/>
+                    <p:pic>
+                        <p:nvPicPr>
+                            <p:cNvPr id="2" name={name} descr={description}/>
+                            <p:cNvPicPr>
+                                <a:picLocks noChangeAspect="1"/>
+                            </p:cNvPicPr>
+                            <p:nvPr/>
+                        </p:nvPicPr>
+                        <p:blipFill>
+                            {blip}
+                            <a:stretch>
+                                <a:fillRect/>
+                            </a:stretch>
+                        </p:blipFill>
+                        <p:spPr>
+                            <a:xfrm>
+                                <a:off x={x} y={y}/>
+                                <a:ext cx={width} cy={height}/>
+                            </a:xfrm>
+                            <a:prstGeom prst="rect">
+                                <a:avLst/>
+                            </a:prstGeom>
+                        </p:spPr>
+                    </p:pic>
+                </p:spTree>
+            </p:cSld>
+            <p:clrMapOvr>
+                <a:masterClrMapping/>
+            </p:clrMapOvr>
+        </p:sld>
+    }
+    .to_string()
+}
+
+const SLIDE_MASTER_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<p:sldMaster xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main"><p:cSld><p:bg><p:bgRef idx="1001"><a:schemeClr val="bg1"/></p:bgRef></p:bg><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/></p:spTree></p:cSld><p:clrMap bg1="lt1" tx1="dk1" bg2="lt2" tx2="dk2" accent1="accent1" accent2="accent2" accent3="accent3" accent4="accent4" accent5="accent5" accent6="accent6" hlink="hlink" folHlink="folHlink"/><p:sldLayoutIdLst><p:sldLayoutId id="2147483649" r:id="rId1"/></p:sldLayoutIdLst></p:sldMaster>"#;
+
+const SLIDE_MASTER_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideLayout" Target="../slideLayouts/slideLayout1.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/theme" Target="../theme/theme1.xml"/></Relationships>"#;
+
+const SLIDE_LAYOUT_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<p:sldLayout xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main" type="blank" preserve="1"><p:cSld name="Blank"><p:spTree><p:nvGrpSpPr><p:cNvPr id="1" name=""/><p:cNvGrpSpPr/><p:nvPr/></p:nvGrpSpPr><p:grpSpPr/></p:spTree></p:cSld><p:clrMapOvr><a:masterClrMapping/></p:clrMapOvr></p:sldLayout>"#;
+
+const SLIDE_LAYOUT_RELATIONSHIPS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/slideMaster" Target="../slideMasters/slideMaster1.xml"/></Relationships>"#;
+
+/// The Office colors and fonts, with plain fills and lines: the slides
+/// draw nothing of their own.
+const THEME_XML: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<a:theme xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main" name="Office Theme"><a:themeElements><a:clrScheme name="Office"><a:dk1><a:sysClr val="windowText" lastClr="000000"/></a:dk1><a:lt1><a:sysClr val="window" lastClr="FFFFFF"/></a:lt1><a:dk2><a:srgbClr val="44546A"/></a:dk2><a:lt2><a:srgbClr val="E7E6E6"/></a:lt2><a:accent1><a:srgbClr val="4472C4"/></a:accent1><a:accent2><a:srgbClr val="ED7D31"/></a:accent2><a:accent3><a:srgbClr val="A5A5A5"/></a:accent3><a:accent4><a:srgbClr val="FFC000"/></a:accent4><a:accent5><a:srgbClr val="5B9BD5"/></a:accent5><a:accent6><a:srgbClr val="70AD47"/></a:accent6><a:hlink><a:srgbClr val="0563C1"/></a:hlink><a:folHlink><a:srgbClr val="954F72"/></a:folHlink></a:clrScheme><a:fontScheme name="Office"><a:majorFont><a:latin typeface="Calibri Light"/><a:ea typeface=""/><a:cs typeface=""/></a:majorFont><a:minorFont><a:latin typeface="Calibri"/><a:ea typeface=""/><a:cs typeface=""/></a:minorFont></a:fontScheme><a:fmtScheme name="Office"><a:fillStyleLst><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:fillStyleLst><a:lnStyleLst><a:ln w="6350"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln><a:ln w="12700"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln><a:ln w="19050"><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:ln></a:lnStyleLst><a:effectStyleLst><a:effectStyle><a:effectLst/></a:effectStyle><a:effectStyle><a:effectLst/></a:effectStyle><a:effectStyle><a:effectLst/></a:effectStyle></a:effectStyleLst><a:bgFillStyleLst><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill><a:solidFill><a:schemeClr val="phClr"/></a:solidFill></a:bgFillStyleLst></a:fmtScheme></a:themeElements></a:theme>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+    use std::io::Read;
+
+    #[test]
+    fn test_slide_size() {
+        let size = |width, height| usvg::Size::new(width, height).unwrap();
+        let fitted = slide_size(size(960.0, 540.0));
+        assert_eq!((fitted.width(), fitted.height()), (960.0, 540.0));
+        let fitted = slide_size(size(48.0, 24.0));
+        assert_eq!((fitted.width(), fitted.height()), (192.0, 96.0));
+        let fitted = slide_size(size(10752.0, 48.0));
+        assert_eq!((fitted.width(), fitted.height()), (5376.0, 96.0));
+        let (x, y, width, height) = place(size(100.0, 100.0), size(200.0, 100.0));
+        assert_eq!((x, y), (px_to_emu(50.0), 0));
+        assert_eq!((width, height), (px_to_emu(100.0), px_to_emu(100.0)));
+    }
+
+    #[test]
+    fn test_pptx() {
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        let svg = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg");
+        docx.add_svg_bytes(&std::fs::read(svg).unwrap()).unwrap();
+        docx.add_svg_bytes(&std::fs::read(svg).unwrap()).unwrap();
+        let bytes = Pptx::from(docx).to_bytes().unwrap();
+        let mut zip = zip::ZipArchive::new(io::Cursor::new(bytes)).unwrap();
+        let mut read = |name: &str| {
+            let mut text = String::new();
+            zip.by_name(name)
+                .unwrap()
+                .read_to_string(&mut text)
+                .unwrap();
+            text
+        };
+        let presentation = read(PRESENTATION);
+        assert!(presentation.contains(r#"<p:sldId id="257" r:id="rId4" />"#));
+        let slide = read("ppt/slides/slide2.xml");
+        assert!(slide.contains(r#"name="Page 2" descr="Page 2""#));
+        assert!(slide.contains(r#"<asvg:svgBlip xmlns:asvg="http://schemas.microsoft.com/office/drawing/2016/SVG/main" r:embed="rId3" />"#));
+        let rels = read("ppt/slides/_rels/slide2.xml.rels");
+        assert!(rels.contains(r#"Target="../media/2.svg""#));
+        assert!(read(CONTENT_TYPES).contains("/ppt/slides/slide2.xml"));
+        assert!(read(APP_PROPERTIES).contains("<Slides>2</Slides>"));
+        assert!(zip.by_name("ppt/media/2.png").is_ok());
+        assert!(zip.by_name("word/document.xml").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+
+//! How a conversion reports its progress: as [`ProgressEvent`]s given to a
+//! [`ProgressHandler`], so that a terminal, GUI or server can show them as
+//! it likes. The library itself prints nothing.
+
+/// A step of a conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// How many pages [`Docx::convert_pdf`] is going to convert, when the
+    /// renderer or the PDF tells. Comes before any page.
+    ///
+    /// [`Docx::convert_pdf`]: crate::Docx::convert_pdf
+    TotalPages(usize),
+    /// Page `n` of the PDF has been rendered. Pages are rendered by several
+    /// threads at once, so they may come out of order.
+    PageRendered(usize),
+    /// The page of [`Page::number`] `n` has been added to the document, so
+    /// `n` pages are done, skipped ones included.
+    ///
+    /// [`Page::number`]: crate::Page::number
+    PageEmbedded(usize),
+    /// The page that would have been [`Page::number`] `n` was skipped by the
+    /// page script or callback. It is done all the same.
+    ///
+    /// [`Page::number`]: crate::Page::number
+    PageSkipped(usize),
+}
+
+/// Receives the [`ProgressEvent`]s of a [`Docx`](crate::Docx), from whichever
+/// thread they happen on. Closures taking a [`ProgressEvent`] are handlers.
+pub trait ProgressHandler: Send + Sync {
+    fn event(&self, event: ProgressEvent);
+}
+
+impl<F: Fn(ProgressEvent) + Send + Sync> ProgressHandler for F {
+    fn event(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
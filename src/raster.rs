@@ -0,0 +1,232 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The SVG of a page parsed, hooked and rasterized into its PNG fallback:
+//! what is left of converting a page once it is rendered, and all of it that
+//! runs without the `native` feature.
+
+#[cfg(feature = "native")]
+use crate::cache::PageCache;
+#[cfg(feature = "native")]
+use crate::image::rotate;
+use crate::image::{parse_svg, render_png};
+#[cfg(feature = "plugins")]
+use crate::plugins::Plugins;
+#[cfg(feature = "native")]
+use crate::renderer::RenderContext;
+use crate::{Docx, DocxOptions, RasterHook, Result, SvgHook};
+use std::time::Duration;
+use tracing::debug;
+
+/// The SVG of a page parsed, hooked, turned upright and rasterized, ready to
+/// be added.
+pub(crate) struct RasterPage {
+    /// Re-serialized from the tree when that differs from the renderer's.
+    pub(crate) svg_data: Vec<u8>,
+    /// Left out with [`Optimization::svg_only`](crate::Optimization::svg_only)
+    /// when nothing else needs the page rasterized.
+    pub(crate) png_data: Option<Vec<u8>>,
+    pub(crate) size: usvg::Size,
+    pub(crate) raster: Duration,
+}
+
+/// What rendering and rasterizing pages needs of a [`Docx`], which worker
+/// threads can share.
+#[derive(Clone, Copy)]
+pub(crate) struct Converter<'a> {
+    pub(crate) options: &'a DocxOptions,
+    #[cfg(feature = "native")]
+    pub(crate) cache: Option<&'a PageCache>,
+    /// The page being converted, whose deadlines and cancellation also stop
+    /// `tesseract`.
+    #[cfg(feature = "native")]
+    pub(crate) context: Option<&'a RenderContext<'a>>,
+    #[cfg(feature = "plugins")]
+    pub(crate) plugins: &'a Plugins,
+}
+
+/// When a step started. `Instant` panics in browsers, where steps take no
+/// time as far as the [`PageMetrics`](crate::PageMetrics) tell.
+#[derive(Clone, Copy)]
+struct Stopwatch {
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    started: std::time::Instant,
+}
+
+impl Stopwatch {
+    fn start() -> Stopwatch {
+        Stopwatch {
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            started: std::time::Instant::now(),
+        }
+    }
+
+    fn elapsed(self) -> Duration {
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        return self.started.elapsed();
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        Duration::ZERO
+    }
+}
+
+impl Converter<'_> {
+    /// The PNG fallback of a page, from the rasterizer plugin if there is one.
+    fn rasterize(&self, svg_data: &[u8], tree: &usvg::Tree) -> Result<tiny_skia::Pixmap> {
+        #[cfg(feature = "plugins")]
+        if let Some(pixmap) = self.plugins.rasterize(svg_data, tree, self.options) {
+            return pixmap;
+        }
+        #[cfg(not(feature = "plugins"))]
+        let _ = svg_data;
+        render_png(tree, self.options)
+    }
+
+    /// Whether pages get a PNG as they are rasterized. Pages embedded as just
+    /// their SVG skip it, unless it is looked at to turn them upright or
+    /// embedded in their place after scan cleanup.
+    fn wants_png(&self) -> bool {
+        !self.options.optimization.svg_only
+            || self.options.auto_orient
+            || self.options.scan_cleanup.is_enabled()
+    }
+
+    /// The PNG of page `number` from `svg_data`, for a page whose PNG was
+    /// left out and is wanted after all.
+    pub(crate) fn png_fallback(
+        &self,
+        number: usize,
+        svg_data: &[u8],
+        raster_hook: Option<&mut RasterHook>,
+    ) -> Result<Vec<u8>> {
+        let tree = parse_svg(svg_data, self.options)?;
+        let mut pixmap = self.rasterize(svg_data, &tree)?;
+        if let Some(hook) = raster_hook {
+            hook(number, &mut pixmap)?;
+        }
+        debug!(page = number, "rasterized the PNG left out");
+        Ok(pixmap.encode_png()?)
+    }
+
+    /// Parses and rasterizes page `number`, running the hooks on the way.
+    pub(crate) fn raster_page(
+        &self,
+        number: usize,
+        svg_data: Vec<u8>,
+        svg_hook: Option<&mut SvgHook>,
+        raster_hook: Option<&mut RasterHook>,
+    ) -> Result<RasterPage> {
+        let started = Stopwatch::start();
+        let mut tree = parse_svg(&svg_data, self.options)?;
+        let hooked = svg_hook.is_some();
+        if let Some(hook) = svg_hook {
+            hook(number, &mut tree)?;
+        }
+        let mut svg_data = svg_data;
+        let mut pixmap = match self.wants_png() {
+            true => Some(self.rasterize(&svg_data, &tree)?),
+            false => None,
+        };
+        #[cfg_attr(not(feature = "native"), allow(unused_mut))]
+        let mut rotated = false;
+        // Orientation is detected by running `tesseract`.
+        #[cfg(feature = "native")]
+        if let Some(pixmap) = pixmap.as_mut().filter(|_| self.options.auto_orient) {
+            if let Some(degrees) = self.detect_orientation(pixmap.encode_png()?)? {
+                debug!(page = number, degrees, "turning upright");
+                rotate(&mut tree, degrees)?;
+                svg_data = tree.to_string(&usvg::XmlOptions::default()).into_bytes();
+                *pixmap = self.rasterize(&svg_data, &tree)?;
+                rotated = true;
+            }
+        }
+        if let (Some(hook), Some(pixmap)) = (raster_hook, pixmap.as_mut()) {
+            hook(number, pixmap)?;
+        }
+        let png_data = pixmap.map(|pixmap| pixmap.encode_png()).transpose()?;
+        let reserialize = hooked || self.options.scrub_metadata;
+        if reserialize && !rotated && !self.options.scan_cleanup.is_enabled() {
+            // Re-serializing keeps only what is rendered, dropping
+            // Inkscape's metadata, titles and docname attributes.
+            svg_data = tree.to_string(&usvg::XmlOptions::default()).into_bytes();
+        }
+        let size = tree.svg_node().size;
+        let raster = started.elapsed();
+        debug!(
+            page = number,
+            ?raster,
+            svg_bytes = svg_data.len(),
+            png_bytes = png_data.as_ref().map_or(0, Vec::len),
+            "rasterized"
+        );
+        Ok(RasterPage {
+            svg_data,
+            png_data,
+            size,
+            raster,
+        })
+    }
+}
+
+impl Docx {
+    pub(crate) fn converter(&self) -> Converter<'_> {
+        Converter {
+            options: &self.options,
+            #[cfg(feature = "native")]
+            cache: None,
+            #[cfg(feature = "native")]
+            context: None,
+            #[cfg(feature = "plugins")]
+            plugins: &self.plugins,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::picture::Picture;
+
+    #[test]
+    fn test_raster_page() {
+        let docx = Docx::new().unwrap();
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="30"><!-- note --><rect width="10" height="10"/></svg>"#;
+        let mut hook: SvgHook = Box::new(|number, _| {
+            assert_eq!(number, 3);
+            Ok(())
+        });
+        let page = docx
+            .converter()
+            .raster_page(3, svg.to_vec(), Some(&mut hook), None)
+            .unwrap();
+        assert_eq!((page.size.width(), page.size.height()), (40.0, 30.0));
+        let picture = Picture::read(page.png_data.as_ref().unwrap()).unwrap();
+        assert_eq!((picture.width, picture.height), (40, 30));
+        // Hooked pages are written back from the tree.
+        assert!(!String::from_utf8(page.svg_data).unwrap().contains("note"));
+    }
+
+    #[test]
+    fn test_svg_only() {
+        let docx = Docx::builder().no_png_fallback().build().unwrap();
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="30"/>"#;
+        let converter = docx.converter();
+        let page = converter.raster_page(1, svg.to_vec(), None, None).unwrap();
+        assert!(page.png_data.is_none());
+        let png_data = converter.png_fallback(1, &page.svg_data, None).unwrap();
+        let picture = Picture::read(&png_data).unwrap();
+        assert_eq!((picture.width, picture.height), (40, 30));
+    }
+}
@@ -0,0 +1,991 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Running Inkscape to turn PDF pages into SVG, isolated and limited.
+
+use crate::a11y::captions;
+use crate::cache::PageCache;
+use crate::metrics::write_csv;
+use crate::ocr::{layout, Ocr, Recognized};
+use crate::ooxml::{LinkTarget, Metadata, PageLink, SourceSeparator};
+use crate::pdf::{is_encrypted, links as pdf_links, metadata, outline, page_count};
+use crate::pdfa::check_pdfa;
+use crate::picture::Picture;
+use crate::progress::ProgressEvent;
+use crate::raster::{Converter, RasterPage};
+use crate::renderer::{poppler_password, PdfRenderer, RenderContext};
+use crate::selection::PageSelection;
+use crate::{Docx, Error, Result, TextLayer};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, ErrorKind, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, info_span, warn};
+
+/// How the external renderer processes are isolated from the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Sandbox {
+    #[default]
+    None,
+    Bubblewrap,
+    Firejail,
+}
+
+impl Sandbox {
+    pub(crate) fn command(
+        self,
+        program: &[OsString],
+        readable: &[&Path],
+        writable: &[&Path],
+    ) -> Command {
+        let mut command = match self {
+            Sandbox::None => return Sandbox::plain_command(program),
+            Sandbox::Bubblewrap => {
+                let mut bwrap = Command::new("bwrap");
+                bwrap
+                    .args(["--ro-bind", "/", "/"])
+                    .args(["--dev", "/dev", "--proc", "/proc"])
+                    .args(["--tmpfs", "/tmp", "--tmpfs", "/home", "--tmpfs", "/root"])
+                    .args(["--setenv", "HOME", "/tmp"])
+                    .args(["--unshare-all", "--die-with-parent", "--new-session"]);
+                for path in readable {
+                    bwrap.arg("--ro-bind").arg(path).arg(path);
+                }
+                for path in writable {
+                    bwrap.arg("--bind").arg(path).arg(path);
+                }
+                bwrap
+            }
+            Sandbox::Firejail => {
+                let mut firejail = Command::new("firejail");
+                firejail
+                    .args(["--quiet", "--noprofile", "--net=none", "--nosound", "--no3d"])
+                    .args(["--caps.drop=all", "--nonewprivs", "--noroot", "--seccomp"])
+                    .args(["--private-dev", "--read-only=/"]);
+                // Firejail shows no other home directories, and bwrap none at
+                // all. Whitelisting files in the user's own hides the rest of
+                // it; with none there, it is swapped for an empty one.
+                let home = std::env::var_os("HOME").map(PathBuf::from);
+                let in_home =
+                    |path: &Path| home.as_ref().is_some_and(|home| path.starts_with(home));
+                let mut hidden = true;
+                for path in readable.iter().filter(|path| in_home(path)) {
+                    firejail.arg(format!("--whitelist={}", path.display()));
+                    firejail.arg(format!("--read-only={}", path.display()));
+                    hidden = false;
+                }
+                for path in writable.iter().filter(|path| in_home(path)) {
+                    firejail.arg(format!("--whitelist={}", path.display()));
+                    hidden = false;
+                }
+                if hidden {
+                    firejail.arg("--private");
+                }
+                for path in writable {
+                    firejail.arg(format!("--read-write={}", path.display()));
+                }
+                firejail
+            }
+        };
+        command.arg("--").args(program);
+        command
+    }
+
+    fn plain_command(program: &[OsString]) -> Command {
+        let mut command = Command::new(&program[0]);
+        command.args(&program[1..]);
+        command
+    }
+}
+
+/// Limits applied to every spawned renderer process. `None` leaves the
+/// corresponding limit inherited from the parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ResourceLimits {
+    pub cpu_seconds: Option<u64>,
+    pub memory_bytes: Option<u64>,
+    pub file_size_bytes: Option<u64>,
+}
+
+#[cfg(unix)]
+macro_rules! set_rlimit {
+    ($resource:expr, $limit:expr) => {
+        if let Some(limit) = $limit {
+            let rlim = libc::rlimit {
+                rlim_cur: limit as libc::rlim_t,
+                rlim_max: limit as libc::rlim_t,
+            };
+            if libc::setrlimit($resource, &rlim) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+    };
+}
+
+impl ResourceLimits {
+    #[cfg(unix)]
+    pub(crate) fn apply(self, command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+        if self == ResourceLimits::default() {
+            return;
+        }
+        // Only setrlimit(2) runs between fork and exec, which is async-signal-safe.
+        unsafe {
+            command.pre_exec(move || {
+                set_rlimit!(libc::RLIMIT_CPU, self.cpu_seconds);
+                set_rlimit!(libc::RLIMIT_AS, self.memory_bytes);
+                set_rlimit!(libc::RLIMIT_FSIZE, self.file_size_bytes);
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub(crate) fn apply(self, _: &mut Command) {}
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+    // A second signal terminates right away.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+    }
+}
+
+/// Turns SIGINT and SIGTERM into a request to stop: the renderer is killed,
+/// [`Docx::convert_pdf`] returns [`Error::Interrupted`] and the temporary
+/// files go away with the [`Docx`]. A second signal terminates immediately.
+/// Does nothing on other platforms.
+pub fn handle_interrupts() {
+    #[cfg(unix)]
+    unsafe {
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+/// Whether an interrupt arrived, or `cancelled` is set for the conversion.
+pub(crate) fn interrupted(cancelled: Option<&AtomicBool>) -> bool {
+    interrupted_by(&INTERRUPTED, cancelled)
+}
+
+/// [`interrupted`], with the process-wide flag given as `interrupt`.
+fn interrupted_by(interrupt: &AtomicBool, cancelled: Option<&AtomicBool>) -> bool {
+    interrupt.load(Ordering::SeqCst)
+        || cancelled.is_some_and(|cancelled| cancelled.load(Ordering::SeqCst))
+}
+
+/// Runs `command` to completion, or kills it and returns `None` once the
+/// deadline passes or an interrupt arrives.
+pub(crate) fn run_until(
+    command: &mut Command,
+    deadline: Option<Instant>,
+    cancelled: Option<&AtomicBool>,
+) -> io::Result<Option<Output>> {
+    run_with_input(command, None, deadline, cancelled)
+}
+
+/// [`run_until`], writing `input` to the standard input of the command.
+fn run_with_input(
+    command: &mut Command,
+    input: Option<Vec<u8>>,
+    deadline: Option<Instant>,
+    cancelled: Option<&AtomicBool>,
+) -> io::Result<Option<Output>> {
+    let stdin = if input.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    debug!(?command, "running");
+    let started = Instant::now();
+    let mut child = command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(mut pipe), Some(input)) = (child.stdin.take(), input) {
+        // A command that exits without reading everything breaks the pipe.
+        thread::spawn(move || pipe.write_all(&input));
+    }
+    let stdout = read_in_background(child.stdout.take().unwrap());
+    let stderr = read_in_background(child.stderr.take().unwrap());
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if interrupted(cancelled) || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            warn!(program = ?command.get_program(), "killed at the deadline or interrupt");
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+    debug!(
+        program = ?command.get_program(),
+        %status,
+        elapsed = ?started.elapsed(),
+        "finished"
+    );
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap()?,
+        stderr: stderr.join().unwrap()?,
+    }))
+}
+
+fn read_in_background(
+    mut pipe: impl io::Read + Send + 'static,
+) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        pipe.read_to_end(&mut buf).map(|_| buf)
+    })
+}
+
+/// A page rendered by a worker, with what the worker could already do of
+/// adding it.
+enum Rendered {
+    /// Only rendered, for pages that the hooks of the [`Docx`] still see.
+    Svg(Vec<u8>),
+    Raster(RasterPage),
+}
+
+struct RenderedPage {
+    /// The number of the page in the PDF.
+    page: usize,
+    render: Duration,
+    rendered: Rendered,
+    caption: Option<String>,
+    text: Option<String>,
+    /// The mean confidence of the text recognized on the page, in percent.
+    confidence: Option<u8>,
+    /// The outline items that lead to the page, as level and title.
+    headings: Vec<(usize, String)>,
+    links: Vec<PageLink>,
+}
+
+/// The pages handed out to the workers and those waiting to be added.
+struct Queue {
+    /// The next page a worker takes.
+    next: usize,
+    /// How many pages have been added, in order.
+    added: usize,
+    /// The first page past the end or failed, or 0 to stop.
+    end: Option<usize>,
+    done: BTreeMap<usize, Result<Option<RenderedPage>>>,
+}
+
+impl Converter<'_> {
+    /// The layout text of one page from pdftotext, if it can be had.
+    fn page_text(&self, context: &RenderContext, page: usize) -> Option<String> {
+        let page = OsString::from(page.to_string());
+        let mut program = vec![
+            "pdftotext".into(),
+            "-layout".into(),
+            "-f".into(),
+            page.clone(),
+            "-l".into(),
+            page,
+        ];
+        program.extend(poppler_password(context));
+        program.extend([context.pdf.into(), "-".into()]);
+        let mut command = self.options.sandbox.command(&program, &[context.pdf], &[]);
+        self.options.limits.apply(&mut command);
+        let output = run_until(&mut command, context.deadline(), context.cancelled).ok()??;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Runs `tesseract` on `png_data` with `args` after the input and output.
+    fn tesseract(&self, args: &[&str], png_data: Vec<u8>) -> Result<Output> {
+        let mut program = vec![OsString::from("tesseract"), "stdin".into(), "stdout".into()];
+        program.extend(args.iter().map(OsString::from));
+        let mut command = self.options.sandbox.command(&program, &[], &[]);
+        self.options.limits.apply(&mut command);
+        let deadline = self.context.and_then(RenderContext::deadline);
+        let cancelled = self.context.and_then(|context| context.cancelled);
+        match run_with_input(&mut command, Some(png_data), deadline, cancelled) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if self.options.sandbox == Sandbox::None {
+                    Err(Error::TesseractNotFound)
+                } else {
+                    Err(Error::SandboxNotFound)
+                }
+            }
+            Err(e) => Err(e.into()),
+            Ok(None) => Err(self
+                .context
+                .map_or(Error::Interrupted, RenderContext::stopped)),
+            Ok(Some(output)) => Ok(output),
+        }
+    }
+
+    /// How many degrees clockwise Tesseract's orientation detection says
+    /// the page image must be turned to be upright, if it is sure enough.
+    pub(crate) fn detect_orientation(&self, png_data: Vec<u8>) -> Result<Option<u32>> {
+        let output = self.tesseract(&["--psm", "0"], png_data)?;
+        // Pages with too little text fail detection; they stay as they are.
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(orientation(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// The text Tesseract reads on page `page` of `ocr.languages`.
+    fn recognize(&self, ocr: &Ocr, page: usize, png_data: Vec<u8>) -> Result<Recognized> {
+        let picture = Picture::read(&png_data)?;
+        let languages = ocr.language_arg();
+        let dpi = self.options.raster_dpi.round().to_string();
+        let output = self.tesseract(&["-l", &languages, "--dpi", &dpi, "tsv"], png_data)?;
+        if !output.status.success() {
+            warn!(
+                page,
+                stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                "text recognition failed"
+            );
+            return Ok(Recognized {
+                text: String::new(),
+                confidence: None,
+            });
+        }
+        let tsv = String::from_utf8_lossy(&output.stdout);
+        let recognized = layout(&tsv, picture.width, picture.height);
+        info!(page, confidence = ?recognized.confidence, "recognized");
+        Ok(recognized)
+    }
+
+    /// Renders page `page` and does what can be done of adding it away from
+    /// the [`Docx`]: rasterizing it unless `hooked`, and finding its caption.
+    fn render(
+        &self,
+        renderer: &dyn PdfRenderer,
+        context: &RenderContext,
+        page: usize,
+        hooked: bool,
+    ) -> Result<Option<RenderedPage>> {
+        let context = &context.for_page(page);
+        // Turning the page upright and recognizing its text run `tesseract`
+        // within the time limits of the page.
+        let converter = Converter {
+            context: Some(context),
+            ..*self
+        };
+        let started = Instant::now();
+        let cached = self.cache.and_then(|cache| {
+            if hooked {
+                cache.svg(page).map(Rendered::Svg)
+            } else {
+                cache.raster(page, self.options).map(Rendered::Raster)
+            }
+        });
+        let (render, rendered) = match cached {
+            Some(rendered) => {
+                debug!("cached");
+                (Duration::ZERO, rendered)
+            }
+            None => {
+                let svg_data = match renderer.render_page(context, page)? {
+                    Some(svg_page) => svg_page.data,
+                    None => return Ok(None),
+                };
+                let render = started.elapsed();
+                debug!(?render, bytes = svg_data.len(), "rendered");
+                if let Some(cache) = self.cache {
+                    cache.put_svg(page, &svg_data);
+                }
+                let rendered = if hooked {
+                    Rendered::Svg(svg_data)
+                } else {
+                    let raster = converter.raster_page(page, svg_data, None, None)?;
+                    if let Some(cache) = self.cache {
+                        cache.put_raster(page, &raster);
+                    }
+                    Rendered::Raster(raster)
+                };
+                (render, rendered)
+            }
+        };
+        let text = if self.options.caption_alt_text || self.options.text_layer != TextLayer::None {
+            self.page_text(context, page)
+        } else {
+            None
+        };
+        let caption = text
+            .as_deref()
+            .filter(|_| self.options.caption_alt_text)
+            .and_then(captions);
+        let mut text = text.filter(|text| {
+            self.options.text_layer != TextLayer::None && !text.trim().is_empty()
+        });
+        let mut confidence = None;
+        if let Some(ocr) = self
+            .options
+            .ocr
+            .as_ref()
+            .filter(|_| text.is_none() && self.options.text_layer != TextLayer::None)
+        {
+            let rasterized;
+            let raster = match &rendered {
+                Rendered::Raster(raster) => raster,
+                Rendered::Svg(svg_data) => {
+                    rasterized = converter.raster_page(page, svg_data.clone(), None, None)?;
+                    &rasterized
+                }
+            };
+            let png_data = match &raster.png_data {
+                Some(png_data) => png_data.clone(),
+                None => converter.png_fallback(page, &raster.svg_data, None)?,
+            };
+            let recognized = converter.recognize(ocr, page, png_data)?;
+            confidence = recognized.confidence;
+            text = Some(recognized.text).filter(|text| !text.trim().is_empty());
+        }
+        Ok(Some(RenderedPage {
+            page,
+            render,
+            rendered,
+            caption,
+            text,
+            confidence,
+            headings: Vec::new(),
+            links: Vec::new(),
+        }))
+    }
+}
+
+/// Takes pages from `queue` and renders them, until the end or an error.
+fn work(
+    queue: &(Mutex<Queue>, Condvar),
+    jobs: usize,
+    render: &(impl Fn(usize) -> Result<Option<RenderedPage>> + Sync),
+) {
+    let (queue, wake) = queue;
+    loop {
+        let page = {
+            let mut queue = queue.lock().unwrap();
+            // Stay a few pages ahead of the pages added, no more.
+            while queue.next > queue.added + 2 * jobs
+                && queue.end.is_none_or(|end| queue.next <= end)
+            {
+                queue = wake.wait(queue).unwrap();
+            }
+            if queue.end.is_some_and(|end| queue.next > end) {
+                return;
+            }
+            queue.next += 1;
+            queue.next - 1
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| render(page)));
+        let mut queue = queue.lock().unwrap();
+        let (result, panicked) = match result {
+            Ok(result) => (result, None),
+            // The pages after it are not waited for, and the panic goes on
+            // once they stop.
+            Err(payload) => (Err(Error::RendererKilled), Some(payload)),
+        };
+        if !matches!(result, Ok(Some(_))) {
+            queue.end = Some(queue.end.map_or(page, |end| end.min(page)));
+        }
+        queue.done.insert(page, result);
+        wake.notify_all();
+        if let Some(payload) = panicked {
+            drop(queue);
+            panic::resume_unwind(payload);
+        }
+    }
+}
+
+impl Docx {
+    /// Adds the pages `render` returns for pages 1, 2, … in order, until it
+    /// returns `None`, calling it from `jobs` threads. Returns how many pages
+    /// there were.
+    fn add_pages(
+        &mut self,
+        jobs: usize,
+        render: impl Fn(usize) -> Result<Option<RenderedPage>> + Sync,
+        context: Option<&RenderContext>,
+    ) -> Result<usize> {
+        let queue = (
+            Mutex::new(Queue {
+                next: 1,
+                added: 0,
+                end: self.options.max_pages.map(|max| max + 1),
+                done: BTreeMap::new(),
+            }),
+            Condvar::new(),
+        );
+        thread::scope(|scope| {
+            for _ in 0..jobs {
+                scope.spawn(|| work(&queue, jobs, &render));
+            }
+            let pages = self.add_rendered(&queue, context);
+            // Stop the workers; the scope waits for them.
+            queue.0.lock().unwrap().end = Some(0);
+            queue.1.notify_all();
+            pages
+        })
+    }
+
+    /// Adds the pages that the workers put in `queue`, in order.
+    fn add_rendered(
+        &mut self,
+        queue: &(Mutex<Queue>, Condvar),
+        context: Option<&RenderContext>,
+    ) -> Result<usize> {
+        let deadline = context.and_then(|context| context.deadline);
+        let (queue, wake) = queue;
+        let mut page = 0;
+        loop {
+            let result = {
+                let mut queue = queue.lock().unwrap();
+                loop {
+                    if let Some(result) = queue.done.remove(&(page + 1)) {
+                        break result;
+                    }
+                    queue = wake.wait(queue).unwrap();
+                }
+            };
+            let Some(rendered) = result? else {
+                return Ok(page);
+            };
+            page += 1;
+            queue.lock().unwrap().added = page;
+            wake.notify_all();
+            if self.options.max_pages.is_some_and(|max| page > max) {
+                return Err(Error::LimitExceeded);
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(Error::TimeBudgetExceeded);
+            }
+            if interrupted(Some(&self.cancelled)) {
+                return Err(Error::Interrupted);
+            }
+            // Named after the page of the document, not of the PDF, so that
+            // pages added before do not clash.
+            let name = format!("{}.svg", self.rendered + 1);
+            match rendered.rendered {
+                Rendered::Svg(svg_data) => {
+                    let context = context.map(|context| context.for_page(rendered.page));
+                    self.add_svg_with(&name, |converter, number, svg_hook, raster_hook| {
+                        let converter = Converter {
+                            context: context.as_ref(),
+                            ..converter
+                        };
+                        converter.raster_page(number, svg_data, svg_hook, raster_hook)
+                    })
+                }
+                Rendered::Raster(raster) => {
+                    self.rendered += 1;
+                    self.add_raster_page(&name, raster)
+                }
+            }
+            .map_err(|e| e.on_page(rendered.page))?;
+            if let Some(metrics) = self.metrics.last_mut() {
+                metrics.render = rendered.render;
+                metrics.ocr_confidence = rendered.confidence;
+            }
+            if let Ok(page) = self.page_mut(self.rendered) {
+                if let Some(caption) = rendered.caption {
+                    page.alt_text.get_or_insert(caption);
+                }
+                page.text = rendered.text;
+                page.headings = rendered.headings;
+                page.links = rendered.links;
+            }
+        }
+    }
+
+    /// Converts every page of `pdf` with the renderer plugin if one is
+    /// loaded, or else the [`DocxOptions::backend`]. With the `pdfium`
+    /// feature, PDFium takes over when Inkscape is not installed.
+    pub fn convert_pdf(&mut self, pdf: &Path) -> Result<()> {
+        self.convert_pdf_pages(pdf, &PageSelection::All)
+    }
+
+    /// Converts every page of `pdf` like [`Docx::convert_pdf`], after the
+    /// pages already added and set apart from them as
+    /// [`DocxOptions::source_separator`] says. The metadata of the document
+    /// stays that of the first PDF, the recorded PDF/A conformance is that
+    /// of all of them, and [`DocxOptions::time_budget`] starts again.
+    pub fn append_pdf(&mut self, pdf: &Path) -> Result<()> {
+        let before = self.pages.len();
+        self.convert_pdf(pdf)?;
+        if before > 0 && self.pages.len() > before {
+            let last = &mut self.pages[before - 1];
+            match self.options.source_separator {
+                SourceSeparator::None => {}
+                SourceSeparator::PageBreak => last.break_after = true,
+                SourceSeparator::BlankPage => last.blank_after = true,
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts every page of an encrypted `pdf` like [`Docx::convert_pdf`],
+    /// opening it with `password` instead of [`DocxOptions::pdf_password`].
+    pub fn convert_pdf_with_password(&mut self, pdf: &Path, password: &str) -> Result<()> {
+        let previous = self.options.pdf_password.replace(password.to_owned());
+        let result = self.convert_pdf(pdf);
+        self.options.pdf_password = previous;
+        result
+    }
+
+    /// Converts the `pages` of `pdf` like [`Docx::convert_pdf`]. Returns
+    /// [`Error::PageOutOfRange`] if none of them are in the PDF, or
+    /// [`Error::PdfEncrypted`] if it is encrypted and none could be rendered.
+    pub fn convert_pdf_pages(&mut self, pdf: &Path, pages: &PageSelection) -> Result<()> {
+        #[cfg(feature = "plugins")]
+        if self.plugins.renders_pages() {
+            let plugins = Arc::clone(&self.plugins);
+            return self.convert_pdf_pages_with(pdf, pages, &*plugins);
+        }
+        let result = self.convert_pdf_pages_with(pdf, pages, self.options.backend.renderer());
+        // Nothing was added, so PDFium can start over.
+        #[cfg(feature = "pdfium")]
+        if matches!(result, Err(Error::InkscapeNotFound)) {
+            warn!("Inkscape not found; rendering with PDFium");
+            return self.convert_pdf_pages_with(pdf, pages, &crate::Pdfium);
+        }
+        result
+    }
+
+    /// Converts every page of `pdf` with `renderer`, rendering and
+    /// rasterizing [`DocxOptions::jobs`] pages at once.
+    pub fn convert_pdf_with(&mut self, pdf: &Path, renderer: &dyn PdfRenderer) -> Result<()> {
+        self.convert_pdf_pages_with(pdf, &PageSelection::All, renderer)
+    }
+
+    /// Converts the `pages` of `pdf` like [`Docx::convert_pdf_with`].
+    pub fn convert_pdf_pages_with(
+        &mut self,
+        pdf: &Path,
+        pages: &PageSelection,
+        renderer: &dyn PdfRenderer,
+    ) -> Result<()> {
+        let span = info_span!("convert_pdf", pdf = %pdf.display());
+        let _entered = span.enter();
+        let started = Instant::now();
+        self.source = pdf
+            .file_name()
+            .filter(|_| !self.options.scrub_metadata)
+            .map(|name| name.to_string_lossy().into_owned());
+        let pdf = pdf.canonicalize().map_err(|e| Error::from(e).at(pdf))?;
+        if self.options.record_pdfa {
+            let report = check_pdfa(&pdf)?;
+            self.pdfa = Some(match self.pdfa.take() {
+                Some(before) => before.and(report),
+                None => report,
+            });
+        }
+        let deadline = self.options.time_budget.map(|budget| Instant::now() + budget);
+        // The hooks run here, so pages they see are only rendered by workers.
+        let hooked = self.svg_hook.is_some() || self.raster_hook.is_some();
+        let jobs = self.options.jobs.max(1);
+        // Copies of what the workers share, as pages are added to `self`
+        // meanwhile.
+        let options = self.options.clone();
+        let scratch = self.scratch.as_ref().map(|scratch| scratch.path().to_owned());
+        let cancelled = Arc::clone(&self.cancelled);
+        #[cfg(feature = "plugins")]
+        let plugins = Arc::clone(&self.plugins);
+        let mut context = RenderContext {
+            pdf: &pdf,
+            options: &options,
+            scratch: scratch.as_deref(),
+            deadline,
+            page_deadline: None,
+            cancelled: Some(&cancelled),
+            page_count: None,
+        };
+        // Read before rendering, to tell an encrypted PDF from a broken one.
+        let data = std::fs::read(&pdf).map_err(|e| Error::from(e).at(&pdf))?;
+        let encrypted = is_encrypted(&data);
+        let cache = match &self.options.cache_dir {
+            Some(root) => Some(PageCache::open(root, &data, &options)?),
+            None => None,
+        };
+        let converter = Converter {
+            options: &options,
+            cache: cache.as_ref(),
+            context: None,
+            #[cfg(feature = "plugins")]
+            plugins: &plugins,
+        };
+        // Appended PDFs leave the metadata of the first one.
+        if self.options.pdf_metadata
+            && !self.options.scrub_metadata
+            && self.source_metadata == Metadata::default()
+        {
+            self.source_metadata = metadata(&data);
+        }
+        // The headings before each page of the PDF.
+        let mut bookmarks: BTreeMap<usize, Vec<(usize, String)>> = BTreeMap::new();
+        if self.options.pdf_outline {
+            for item in outline(&data) {
+                bookmarks
+                    .entry(item.page)
+                    .or_default()
+                    .push((item.level, item.title));
+            }
+        }
+        // The links on each page of the PDF, leading to pages as numbered in
+        // the document.
+        let mut links: BTreeMap<usize, Vec<PageLink>> = BTreeMap::new();
+        if self.options.pdf_links {
+            let first = self.rendered;
+            for (page, mut link) in pdf_links(&data) {
+                if let LinkTarget::Page(target) = link.target {
+                    let Some(n) = pages.position(target) else {
+                        continue;
+                    };
+                    link.target = LinkTarget::Page(first + n);
+                }
+                links.entry(page).or_default().push(link);
+            }
+        }
+        // With the page count from the renderer, a page that renders to
+        // nothing is an error rather than the end of the PDF. The one read
+        // from the PDF itself may be wrong, so it only bounds the pages.
+        let reported = renderer.page_count(&context)?;
+        context.page_count = reported.or_else(|| page_count(&data));
+        let total = context.page_count;
+        info!(pages = ?total, encrypted, jobs, "opened");
+        if let Some(total) = total {
+            self.report(ProgressEvent::TotalPages(pages.count(total)));
+        }
+        let progress = self.progress.clone();
+        let added = self.add_pages(
+            jobs,
+            |n| {
+                let Some(page) = pages
+                    .nth(n)
+                    .filter(|&page| total.is_none_or(|total| page <= total))
+                else {
+                    return Ok(None);
+                };
+                // The workers run outside the span of the conversion.
+                let _span = info_span!(parent: &span, "page", page).entered();
+                let mut rendered = converter
+                    .render(renderer, &context, page, hooked)
+                    .map_err(|e| e.on_page(page))?;
+                if let (Some(rendered), Some(headings)) = (&mut rendered, bookmarks.get(&page)) {
+                    rendered.headings = headings.clone();
+                }
+                if let (Some(rendered), Some(links)) = (&mut rendered, links.get(&page)) {
+                    rendered.links = links.clone();
+                }
+                if rendered.is_none() && reported.is_some() {
+                    return Err(if encrypted && n == 1 {
+                        Error::PdfEncrypted
+                    } else {
+                        Error::PageRender {
+                            page,
+                            source: Box::new(Error::PDFInvalid),
+                        }
+                    });
+                }
+                if let (Some(_), Some(progress)) = (&rendered, &progress) {
+                    progress.event(ProgressEvent::PageRendered(page));
+                }
+                Ok(rendered)
+            },
+            Some(&context),
+        )?;
+        // Links to pages past the end, or skipped, lead nowhere.
+        let numbers: HashSet<usize> = self.pages.iter().map(|page| page.number).collect();
+        for page in &mut self.pages {
+            page.links.retain(|link| match link.target {
+                LinkTarget::Page(number) => numbers.contains(&number),
+                LinkTarget::Uri(_) => true,
+            });
+        }
+        info!(pages = added, elapsed = ?started.elapsed(), "converted");
+        if added == 0 && encrypted {
+            return Err(Error::PdfEncrypted);
+        }
+        if added == 0 && *pages != PageSelection::All {
+            return Err(Error::PageOutOfRange);
+        }
+        if added == 0 {
+            return Err(Error::PDFInvalid);
+        }
+        if let Some(path) = &self.options.metrics {
+            let file = File::create(path).map_err(|e| Error::from(e).at(path))?;
+            write_csv(file, &self.metrics).map_err(|e| e.at(path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Tesseract reports less than this confidence for pages that have little
+/// text, or text in more than one direction.
+const MIN_ORIENTATION_CONFIDENCE: f64 = 14.0;
+
+/// The rotation in Tesseract's `--psm 0` output.
+fn orientation(osd: &str) -> Option<u32> {
+    let field = |name: &str| {
+        osd.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .map(str::trim)
+    };
+    let rotate: u32 = field("Rotate")?.parse().ok()?;
+    let confidence: f64 = field("Orientation confidence")?.parse().ok()?;
+    (rotate != 0 && confidence >= MIN_ORIENTATION_CONFIDENCE).then_some(rotate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+
+    #[test]
+    fn test_firejail() {
+        let Some(home) = std::env::var_os("HOME").map(PathBuf::from) else {
+            return;
+        };
+        let args = |readable: &[&Path]| {
+            let command = Sandbox::Firejail.command(&["pdftocairo".into()], readable, &[]);
+            command
+                .get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert!(args(&[Path::new("/nonexistent/a.pdf")]).contains(&String::from("--private")));
+        let pdf = home.join("a.pdf");
+        let args = args(&[&pdf]);
+        assert!(!args.contains(&String::from("--private")));
+        assert!(args.contains(&format!("--whitelist={}", pdf.display())));
+        assert!(args.contains(&format!("--read-only={}", pdf.display())));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resource_limits() {
+        let limits = ResourceLimits {
+            cpu_seconds: Some(7),
+            ..ResourceLimits::default()
+        };
+        let mut command = Command::new("sh");
+        command.args(["-c", "ulimit -t"]);
+        limits.apply(&mut command);
+        let output = command.output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "7");
+    }
+
+    #[test]
+    fn test_orientation() {
+        let osd = "Page number: 0\nOrientation in degrees: 270\nRotate: 90\n\
+                   Orientation confidence: 21.67\nScript: Latin\nScript confidence: 4.41\n";
+        assert_eq!(orientation(osd), Some(90));
+        assert_eq!(orientation(&osd.replace("21.67", "1.20")), None);
+        assert_eq!(orientation(&osd.replace("Rotate: 90", "Rotate: 0")), None);
+        assert_eq!(orientation(""), None);
+    }
+
+    #[test]
+    fn test_add_pages() {
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let svg_data =
+            std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg")).unwrap();
+        let highest = std::sync::atomic::AtomicUsize::new(0);
+        let render = |page: usize| {
+            highest.fetch_max(page, Ordering::SeqCst);
+            // Finish out of order.
+            thread::sleep(Duration::from_millis(((7 - page % 7) * 5) as u64));
+            Ok((page <= 6).then(|| RenderedPage {
+                page,
+                render: Duration::from_millis(page as u64),
+                rendered: Rendered::Svg(svg_data.clone()),
+                caption: None,
+                text: None,
+                confidence: None,
+                headings: Vec::new(),
+                links: Vec::new(),
+            }))
+        };
+
+        let mut docx = Docx::with_options(options.clone()).unwrap();
+        assert_eq!(docx.add_pages(3, render, None).unwrap(), 6);
+        let svgs: Vec<_> = docx.pages.iter().map(|page| page.svg.clone().unwrap()).collect();
+        assert_eq!(svgs, ["1.svg", "2.svg", "3.svg", "4.svg", "5.svg", "6.svg"]);
+        assert_eq!(docx.metrics[3].render, Duration::from_millis(4));
+
+        let mut docx = Docx::with_options(DocxOptions {
+            max_pages: Some(2),
+            ..options.clone()
+        })
+        .unwrap();
+        highest.store(0, Ordering::SeqCst);
+        assert!(matches!(
+            docx.add_pages(3, render, None),
+            Err(Error::LimitExceeded)
+        ));
+        assert_eq!(highest.load(Ordering::SeqCst), 3);
+        assert!(matches!(
+            docx.add_pages(2, |_| Err(Error::RendererKilled), None),
+            Err(Error::RendererKilled)
+        ));
+
+        // A page that panics is not waited for.
+        let panicking = |page: usize| match page {
+            2 => panic!("page 2"),
+            _ => render(page),
+        };
+        let mut docx = Docx::with_options(options).unwrap();
+        let added = panic::catch_unwind(AssertUnwindSafe(|| docx.add_pages(3, panicking, None)));
+        assert!(added.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_interrupt() {
+        // The process-wide flag is left alone: other tests run meanwhile.
+        let signalled = AtomicBool::new(true);
+        let quiet = AtomicBool::new(false);
+        assert!(interrupted_by(&signalled, None));
+        assert!(!interrupted_by(&quiet, None));
+        assert!(interrupted_by(&quiet, Some(&signalled)));
+        assert!(!interrupted_by(&quiet, Some(&quiet)));
+
+        let started = Instant::now();
+        let cancelled = AtomicBool::new(true);
+        let output = run_until(Command::new("sleep").arg("10"), None, Some(&cancelled));
+        assert!(output.unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
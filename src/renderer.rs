@@ -0,0 +1,603 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! The programs that turn PDF pages into SVG, behind [`PdfRenderer`], and how
+//! they are run: sandboxed, limited, and killed on the deadline or an
+//! interrupt.
+
+use crate::package::shred_file;
+use crate::render::{interrupted, run_until};
+use crate::{DocxOptions, Error, Result};
+use std::ffi::OsString;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+/// One page of a PDF, as SVG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SvgPage {
+    pub data: Vec<u8>,
+}
+
+/// Turns the pages of a PDF into SVG, for [`Docx::convert_pdf_with`]. Pages
+/// are requested from several threads at once when
+/// [`DocxOptions::jobs`] is above 1.
+///
+/// [`Docx::convert_pdf_with`]: crate::Docx::convert_pdf_with
+pub trait PdfRenderer: Sync {
+    /// How many pages the PDF has, if the renderer can tell cheaply. Pages
+    /// past it are not asked for, and a page before it that renders to
+    /// `None` fails the conversion. It is also
+    /// [`ProgressEvent::TotalPages`](crate::ProgressEvent).
+    fn page_count(&self, _context: &RenderContext) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Page `page`, counting from 1, or `None` past the last page.
+    fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>>;
+}
+
+/// The PDF being converted, and the means to run programs on it the way the
+/// [`DocxOptions`] ask for.
+pub struct RenderContext<'a> {
+    pub(crate) pdf: &'a Path,
+    pub(crate) options: &'a DocxOptions,
+    pub(crate) scratch: Option<&'a Path>,
+    pub(crate) deadline: Option<Instant>,
+    /// The page being rendered and when its time is up, with
+    /// [`DocxOptions::page_timeout`].
+    pub(crate) page_deadline: Option<(usize, Instant)>,
+    pub(crate) cancelled: Option<&'a AtomicBool>,
+    pub(crate) page_count: Option<usize>,
+}
+
+impl<'a> RenderContext<'a> {
+    /// The context for rendering page `page`, whose programs are also killed
+    /// once [`DocxOptions::page_timeout`] passes.
+    pub(crate) fn for_page(&self, page: usize) -> RenderContext<'a> {
+        let page_deadline = self
+            .options
+            .page_timeout
+            .map(|timeout| (page, Instant::now() + timeout));
+        RenderContext {
+            page_deadline,
+            ..*self
+        }
+    }
+
+    /// When programs are killed: at the end of the time budget or of the
+    /// page timeout, whichever comes first.
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        let page_deadline = self.page_deadline.map(|(_, deadline)| deadline);
+        self.deadline.into_iter().chain(page_deadline).min()
+    }
+
+    /// Why a program run until [`RenderContext::deadline`] was killed: an
+    /// interrupt, the page timeout or the end of the time budget.
+    pub(crate) fn stopped(&self) -> Error {
+        if interrupted(self.cancelled) {
+            return Error::Interrupted;
+        }
+        match self.page_deadline {
+            Some((page, deadline)) if Instant::now() >= deadline => Error::PageTimeout { page },
+            _ => Error::TimeBudgetExceeded,
+        }
+    }
+}
+
+impl RenderContext<'_> {
+    /// The PDF, as an absolute path.
+    pub fn pdf(&self) -> &Path {
+        self.pdf
+    }
+
+    /// The password of the PDF, from [`DocxOptions::pdf_password`]. It goes
+    /// on the command line, where other local users may see it.
+    pub fn password(&self) -> Option<&str> {
+        self.options.pdf_password.as_deref()
+    }
+
+    /// How many pages the PDF has, from [`PdfRenderer::page_count`] or the
+    /// PDF itself, if either could tell. Pages up to it are never past the
+    /// end.
+    pub fn page_count(&self) -> Option<usize> {
+        self.page_count
+    }
+
+    /// A private directory the renderer may write files to, or `None` when
+    /// the output must come from pipes ([`DocxOptions::in_memory`]).
+    pub fn scratch(&self) -> Option<&Path> {
+        self.scratch
+    }
+
+    /// Runs `program` with its arguments in the sandbox and within the
+    /// resource limits. It can read the PDF and write to the scratch
+    /// directory. A program that cannot be found is
+    /// [`Error::RendererNotFound`]; one still running past the page timeout
+    /// is killed, as [`Error::PageTimeout`].
+    pub fn run(&self, program: &[OsString]) -> Result<Output> {
+        let writable: Vec<&Path> = self.scratch.into_iter().collect();
+        let mut command = self
+            .options
+            .sandbox
+            .command(program, &[self.pdf], &writable);
+        self.options.limits.apply(&mut command);
+        match run_until(&mut command, self.deadline(), self.cancelled) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                if self.options.sandbox == crate::Sandbox::None {
+                    Err(Error::RendererNotFound)
+                } else {
+                    Err(Error::SandboxNotFound)
+                }
+            }
+            Err(e) => Err(e.into()),
+            Ok(None) => Err(self.stopped()),
+            Ok(Some(output)) if output.status.code().is_none() => Err(Error::RendererKilled),
+            Ok(Some(output)) => Ok(output),
+        }
+    }
+
+    /// Where the SVG of `page` goes in the scratch directory, if there is one.
+    pub fn scratch_file(&self, page: usize) -> Option<PathBuf> {
+        self.scratch
+            .map(|scratch| scratch.join(format!("{}.svg", page)))
+    }
+
+    /// Reads and deletes a file the renderer wrote, overwriting it first
+    /// with [`DocxOptions::secure_delete`].
+    pub fn take_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let data = std::fs::read(path)?;
+        self.remove_file(path)?;
+        Ok(data)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        if self.options.secure_delete {
+            shred_file(path)
+        } else {
+            Ok(std::fs::remove_file(path)?)
+        }
+    }
+
+    /// The SVG a program wrote to `image`, or else to its standard output.
+    fn output(&self, image: Option<PathBuf>, output: Output) -> Result<Option<SvgPage>> {
+        let data = match image {
+            Some(image) if !image.exists() => return Ok(None),
+            Some(image) => self.take_file(&image)?,
+            None => output.stdout,
+        };
+        Ok((!data.is_empty()).then_some(SvgPage { data }))
+    }
+
+    /// Removes what a failed program may have left in `image`.
+    fn discard(&self, image: Option<PathBuf>) -> Result<Option<SvgPage>> {
+        if let Some(image) = image.filter(|image| image.exists()) {
+            self.remove_file(&image)?;
+        }
+        Ok(None)
+    }
+}
+
+/// Inkscape with its poppler import, which keeps text as paths and is the
+/// most faithful of the three. It cannot open PDFs that need a password,
+/// and fails before the first page when given one instead of ignoring it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Inkscape;
+
+impl PdfRenderer for Inkscape {
+    /// From `pdfinfo`, which comes with the poppler Inkscape imports with,
+    /// if it is installed.
+    fn page_count(&self, context: &RenderContext) -> Result<Option<usize>> {
+        if context.password().is_some() {
+            let message = "Inkscape cannot open PDFs with a password; use pdftocairo or mutool";
+            return Err(std::io::Error::new(ErrorKind::Unsupported, message).into());
+        }
+        match Pdftocairo.page_count(context) {
+            Err(Error::RendererNotFound | Error::SandboxNotFound) => Ok(None),
+            result => result,
+        }
+    }
+
+    fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+        let image = context.scratch_file(page);
+        let mut program = vec![
+            OsString::from("inkscape"),
+            context.pdf.into(),
+            format!("--pdf-page={}", page).into(),
+        ];
+        match &image {
+            Some(image) => program.extend(["-o".into(), image.into()]),
+            None => program.extend(["--export-type=svg".into(), "--export-filename=-".into()]),
+        }
+        program.push("--pdf-poppler".into());
+        let output = match context.run(&program) {
+            Err(Error::RendererNotFound) => return Err(Error::InkscapeNotFound),
+            result => result?,
+        };
+        // Inkscape exits successfully past the last page, complaining. It
+        // also warns about pages it renders well.
+        let past_end = context.page_count().is_none_or(|count| page > count);
+        if !output.stderr.is_empty() && past_end {
+            return context.discard(image);
+        }
+        context.output(image, output)
+    }
+}
+
+/// `pdftocairo` from poppler-utils, much faster than Inkscape. The page
+/// count comes from `pdfinfo`, of the same package.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pdftocairo;
+
+impl PdfRenderer for Pdftocairo {
+    fn page_count(&self, context: &RenderContext) -> Result<Option<usize>> {
+        let mut program = vec!["pdfinfo".into()];
+        program.extend(poppler_password(context));
+        program.push(context.pdf.into());
+        let output = context.run(&program)?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("Pages:"))
+            .and_then(|count| count.trim().parse().ok()))
+    }
+
+    fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+        let image = context.scratch_file(page);
+        let page = OsString::from(page.to_string());
+        let mut program = vec![
+            "pdftocairo".into(),
+            "-svg".into(),
+            "-f".into(),
+            page.clone(),
+            "-l".into(),
+            page,
+        ];
+        program.extend(poppler_password(context));
+        program.extend([
+            context.pdf.into(),
+            image.as_deref().map_or("-".into(), OsString::from),
+        ]);
+        let output = context.run(&program)?;
+        // Past the last page, the page range is wrong.
+        if !output.status.success() {
+            return context.discard(image);
+        }
+        context.output(image, output)
+    }
+}
+
+/// The `-upw` option of the poppler tools, with the user password.
+pub(crate) fn poppler_password(context: &RenderContext) -> Vec<OsString> {
+    match context.password() {
+        Some(password) => vec!["-upw".into(), password.into()],
+        None => Vec::new(),
+    }
+}
+
+/// The `-p` option of `mutool`, with the password.
+fn mutool_password(context: &RenderContext) -> Vec<OsString> {
+    match context.password() {
+        Some(password) => vec!["-p".into(), password.into()],
+        None => Vec::new(),
+    }
+}
+
+/// `mutool` from MuPDF, the fastest, with text as `<text>` elements where
+/// the fonts allow.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Mutool;
+
+impl PdfRenderer for Mutool {
+    fn page_count(&self, context: &RenderContext) -> Result<Option<usize>> {
+        let mut program = vec!["mutool".into(), "show".into()];
+        program.extend(mutool_password(context));
+        program.extend([context.pdf.into(), "trailer/Root/Pages/Count".into()]);
+        let output = context.run(&program)?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+    }
+
+    fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+        let image = context.scratch_file(page);
+        let mut program = vec![
+            "mutool".into(),
+            "draw".into(),
+            "-q".into(),
+            "-F".into(),
+            "svg".into(),
+            "-o".into(),
+            image
+                .as_deref()
+                .map_or("/dev/stdout".into(), OsString::from),
+        ];
+        program.extend(mutool_password(context));
+        program.extend([context.pdf.into(), page.to_string().into()]);
+        let output = context.run(&program)?;
+        // Past the last page mutool warns and draws nothing.
+        if !output.status.success() {
+            return context.discard(image);
+        }
+        context.output(image, output)
+    }
+}
+
+/// Which built-in [`PdfRenderer`] [`Docx::convert_pdf`] uses.
+///
+/// [`Docx::convert_pdf`]: crate::Docx::convert_pdf
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Backend {
+    #[default]
+    Inkscape,
+    Pdftocairo,
+    Mutool,
+    /// The PDFium library, without any external program.
+    #[cfg(feature = "pdfium")]
+    Pdfium,
+}
+
+impl Backend {
+    pub fn renderer(self) -> &'static dyn PdfRenderer {
+        match self {
+            Backend::Inkscape => &Inkscape,
+            Backend::Pdftocairo => &Pdftocairo,
+            Backend::Mutool => &Mutool,
+            #[cfg(feature = "pdfium")]
+            Backend::Pdfium => &crate::Pdfium,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Docx, PageSelection, ProgressEvent};
+
+    /// Three copies of the test SVG, without running anything.
+    struct Fixture;
+
+    impl PdfRenderer for Fixture {
+        fn page_count(&self, _: &RenderContext) -> Result<Option<usize>> {
+            Ok(Some(3))
+        }
+
+        fn render_page(&self, _: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+            if page > 3 {
+                return Ok(None);
+            }
+            let data = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg"))?;
+            Ok(Some(SvgPage { data }))
+        }
+    }
+
+    #[test]
+    fn test_convert_pdf_with() {
+        let docx = || {
+            Docx::with_options(DocxOptions {
+                in_memory: true,
+                jobs: 2,
+                ..DocxOptions::default()
+            })
+            .unwrap()
+        };
+        let pdf = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg"));
+        let mut all = docx();
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = std::sync::Arc::clone(&events);
+        all.set_progress_handler(move |event| sink.lock().unwrap().push(event));
+        all.convert_pdf_with(pdf, &Fixture).unwrap();
+        assert_eq!(all.metrics().len(), 3);
+        let events = events.lock().unwrap();
+        assert_eq!(events[0], ProgressEvent::TotalPages(3));
+        let embedded: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::PageEmbedded(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(embedded, [1, 2, 3]);
+        let rendered = events
+            .iter()
+            .filter(|event| matches!(event, ProgressEvent::PageRendered(_)))
+            .count();
+        assert_eq!(rendered, 3);
+        let mut some = docx();
+        let pages = "1,3-9".parse().unwrap();
+        some.convert_pdf_pages_with(pdf, &pages, &Fixture).unwrap();
+        assert_eq!(some.metrics().len(), 2);
+        let pages = PageSelection::Range(4..=5);
+        assert!(matches!(
+            docx().convert_pdf_pages_with(pdf, &pages, &Fixture),
+            Err(Error::PageOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn test_record_pdfa() {
+        let dir = tempfile::tempdir().unwrap();
+        let conformant = dir.path().join("a.pdf");
+        let encrypted = dir.path().join("b.pdf");
+        let xmp = "<rdf:Description pdfaid:part=\"2\" pdfaid:conformance=\"B\"/>";
+        std::fs::write(
+            &conformant,
+            format!("%PDF-1.7\n<< /OutputIntents [] >>\n{}", xmp),
+        )
+        .unwrap();
+        std::fs::write(
+            &encrypted,
+            format!("%PDF-1.7\n<< /Encrypt 5 0 R >>\n{}", xmp),
+        )
+        .unwrap();
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            record_pdfa: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        docx.convert_pdf_with(&conformant, &Fixture).unwrap();
+        assert_eq!(docx.pdfa.as_ref().unwrap().to_string(), "PDF/A-2B");
+        docx.convert_pdf_with(&encrypted, &Fixture).unwrap();
+        docx.convert_pdf_with(&conformant, &Fixture).unwrap();
+        assert_eq!(
+            docx.pdfa.as_ref().unwrap().to_string(),
+            "claims PDF/A-2B, but it is encrypted; it has no output intent"
+        );
+    }
+
+    /// The fixture with page 2 failing to render.
+    struct Gappy;
+
+    impl PdfRenderer for Gappy {
+        fn page_count(&self, _: &RenderContext) -> Result<Option<usize>> {
+            Ok(Some(3))
+        }
+
+        fn render_page(&self, context: &RenderContext, page: usize) -> Result<Option<SvgPage>> {
+            assert!(page <= 3);
+            match page {
+                2 => Ok(None),
+                _ => Fixture.render_page(context, page),
+            }
+        }
+    }
+
+    #[test]
+    fn test_page_count() {
+        let pdf = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/2.svg"));
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            docx.convert_pdf_with(pdf, &Gappy),
+            Err(Error::PageRender { page: 2, source }) if matches!(*source, Error::PDFInvalid)
+        ));
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        docx.convert_pdf_pages_with(pdf, &PageSelection::Range(3..=9), &Gappy)
+            .unwrap();
+        assert_eq!(docx.metrics().len(), 1);
+    }
+
+    /// Renders nothing, like a renderer given the wrong password.
+    struct Locked;
+
+    impl PdfRenderer for Locked {
+        fn render_page(&self, context: &RenderContext, _: usize) -> Result<Option<SvgPage>> {
+            assert_eq!(context.password(), Some("secret"));
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_encrypted() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = dir.path().join("locked.pdf");
+        std::fs::write(&pdf, b"%PDF-1.7\ntrailer << /Encrypt 9 0 R >>").unwrap();
+        let mut docx = Docx::with_options(DocxOptions {
+            in_memory: true,
+            pdf_password: Some(String::from("secret")),
+            ..DocxOptions::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            docx.convert_pdf_with(&pdf, &Locked),
+            Err(Error::PdfEncrypted)
+        ));
+        let options = DocxOptions {
+            pdf_password: Some(String::from("secret")),
+            ..DocxOptions::default()
+        };
+        let context = RenderContext {
+            pdf: &pdf,
+            options: &options,
+            scratch: None,
+            deadline: None,
+            page_deadline: None,
+            cancelled: None,
+            page_count: None,
+        };
+        assert_eq!(poppler_password(&context), ["-upw", "secret"]);
+        assert_eq!(mutool_password(&context), ["-p", "secret"]);
+        assert!(matches!(
+            Inkscape.page_count(&context),
+            Err(Error::IoError { source, .. }) if source.kind() == ErrorKind::Unsupported
+        ));
+    }
+
+    #[test]
+    fn test_run() {
+        let options = DocxOptions::default();
+        let context = RenderContext {
+            pdf: Path::new("a.pdf"),
+            options: &options,
+            scratch: None,
+            deadline: None,
+            page_deadline: None,
+            cancelled: None,
+            page_count: None,
+        };
+        assert!(matches!(
+            context.run(&["docx-you-want-no-such-renderer".into()]),
+            Err(Error::RendererNotFound)
+        ));
+        #[cfg(unix)]
+        {
+            let output = context.run(&["echo".into(), "<svg/>".into()]).unwrap();
+            assert_eq!(
+                context.output(None, output).unwrap(),
+                Some(SvgPage {
+                    data: b"<svg/>\n".to_vec()
+                })
+            );
+        }
+        #[cfg(unix)]
+        {
+            let options = DocxOptions {
+                page_timeout: Some(std::time::Duration::from_millis(100)),
+                ..DocxOptions::default()
+            };
+            let context = RenderContext {
+                options: &options,
+                ..context
+            };
+            assert!(matches!(
+                context.for_page(7).run(&["sleep".into(), "5".into()]),
+                Err(Error::PageTimeout { page: 7 })
+            ));
+        }
+        // Why `tesseract` was killed, as for the renderers.
+        let cancelled = AtomicBool::new(true);
+        let stopped = RenderContext {
+            cancelled: Some(&cancelled),
+            ..context
+        };
+        assert!(matches!(stopped.stopped(), Error::Interrupted));
+        let past = RenderContext {
+            page_deadline: Some((4, Instant::now())),
+            ..context
+        };
+        assert!(matches!(past.stopped(), Error::PageTimeout { page: 4 }));
+        assert!(matches!(context.stopped(), Error::TimeBudgetExceeded));
+    }
+}
@@ -0,0 +1,221 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Per-page Lua scripts. The script evaluates to a function called with a
+//! table of the [`PageInfo`] fields for every page. It returns `nil` to add
+//! the page unchanged, or a table with any of the [`PageDecision`] fields:
+//!
+//! ```lua
+//! return function(page)
+//!     if page.png_bytes > 5000000 then
+//!         return { raster_only = true, scale = 0.5 }
+//!     end
+//!     return { caption = "Page " .. page.number }
+//! end
+//! ```
+//!
+//! Scripts get the `table`, `string` and `math` libraries, not `os`, `io` or
+//! the functions that read files, and are stopped once they run past the
+//! page timeout or the time budget.
+
+use crate::{DocxOptions, Error, PageDecision, PageInfo, Result};
+use mlua::{Function, HookTriggers, Lua, LuaOptions, RegistryKey, StdLib, Table, Value};
+use std::time::{Duration, Instant};
+
+/// How many Lua instructions run between looks at the clock.
+const INSTRUCTIONS_PER_CHECK: u32 = 10_000;
+
+pub(crate) struct PageScript {
+    lua: Lua,
+    function: RegistryKey,
+    page_timeout: Option<Duration>,
+    time_budget: Option<Duration>,
+}
+
+/// The shorter of two limits, either of which may be missing.
+fn shorter(a: Option<Duration>, b: Option<Duration>) -> Option<Duration> {
+    a.into_iter().chain(b).min()
+}
+
+/// Makes `lua` fail once `limit` passes from now, and returns when that is.
+fn limit_time(lua: &Lua, limit: Option<Duration>) -> Option<Instant> {
+    let deadline = Instant::now() + limit?;
+    lua.set_hook(
+        HookTriggers::new().every_nth_instruction(INSTRUCTIONS_PER_CHECK),
+        move |_, _| match Instant::now() >= deadline {
+            true => Err(mlua::Error::runtime("out of time")),
+            false => Ok(()),
+        },
+    );
+    Some(deadline)
+}
+
+impl PageScript {
+    /// Runs `source`, which must evaluate to the per-page function.
+    pub(crate) fn new(source: &str, options: &DocxOptions) -> Result<PageScript> {
+        let lua = Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::new(),
+        )
+        .map_err(|_| Error::ScriptInvalid)?;
+        // The base library reads files with these.
+        for name in ["dofile", "loadfile"] {
+            lua.globals()
+                .set(name, Value::Nil)
+                .map_err(|_| Error::ScriptInvalid)?;
+        }
+        let limit = shorter(options.page_timeout, options.time_budget);
+        limit_time(&lua, limit);
+        let function: Function = lua.load(source).eval().map_err(|_| Error::ScriptInvalid)?;
+        let function = lua
+            .create_registry_value(function)
+            .map_err(|_| Error::ScriptInvalid)?;
+        Ok(PageScript {
+            lua,
+            function,
+            page_timeout: options.page_timeout,
+            time_budget: options.time_budget,
+        })
+    }
+
+    /// How long a call may run: the page timeout, or the whole time budget
+    /// if that is shorter.
+    fn limit(&self) -> Option<Duration> {
+        shorter(self.page_timeout, self.time_budget)
+    }
+
+    pub(crate) fn decide(&self, info: &PageInfo) -> Result<PageDecision> {
+        let deadline = limit_time(&self.lua, self.limit());
+        self.call(info).map_err(|_| {
+            if deadline.is_none_or(|deadline| Instant::now() < deadline) {
+                Error::ScriptFailed
+            } else if self.limit() == self.page_timeout {
+                Error::PageTimeout { page: info.number }
+            } else {
+                Error::TimeBudgetExceeded
+            }
+        })
+    }
+
+    fn call(&self, info: &PageInfo) -> mlua::Result<PageDecision> {
+        let page = self.lua.create_table()?;
+        page.set("number", info.number)?;
+        page.set("width", info.width)?;
+        page.set("height", info.height)?;
+        page.set("svg_bytes", info.svg_bytes)?;
+        page.set("png_bytes", info.png_bytes)?;
+        let function: Function = self.lua.registry_value(&self.function)?;
+        let decision = match function.call::<_, Option<Table>>(page)? {
+            Some(decision) => decision,
+            None => return Ok(PageDecision::default()),
+        };
+        Ok(PageDecision {
+            skip: decision.get::<_, Option<bool>>("skip")?.unwrap_or(false),
+            caption: decision.get("caption")?,
+            alt_text: decision.get("alt_text")?,
+            raster_only: decision
+                .get::<_, Option<bool>>("raster_only")?
+                .unwrap_or(false),
+            scale: decision.get("scale")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(number: usize) -> PageInfo {
+        PageInfo {
+            number,
+            width: 100.0,
+            height: 200.0,
+            svg_bytes: 10,
+            png_bytes: 20,
+        }
+    }
+
+    #[test]
+    fn test_page_script() {
+        let script = PageScript::new(
+            r#"return function(page)
+                if page.number == 2 then return { skip = true } end
+                if page.number == 3 then return nil end
+                return { caption = "Page " .. page.number .. " of " .. page.width, scale = 0.5 }
+            end"#,
+            &DocxOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            script.decide(&info(1)).unwrap(),
+            PageDecision {
+                caption: Some(String::from("Page 1 of 100.0")),
+                scale: Some(0.5),
+                ..PageDecision::default()
+            }
+        );
+        assert!(script.decide(&info(2)).unwrap().skip);
+        assert_eq!(script.decide(&info(3)).unwrap(), PageDecision::default());
+
+        assert!(matches!(
+            PageScript::new("return 1 +", &DocxOptions::default()),
+            Err(Error::ScriptInvalid)
+        ));
+        let failing = PageScript::new(
+            "return function(page) error('no') end",
+            &DocxOptions::default(),
+        )
+        .unwrap();
+        assert!(matches!(failing.decide(&info(1)), Err(Error::ScriptFailed)));
+    }
+
+    #[test]
+    fn test_page_script_sandbox() {
+        let options = DocxOptions {
+            page_timeout: Some(Duration::from_millis(100)),
+            ..DocxOptions::default()
+        };
+        let script = PageScript::new(
+            "return function(page) return { skip = os ~= nil or io ~= nil or dofile ~= nil } end",
+            &options,
+        )
+        .unwrap();
+        assert!(!script.decide(&info(1)).unwrap().skip);
+
+        let started = Instant::now();
+        let endless =
+            PageScript::new("return function(page) while true do end end", &options).unwrap();
+        assert!(matches!(
+            endless.decide(&info(4)),
+            Err(Error::PageTimeout { page: 4 })
+        ));
+        assert!(matches!(
+            PageScript::new("while true do end", &options),
+            Err(Error::ScriptInvalid)
+        ));
+        let options = DocxOptions {
+            time_budget: Some(Duration::from_millis(50)),
+            ..options
+        };
+        assert!(matches!(
+            PageScript::new("return function(page) while true do end end", &options)
+                .unwrap()
+                .decide(&info(1)),
+            Err(Error::TimeBudgetExceeded)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,158 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::Error;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// Which pages of a PDF [`Docx::convert_pdf_pages`] converts, the first
+/// being page 1. Pages are converted in the order given, until the first one
+/// past the end of the PDF.
+///
+/// [`Docx::convert_pdf_pages`]: crate::Docx::convert_pdf_pages
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum PageSelection {
+    #[default]
+    All,
+    Range(RangeInclusive<usize>),
+    List(Vec<RangeInclusive<usize>>),
+}
+
+static ALL: RangeInclusive<usize> = 1..=usize::MAX;
+
+impl PageSelection {
+    fn ranges(&self) -> &[RangeInclusive<usize>] {
+        match self {
+            PageSelection::All => std::slice::from_ref(&ALL),
+            PageSelection::Range(range) => std::slice::from_ref(range),
+            PageSelection::List(ranges) => ranges,
+        }
+    }
+
+    /// The `n`th page selected, counting from 1.
+    pub fn nth(&self, n: usize) -> Option<usize> {
+        let mut n = n.checked_sub(1)?;
+        for range in self.ranges() {
+            if range.is_empty() {
+                continue;
+            }
+            let len = range.end() - range.start();
+            if n <= len {
+                return Some(range.start() + n);
+            }
+            n -= len + 1;
+        }
+        None
+    }
+
+    /// Where `page` is among the pages selected, counting from 1.
+    pub fn position(&self, page: usize) -> Option<usize> {
+        let mut n: usize = 0;
+        for range in self.ranges() {
+            if range.contains(&page) {
+                return Some(n + (page - range.start()) + 1);
+            }
+            if !range.is_empty() {
+                n = n.saturating_add(range.end() - range.start() + 1);
+            }
+        }
+        None
+    }
+
+    /// How many pages are selected of a PDF with `total` pages.
+    pub fn count(&self, total: usize) -> usize {
+        let mut count = 0;
+        for range in self.ranges() {
+            if *range.start() > total {
+                break;
+            }
+            count += range.clone().take_while(|&page| page <= total).count();
+        }
+        count
+    }
+}
+
+/// Parses a list like `1,3,7-9` or `5-`, with ranges sorted and merged.
+impl FromStr for PageSelection {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let page = |s: &str| match s.trim().parse() {
+            Ok(0) | Err(_) => Err(Error::PageOutOfRange),
+            Ok(page) => Ok(page),
+        };
+        let mut ranges = Vec::new();
+        for item in s.split(',') {
+            let range = match item.split_once('-') {
+                Some((start, end)) if end.trim().is_empty() => page(start)?..=usize::MAX,
+                Some((start, end)) => page(start)?..=page(end)?,
+                None => page(item)?..=page(item)?,
+            };
+            if range.is_empty() {
+                return Err(Error::PageOutOfRange);
+            }
+            ranges.push(range);
+        }
+        ranges.sort_by_key(|range| *range.start());
+        let mut merged: Vec<RangeInclusive<usize>> = Vec::new();
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                    *last = *last.start()..=*last.end().max(range.end());
+                }
+                _ => merged.push(range),
+            }
+        }
+        Ok(match &merged[..] {
+            [range] => PageSelection::Range(range.clone()),
+            _ => PageSelection::List(merged),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_selection() {
+        let selection: PageSelection = "7-9, 1,3,8-10".parse().unwrap();
+        assert_eq!(selection, PageSelection::List(vec![1..=1, 3..=3, 7..=10]));
+        let pages: Vec<_> = (1..=7).map(|n| selection.nth(n)).collect();
+        assert_eq!(
+            pages,
+            [1, 3, 7, 8, 9, 10]
+                .map(Some)
+                .into_iter()
+                .chain([None])
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(selection.count(8), 4);
+        assert_eq!(selection.position(8), Some(4));
+        assert_eq!(selection.position(5), None);
+        assert_eq!(PageSelection::All.position(42), Some(42));
+        assert_eq!(
+            "5-".parse().ok(),
+            Some(PageSelection::Range(5..=usize::MAX))
+        );
+        assert_eq!("5-".parse::<PageSelection>().unwrap().count(20), 16);
+        assert_eq!(PageSelection::All.nth(42), Some(42));
+        assert_eq!(PageSelection::All.count(3), 3);
+        for invalid in ["", "0", "3-1", "a", "1,,2", "-3"] {
+            assert!(invalid.parse::<PageSelection>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,337 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! OPC digital signatures (ECMA-376 Part 2, section 13): an XML-DSig
+//! signature over the digests of every part, stored in `_xmlsignatures`.
+
+use crate::ooxml::{now, utc_timestamp, CONTENT_TYPES, PACKAGE_RELS};
+use crate::package::Package;
+use crate::parts::{self, ContentTypes, Relationship, Relationships, RELATIONSHIPS_NAMESPACE};
+use crate::{Error, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::RsaPrivateKey;
+use sha2::{Digest, Sha256};
+
+const XMLDSIG: &str = "http://www.w3.org/2000/09/xmldsig#";
+const C14N: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+const RELATIONSHIP_TRANSFORM: &str =
+    "http://schemas.openxmlformats.org/package/2006/RelationshipTransform";
+const DIGITAL_SIGNATURE: &str = "http://schemas.openxmlformats.org/package/2006/digital-signature";
+const ORIGIN: &str = "_xmlsignatures/origin.sigs";
+const ORIGIN_RELS: &str = "_xmlsignatures/_rels/origin.sigs.rels";
+const SIGNATURE: &str = "_xmlsignatures/sig1.xml";
+
+/// An RSA private key and the X.509 certificate embedded in the signature so
+/// that recipients can verify it.
+#[derive(Debug, Clone)]
+pub struct SigningIdentity {
+    key: RsaPrivateKey,
+    certificate: Vec<u8>,
+}
+
+impl SigningIdentity {
+    /// Reads a PKCS#8 or PKCS#1 PEM private key and a PEM certificate.
+    pub fn from_pem(key: &str, certificate: &str) -> Result<SigningIdentity> {
+        let key = RsaPrivateKey::from_pkcs8_pem(key)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(key))
+            .map_err(|_| Error::InvalidSigningKey)?;
+        let certificate = pem_body(certificate, "CERTIFICATE").ok_or(Error::InvalidSigningKey)?;
+        Ok(SigningIdentity { key, certificate })
+    }
+}
+
+fn pem_body(pem: &str, label: &str) -> Option<Vec<u8>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let start = pem.find(&begin)? + begin.len();
+    let stop = start + pem[start..].find(&end)?;
+    let body: String = pem[start..stop].split_whitespace().collect();
+    BASE64.decode(body).ok()
+}
+
+fn text(package: &Package, name: &str) -> Result<String> {
+    String::from_utf8(package.get(name)?).map_err(Error::invalid_data)
+}
+
+fn sha256_base64(data: &[u8]) -> String {
+    BASE64.encode(Sha256::digest(data))
+}
+
+/// Serialized in canonical (C14N) form already, so the digests can be taken
+/// over exactly these bytes. `transforms` are the `Transform` elements, if
+/// the digest is of the part transformed.
+fn reference(uri: &str, transforms: &str, digest: &str) -> String {
+    let transforms = match transforms {
+        "" => String::new(),
+        transforms => format!("<Transforms>{}</Transforms>", transforms),
+    };
+    format!(
+        "<Reference URI=\"{}\">{}<DigestMethod Algorithm=\"http://www.w3.org/2001/04/xmlenc#sha256\"></DigestMethod><DigestValue>{}</DigestValue></Reference>",
+        uri, transforms, digest
+    )
+}
+
+/// `value` escaped as C14N writes attribute values.
+fn c14n_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '"' => escaped += "&quot;",
+            '\t' => escaped += "&#x9;",
+            '\n' => escaped += "&#xA;",
+            '\r' => escaped += "&#xD;",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The relationships `signed` as the RelationshipTransform (ECMA-376 Part 2,
+/// 13.2.4.24) followed by C14N leaves them: sorted by Id, with only the Id,
+/// Target, TargetMode and Type attributes, TargetMode written out, and nothing
+/// between the elements. Office checks the digest of this, not of the part.
+fn transform_relationships(signed: &[Relationship]) -> String {
+    let mut signed: Vec<&Relationship> = signed.iter().collect();
+    signed.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut xml = format!("<Relationships xmlns=\"{}\">", RELATIONSHIPS_NAMESPACE);
+    for relationship in signed {
+        let mode = if relationship.external {
+            "External"
+        } else {
+            "Internal"
+        };
+        xml += &format!(
+            "<Relationship Id=\"{}\" Target=\"{}\" TargetMode=\"{}\" Type=\"{}\"></Relationship>",
+            c14n_attribute(&relationship.id),
+            c14n_attribute(&relationship.target),
+            mode,
+            c14n_attribute(&relationship.kind)
+        );
+    }
+    xml + "</Relationships>"
+}
+
+/// The reference to the relationships part `xml` at `uri`, signing all of
+/// them but the one to the signature origin, which later signatures change.
+fn relationships_reference(uri: &str, xml: &str) -> String {
+    let signed: Vec<Relationship> = Relationships::parse(xml)
+        .into_iter()
+        .filter(|relationship| relationship.kind != parts::SIGNATURE_ORIGIN)
+        .collect();
+    let mut transforms = format!("<Transform Algorithm=\"{}\">", RELATIONSHIP_TRANSFORM);
+    for relationship in &signed {
+        transforms += &format!(
+            "<mdssi:RelationshipReference xmlns:mdssi=\"{}\" SourceId=\"{}\"></mdssi:RelationshipReference>",
+            DIGITAL_SIGNATURE,
+            c14n_attribute(&relationship.id)
+        );
+    }
+    transforms += &format!("</Transform><Transform Algorithm=\"{}\"></Transform>", C14N);
+    let digest = sha256_base64(transform_relationships(&signed).as_bytes());
+    reference(uri, &transforms, &digest)
+}
+
+/// Adds the signature origin, its relationships and the signature itself.
+/// Must run after every other part of the package has been written.
+pub(crate) fn sign(
+    package: &mut Package,
+    identity: &SigningIdentity,
+    scrub_metadata: bool,
+) -> Result<()> {
+    let mut types = ContentTypes::parse(&text(package, CONTENT_TYPES)?);
+    types.add_default(
+        "sigs",
+        "application/vnd.openxmlformats-package.digital-signature-origin",
+    );
+    types.add_override(
+        SIGNATURE,
+        "application/vnd.openxmlformats-package.digital-signature-xmlsignature+xml",
+    );
+    package.put(CONTENT_TYPES, types.to_xml().into_bytes())?;
+    let mut package_rels = Relationships::parse(&text(package, PACKAGE_RELS)?);
+    package_rels.add(Relationship::part(
+        "rIdSignatureOrigin",
+        parts::SIGNATURE_ORIGIN,
+        ORIGIN,
+    ));
+    package.put(PACKAGE_RELS, package_rels.to_xml().into_bytes())?;
+    package.put(ORIGIN, Vec::new())?;
+    let mut origin_rels = Relationships::new();
+    origin_rels.push(Relationship::part("rId1", parts::SIGNATURE, "sig1.xml"));
+    package.put(ORIGIN_RELS, origin_rels.to_xml().into_bytes())?;
+
+    let mut manifest = String::new();
+    for name in package.names()? {
+        if name == CONTENT_TYPES || name.starts_with("_xmlsignatures/") {
+            continue;
+        }
+        let content_type = types
+            .get(&name)
+            .ok_or_else(|| Error::invalid_data(format!("{} has no content type", name)))?;
+        let uri = format!("/{}?ContentType={}", name, content_type);
+        manifest += &if name.ends_with(".rels") {
+            relationships_reference(&uri, &text(package, &name)?)
+        } else {
+            reference(&uri, "", &sha256_base64(&package.get(&name)?))
+        };
+    }
+    let secs = if scrub_metadata {
+        // The zip epoch, matching the entry timestamps.
+        315532800
+    } else {
+        now()
+    };
+    let object_body = format!(
+        "<Manifest>{}</Manifest><SignatureProperties><SignatureProperty Id=\"idSignatureTime\" Target=\"#idPackageSignature\"><mdssi:SignatureTime xmlns:mdssi=\"{}\"><mdssi:Format>YYYY-MM-DDThh:mm:ssTZD</mdssi:Format><mdssi:Value>{}</mdssi:Value></mdssi:SignatureTime></SignatureProperty></SignatureProperties>",
+        manifest,
+        DIGITAL_SIGNATURE,
+        utc_timestamp(secs)
+    );
+    // The canonical form of an element carries the namespace it inherits
+    // from <Signature>.
+    let object_digest = sha256_base64(
+        format!(
+            "<Object xmlns=\"{}\" Id=\"idPackageObject\">{}</Object>",
+            XMLDSIG, object_body
+        )
+        .as_bytes(),
+    );
+    let signed_info_body = format!(
+        "<CanonicalizationMethod Algorithm=\"{}\"></CanonicalizationMethod><SignatureMethod Algorithm=\"http://www.w3.org/2001/04/xmldsig-more#rsa-sha256\"></SignatureMethod><Reference Type=\"http://www.w3.org/2000/09/xmldsig#Object\" URI=\"#idPackageObject\"><DigestMethod Algorithm=\"http://www.w3.org/2001/04/xmlenc#sha256\"></DigestMethod><DigestValue>{}</DigestValue></Reference>",
+        C14N, object_digest
+    );
+    let signed_info = format!(
+        "<SignedInfo xmlns=\"{}\">{}</SignedInfo>",
+        XMLDSIG, signed_info_body
+    );
+    let signature = SigningKey::<Sha256>::new(identity.key.clone()).sign(signed_info.as_bytes());
+    let xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Signature xmlns=\"{}\" Id=\"idPackageSignature\"><SignedInfo>{}</SignedInfo><SignatureValue>{}</SignatureValue><KeyInfo><X509Data><X509Certificate>{}</X509Certificate></X509Data></KeyInfo><Object Id=\"idPackageObject\">{}</Object></Signature>",
+        XMLDSIG,
+        signed_info_body,
+        BASE64.encode(signature.to_bytes()),
+        BASE64.encode(&identity.certificate),
+        object_body
+    );
+    package.put(SIGNATURE, xml.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+    use rsa::pkcs1::EncodeRsaPublicKey;
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::signature::Verifier;
+
+    fn between<'a>(xml: &'a str, start: &str, end: &str) -> &'a str {
+        let from = xml.find(start).unwrap() + start.len();
+        &xml[from..from + xml[from..].find(end).unwrap()]
+    }
+
+    #[test]
+    fn test_transform_relationships() {
+        let relationships = [
+            Relationship::external(
+                "rId2",
+                "http://example.com/link",
+                "https://a.test/?x=1&y=\"2\"",
+            ),
+            Relationship::part("rId10", "http://example.com/image", "media/1.png"),
+        ];
+        assert_eq!(
+            transform_relationships(&relationships),
+            format!(
+                "<Relationships xmlns=\"{}\">\
+                 <Relationship Id=\"rId10\" Target=\"media/1.png\" TargetMode=\"Internal\" Type=\"http://example.com/image\"></Relationship>\
+                 <Relationship Id=\"rId2\" Target=\"https://a.test/?x=1&amp;y=&quot;2&quot;\" TargetMode=\"External\" Type=\"http://example.com/link\"></Relationship>\
+                 </Relationships>",
+                RELATIONSHIPS_NAMESPACE
+            )
+        );
+    }
+
+    #[test]
+    fn test_sign() {
+        let key = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/signing.key"));
+        let certificate = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/signing.crt"));
+        let identity = SigningIdentity::from_pem(key, certificate).unwrap();
+        let key = identity.key.clone();
+        // The certificate is of the key that signs.
+        let public_key = key.to_public_key().to_pkcs1_der().unwrap();
+        assert!(identity
+            .certificate
+            .windows(public_key.as_bytes().len())
+            .any(|window| window == public_key.as_bytes()));
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let mut package = Package::new(&options).unwrap();
+        sign(&mut package, &identity, true).unwrap();
+
+        let xml = String::from_utf8(package.get(SIGNATURE).unwrap()).unwrap();
+        let document = package.get("word/document.xml").unwrap();
+        assert!(xml.contains(&sha256_base64(&document)));
+        let object = format!(
+            "<Object xmlns=\"{}\" Id=\"idPackageObject\">{}</Object>",
+            XMLDSIG,
+            between(&xml, "<Object Id=\"idPackageObject\">", "</Object>")
+        );
+        assert!(xml.contains(&sha256_base64(object.as_bytes())));
+        let signed_info = format!(
+            "<SignedInfo xmlns=\"{}\">{}</SignedInfo>",
+            XMLDSIG,
+            between(&xml, "<SignedInfo>", "</SignedInfo>")
+        );
+        let signature = BASE64
+            .decode(between(&xml, "<SignatureValue>", "</SignatureValue>"))
+            .unwrap();
+        VerifyingKey::<Sha256>::new(key.to_public_key())
+            .verify(
+                signed_info.as_bytes(),
+                &Signature::try_from(&signature[..]).unwrap(),
+            )
+            .unwrap();
+        let rels = String::from_utf8(package.get(PACKAGE_RELS).unwrap()).unwrap();
+        assert!(rels.contains("digital-signature/origin"));
+        assert!(xml.contains(&BASE64.encode(&identity.certificate)));
+
+        // Relationships are signed through the transform, leaving out the
+        // signature origin.
+        let reference = between(&xml, "<Reference URI=\"/_rels/.rels?", "</Reference>");
+        assert!(reference.contains(RELATIONSHIP_TRANSFORM));
+        assert!(!reference.contains("rIdSignatureOrigin"));
+        let signed: Vec<Relationship> = Relationships::parse(&rels)
+            .into_iter()
+            .filter(|relationship| relationship.id != "rIdSignatureOrigin")
+            .collect();
+        assert!(!signed.is_empty());
+        let digest = sha256_base64(transform_relationships(&signed).as_bytes());
+        assert_eq!(
+            between(reference, "<DigestValue>", "</DigestValue>"),
+            digest
+        );
+    }
+}
@@ -0,0 +1,190 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! `{{name}}` placeholders in the templated parts of the package. Each part
+//! has a few built-in placeholders filled by the converter; any other name
+//! must be one of the user's template variables.
+
+use crate::ooxml::{escape, DOCUMENT, DOCUMENT_RELS};
+use crate::package::Package;
+use crate::{Error, Result};
+use std::collections::BTreeMap;
+
+/// A templated part, its built-in placeholders and the ones it must contain.
+struct TemplatedPart {
+    name: &'static str,
+    builtins: &'static [&'static str],
+    required: &'static [&'static str],
+}
+
+const PARTS: [TemplatedPart; 2] = [
+    TemplatedPart {
+        name: DOCUMENT,
+        builtins: &[
+            "pages",
+            "page_width",
+            "page_height",
+            "page_orient",
+            "margin_top",
+            "margin_right",
+            "margin_bottom",
+            "margin_left",
+        ],
+        required: &["pages"],
+    },
+    // The relationships of the pages are added to those of the part, so
+    // the placeholder of older templates is left empty.
+    TemplatedPart {
+        name: DOCUMENT_RELS,
+        builtins: &["relationships"],
+        required: &[],
+    },
+];
+
+/// The placeholder names in `text`, in order, or the offset of a `{{` that is
+/// never closed.
+fn placeholders(text: &str) -> std::result::Result<Vec<&str>, usize> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or(text.len() - rest.len() + start)?;
+        names.push(after[..end].trim());
+        rest = &after[end + 2..];
+    }
+    Ok(names)
+}
+
+/// Everything wrong with the templated parts of `package`, as messages for
+/// the user. Empty when the template is usable.
+pub(crate) fn problems(package: &Package, variables: &BTreeMap<String, String>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for part in &PARTS {
+        let text = match package.get(part.name).map(String::from_utf8) {
+            Ok(Ok(text)) => text,
+            _ => {
+                problems.push(format!("{} is missing or not UTF-8", part.name));
+                continue;
+            }
+        };
+        let names = match placeholders(&text) {
+            Ok(names) => names,
+            Err(offset) => {
+                problems.push(format!("{}: unclosed {{{{ at byte {}", part.name, offset));
+                continue;
+            }
+        };
+        for required in part.required {
+            match names.iter().filter(|&name| name == required).count() {
+                0 => problems.push(format!("{}: missing {{{{{}}}}}", part.name, required)),
+                1 => {}
+                _ => problems.push(format!(
+                    "{}: {{{{{}}}}} may appear only once",
+                    part.name, required
+                )),
+            }
+        }
+        let mut unknown = Vec::new();
+        for name in names {
+            if !part.builtins.contains(&name)
+                && !variables.contains_key(name)
+                && !unknown.contains(&name)
+            {
+                unknown.push(name);
+                problems.push(format!(
+                    "{}: unknown placeholder {{{{{}}}}}; pass it as a template variable",
+                    part.name, name
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Replaces the placeholders in `text` with the built-in values as they are
+/// and the user's variables XML-escaped.
+pub(crate) fn fill(
+    text: &str,
+    builtins: &[(&str, &str)],
+    variables: &BTreeMap<String, String>,
+) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out += &rest[..start];
+        let after = &rest[start + 2..];
+        let end = after.find("}}").ok_or(Error::TemplateInvalid)?;
+        let name = after[..end].trim();
+        match builtins.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, value)) => out += value,
+            None => {
+                let value = variables.get(name).ok_or(Error::TemplateInvalid)?;
+                out += &escape(value);
+            }
+        }
+        rest = &after[end + 2..];
+    }
+    out += rest;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DocxOptions;
+
+    #[test]
+    fn test_fill() {
+        let variables = BTreeMap::from([(String::from("title"), String::from("A & B"))]);
+        assert_eq!(
+            fill("<t>{{ title }}</t>{{pages}}", &[("pages", "<p/>")], &variables).unwrap(),
+            "<t>A &amp; B</t><p/>"
+        );
+        assert!(matches!(
+            fill("{{author}}", &[], &variables),
+            Err(Error::TemplateInvalid)
+        ));
+    }
+
+    #[test]
+    fn test_problems() {
+        let options = DocxOptions {
+            in_memory: true,
+            ..DocxOptions::default()
+        };
+        let mut package = Package::new(&options).unwrap();
+        assert!(problems(&package, &BTreeMap::new()).is_empty());
+        package
+            .put(DOCUMENT, b"<w:body>{{pages}}{{pages}}{{title}}{{x</w:body>".to_vec())
+            .unwrap();
+        assert_eq!(
+            problems(&package, &BTreeMap::new()),
+            ["word/document.xml: unclosed {{ at byte 35"]
+        );
+        package
+            .put(DOCUMENT, b"<w:body>{{pages}}{{pages}}{{title}}</w:body>".to_vec())
+            .unwrap();
+        assert_eq!(
+            problems(&package, &BTreeMap::new()),
+            [
+                "word/document.xml: {{pages}} may appear only once",
+                "word/document.xml: unknown placeholder {{title}}; pass it as a template variable"
+            ]
+        );
+    }
+}
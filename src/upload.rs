@@ -0,0 +1,222 @@
+/* This file is part of docx-you-want.
+
+   docx-you-want is free software: you can redistribute it and/or modify
+   it under the terms of the GNU General Public License as published by
+   the Free Software Foundation, either version 3 of the License, or
+   (at your option) any later version.
+
+   docx-you-want is distributed in the hope that it will be useful,
+   but WITHOUT ANY WARRANTY; without even the implied warranty of
+   MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+   GNU General Public License for more details.
+
+   You should have received a copy of the GNU General Public License
+   along with docx-you-want.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+//! Uploading the finished document to a WebDAV server or a SharePoint
+//! document library with an HTTP `PUT`.
+
+use crate::{Error, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use std::fs::File;
+use std::path::Path;
+use tracing::warn;
+
+/// Where and as whom to upload.
+///
+/// `webdav://` URLs are uploaded over HTTP and `webdavs://` URLs over HTTPS;
+/// `http://` and `https://` URLs are used as they are. A URL ending in `/`
+/// names a folder, and the file keeps its name in it.
+///
+/// Credentials come from the environment: `DYW_UPLOAD_TOKEN` is sent as a
+/// bearer token (SharePoint), otherwise `DYW_UPLOAD_USER` and
+/// `DYW_UPLOAD_PASSWORD` are sent with basic authentication.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Upload {
+    url: String,
+    authorization: Option<String>,
+}
+
+fn http_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    let scheme = match scheme {
+        "webdav" | "http" => "http",
+        "webdavs" | "https" => "https",
+        _ => return None,
+    };
+    (!rest.is_empty() && !rest.starts_with('/')).then(|| format!("{}://{}", scheme, rest))
+}
+
+/// The MIME type of `file`, one of the formats documents are written in.
+fn content_type(file: &Path) -> &'static str {
+    let extension = file.extension().map(|ext| ext.to_ascii_lowercase());
+    match extension.as_ref().and_then(|ext| ext.to_str()) {
+        Some("docx") => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        Some("pptx") => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        Some("odt") => "application/vnd.oasis.opendocument.text",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Percent-encodes everything but the unreserved characters.
+fn encode(name: &str) -> String {
+    name.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+impl Upload {
+    /// Checks `url` and reads the credentials, so that a typo is reported
+    /// before the conversion rather than after.
+    pub fn from_env(url: &str) -> Result<Upload> {
+        let var = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let authorization = match (
+            var("DYW_UPLOAD_TOKEN"),
+            var("DYW_UPLOAD_USER"),
+            var("DYW_UPLOAD_PASSWORD"),
+        ) {
+            (Some(token), _, _) => Some(format!("Bearer {}", token)),
+            (None, Some(user), password) => {
+                let credentials = format!("{}:{}", user, password.unwrap_or_default());
+                Some(format!("Basic {}", BASE64.encode(credentials)))
+            }
+            (None, None, _) => None,
+        };
+        Upload::new(url, authorization)
+    }
+
+    fn new(url: &str, authorization: Option<String>) -> Result<Upload> {
+        Ok(Upload {
+            url: http_url(url).ok_or(Error::UploadInvalid)?,
+            authorization,
+        })
+    }
+
+    /// The URL `file` is uploaded to.
+    fn target(&self, file: &Path) -> Result<String> {
+        if !self.url.ends_with('/') {
+            return Ok(self.url.clone());
+        }
+        let name = file.file_name().ok_or(Error::UploadInvalid)?;
+        Ok(format!("{}{}", self.url, encode(&name.to_string_lossy())))
+    }
+
+    /// Uploads `file`, replacing whatever is at the URL, as the type its
+    /// extension names.
+    pub fn put(&self, file: &Path) -> Result<()> {
+        let target = self.target(file)?;
+        let data = File::open(file)?;
+        let length = data.metadata()?.len();
+        let mut request = ureq::put(&target)
+            .set("Content-Type", content_type(file))
+            .set("Content-Length", &length.to_string());
+        if let Some(authorization) = &self.authorization {
+            request = request.set("Authorization", authorization);
+        }
+        match request.send(data) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, response)) => {
+                warn!(url = %target, status, reason = response.status_text(), "upload refused");
+                Err(Error::UploadFailed)
+            }
+            Err(e) => {
+                warn!(url = %target, error = %e, "upload failed");
+                Err(Error::UploadFailed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_url() {
+        let upload = Upload::new("webdavs://example.com/Shared%20Documents/", None).unwrap();
+        assert_eq!(
+            upload.target(Path::new("/tmp/Q3 report.docx")).unwrap(),
+            "https://example.com/Shared%20Documents/Q3%20report.docx"
+        );
+        let upload = Upload::new("webdav://nas:8080/a.docx", None).unwrap();
+        assert_eq!(
+            upload.target(Path::new("b.docx")).unwrap(),
+            "http://nas:8080/a.docx"
+        );
+        assert!(matches!(
+            Upload::new("ftp://example.com/", None),
+            Err(Error::UploadInvalid)
+        ));
+        assert!(matches!(
+            Upload::new("webdav:///a.docx", None),
+            Err(Error::UploadInvalid)
+        ));
+    }
+
+    #[test]
+    fn test_put() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("webdav://{}/docs/", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut statuses = [201, 403].into_iter();
+            let mut requests = Vec::new();
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let lower = line.to_ascii_lowercase();
+                    if let Some(value) = lower.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                    head += &line;
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                requests.push((head, body));
+                let status = statuses.next().unwrap();
+                write!(
+                    stream,
+                    "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                )
+                .unwrap();
+            }
+            requests
+        });
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("out.docx");
+        std::fs::write(&file, "PK").unwrap();
+        let slides = dir.path().join("out.pptx");
+        std::fs::write(&slides, "PK").unwrap();
+        let upload = Upload::new(&url, Some(String::from("Bearer t0ken"))).unwrap();
+        upload.put(&file).unwrap();
+        assert!(matches!(upload.put(&slides), Err(Error::UploadFailed)));
+
+        let requests = server.join().unwrap();
+        let (head, body) = &requests[0];
+        assert!(head.starts_with("PUT /docs/out.docx HTTP/1.1\r\n"));
+        assert!(head.contains("Authorization: Bearer t0ken\r\n"));
+        assert!(head.contains("wordprocessingml.document\r\n"));
+        assert_eq!(body, b"PK");
+        let (head, _) = &requests[1];
+        assert!(head.starts_with("PUT /docs/out.pptx HTTP/1.1\r\n"));
+        assert!(head.contains("presentationml.presentation\r\n"));
+    }
+}